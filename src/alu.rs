@@ -1,65 +1,225 @@
 use crate::gates::*;
-use Signal::*;
+use crate::arithmetic::multiplier;
+use crate::shifter::{shift_left, shift_right_arithmetic};
 
-pub fn half_adder(in1: Signal, in2: Signal) -> (Signal, Signal) {
+pub fn half_adder<S: Logic>(in1: S, in2: S) -> (S, S) {
   (xor(in1, in2), and(in1, in2))
 }
 
 
-pub fn full_adder(in1: Signal, in2: Signal, carry: Signal) -> (Signal, Signal) {
+pub fn full_adder<S: Logic>(in1: S, in2: S, carry: S) -> (S, S) {
   let (s1, c1) = half_adder(in1, in2);
   let (s2, c2) = half_adder(carry, s1);
   (s2, or(c1, c2))
 }
 
 
-pub fn n_adder<const BITS: usize>(in1: Signals<BITS>, in2: Signals<BITS>) -> Signals<BITS> {
-  let mut carry = Low;
-  let mut added: [Signal; BITS] = [Signal::Low; BITS];
+pub fn n_adder<const BITS: usize, S: Logic>(in1: Signals<BITS, S>, in2: Signals<BITS, S>) -> Signals<BITS, S> {
+  n_adder_carry(in1, in2, S::LOW).0
+}
+
+// Ripple adder with a carry in and out, to chain words for multi-word arithmetic
+pub fn n_adder_carry<const BITS: usize, S: Logic>(in1: Signals<BITS, S>, in2: Signals<BITS, S>, mut carry: S) -> (Signals<BITS, S>, S) {
+  let mut added: [S; BITS] = [S::LOW; BITS];
   for (i, (bit1, bit2)) in in1.iter().zip(in2.iter()).enumerate().rev() {
       let (sum, carry_tmp) = full_adder(*bit1, *bit2, carry);
       carry = carry_tmp;
       added[i] = sum
   }
+  (added, carry)
+}
+
+// Width of the generate/propagate groups of cla_adder and of the blocks of carry_select_adder
+pub const GROUP: usize = 4;
+
+fn and_all<S: Logic>(in1: &[S]) -> S {
+  in1.iter().fold(S::HIGH, |acc, &s| and(acc, s))
+}
+
+fn or_all<S: Logic>(in1: &[S]) -> S {
+  in1.iter().fold(S::LOW, |acc, &s| or(acc, s))
+}
+
+// Carry out of bits start..end (lsb first) computed in two levels from the generate and
+// propagate signals instead of rippling through every bit
+fn lookahead<S: Logic>(generate: &[S], propagate: &[S], start: usize, end: usize, carry: S) -> S {
+  let mut terms = vec![];
+  for m in start..end {
+    let mut term = vec![generate[m]];
+    term.extend_from_slice(&propagate[m + 1..end]);
+    terms.push(and_all(&term));
+  }
+  let group_generate = or_all(&terms);
+  let group_propagate = and_all(&propagate[start..end]);
+  or(group_generate, and(group_propagate, carry))
+}
+
+pub fn cla_adder<const BITS: usize, S: Logic>(in1: Signals<BITS, S>, in2: Signals<BITS, S>) -> Signals<BITS, S> {
+  let mut generate = [S::LOW; BITS];
+  let mut propagate = [S::LOW; BITS];
+  for j in 0..BITS {
+    generate[j] = and(in1[BITS - 1 - j], in2[BITS - 1 - j]);
+    propagate[j] = xor(in1[BITS - 1 - j], in2[BITS - 1 - j]);
+  }
+  let mut added: [S; BITS] = [S::LOW; BITS];
+  let mut carry = S::LOW;
+  for start in (0..BITS).step_by(GROUP) {
+    let end = (start + GROUP).min(BITS);
+    for j in start..end {
+      added[BITS - 1 - j] = xor(propagate[j], lookahead(&generate, &propagate, start, j, carry));
+    }
+    carry = lookahead(&generate, &propagate, start, end, carry);
+  }
+  added
+}
+
+// Sums (lsb first) and carry out of bits start..end, rippling from carry
+fn ripple<const BITS: usize, S: Logic>(in1: Signals<BITS, S>, in2: Signals<BITS, S>, start: usize, end: usize, mut carry: S) -> (Vec<S>, S) {
+  let mut added = vec![];
+  for j in start..end {
+    let (sum, carry_tmp) = full_adder(in1[BITS - 1 - j], in2[BITS - 1 - j], carry);
+    carry = carry_tmp;
+    added.push(sum);
+  }
+  (added, carry)
+}
+
+pub fn carry_select_adder<const BITS: usize, S: Logic>(in1: Signals<BITS, S>, in2: Signals<BITS, S>) -> Signals<BITS, S> {
+  let mut added: [S; BITS] = [S::LOW; BITS];
+  let (first, mut carry) = ripple(in1, in2, 0, GROUP.min(BITS), S::LOW);
+  for (j, &sum) in first.iter().enumerate() {
+    added[BITS - 1 - j] = sum;
+  }
+  for start in (GROUP..BITS).step_by(GROUP) {
+    let end = (start + GROUP).min(BITS);
+    let (sum0, carry0) = ripple(in1, in2, start, end, S::LOW);
+    let (sum1, carry1) = ripple(in1, in2, start, end, S::HIGH);
+    for j in start..end {
+      added[BITS - 1 - j] = mux(sum0[j - start], sum1[j - start], carry);
+    }
+    carry = mux(carry0, carry1, carry);
+  }
   added
 }
 
-pub fn n_incrementor<const BITS: usize>(in1: Signals<BITS>) -> Signals<BITS> {
-  let mut carry = High;
-  let mut added: [Signal; BITS] = [Signal::Low; BITS];
+pub fn n_incrementor<const BITS: usize, S: Logic>(in1: Signals<BITS, S>) -> Signals<BITS, S> {
+  let mut carry = S::HIGH;
+  let mut added: [S; BITS] = [S::LOW; BITS];
   for (i, bit1) in in1.iter().enumerate().rev() {
-      let (sum, carry_tmp) = full_adder(*bit1, Low, carry);
+      let (sum, carry_tmp) = full_adder(*bit1, S::LOW, carry);
       carry = carry_tmp;
       added[i] = sum
   }
   added
 }
 
-pub fn alu<const BITS: usize>(
-  bits1: Signals<BITS>,
-  bits2: Signals<BITS>,
-  zx: Signal,
-  nx: Signal,
-  zy: Signal,
-  ny: Signal,
-  f: Signal,
-  no: Signal
-) -> (Signals<BITS>, Signal, Signal) {
-  let bits1 = mux_n(bits1, [Low; BITS], zx);
+pub fn alu<const BITS: usize, S: Logic>(
+  bits1: Signals<BITS, S>,
+  bits2: Signals<BITS, S>,
+  zx: S,
+  nx: S,
+  zy: S,
+  ny: S,
+  f: S,
+  no: S
+) -> (Signals<BITS, S>, S, S) {
+  alu_with(n_adder, bits1, bits2, zx, nx, zy, ny, f, no)
+}
+
+// Same alu with any of n_adder, cla_adder or carry_select_adder for its f = 1 branch
+pub fn alu_with<const BITS: usize, S: Logic>(
+  adder: fn(Signals<BITS, S>, Signals<BITS, S>) -> Signals<BITS, S>,
+  bits1: Signals<BITS, S>,
+  bits2: Signals<BITS, S>,
+  zx: S,
+  nx: S,
+  zy: S,
+  ny: S,
+  f: S,
+  no: S
+) -> (Signals<BITS, S>, S, S) {
+  let bits1 = mux_n(bits1, [S::LOW; BITS], zx);
   let bits1 = mux_n(bits1, not_n(bits1), nx);
-  let bits2 = mux_n(bits2, [Low; BITS], zy);
+  let bits2 = mux_n(bits2, [S::LOW; BITS], zy);
   let bits2 = mux_n(bits2, not_n(bits2), ny);
-  let out = mux_n(and_n(bits1, bits2), n_adder(bits1, bits2), f);
+  let out = mux_n(and_n(bits1, bits2), adder(bits1, bits2), f);
   let out = mux_n(out, not_n(out), no);
   let zr = not(or_m_way(out));
   let ng = out[0];
   (out, zr, ng)
 }
 
+// alu with two more status flags, both Low when f selects the and:
+// carry is the carry out of the adder and overflow is set when the signed addition of
+// the preprocessed inputs overflows. They describe the addition before no is applied.
+pub fn alu_flags<const BITS: usize, S: Logic>(
+  bits1: Signals<BITS, S>,
+  bits2: Signals<BITS, S>,
+  zx: S,
+  nx: S,
+  zy: S,
+  ny: S,
+  f: S,
+  no: S
+) -> (Signals<BITS, S>, S, S, S, S) {
+  let bits1 = mux_n(bits1, [S::LOW; BITS], zx);
+  let bits1 = mux_n(bits1, not_n(bits1), nx);
+  let bits2 = mux_n(bits2, [S::LOW; BITS], zy);
+  let bits2 = mux_n(bits2, not_n(bits2), ny);
+  let (added, carry) = n_adder_carry(bits1, bits2, S::LOW);
+  let out = mux_n(and_n(bits1, bits2), added, f);
+  let out = mux_n(out, not_n(out), no);
+  let zr = not(or_m_way(out));
+  let ng = out[0];
+  let overflow = and(not(xor(bits1[0], bits2[0])), xor(bits1[0], added[0]));
+  (out, zr, ng, and(f, carry), and(f, overflow))
+}
+
+// alu of the extended Hack instructions, selected by the two bits x1 x0 (bits 14 and 13 of a
+// C-instruction) that the standard cpu ignores:
+//   1 1 and 1 0: the standard alu
+//   0 1: shift by one bit, zx selects bits1 (Low) or bits2 (High), nx left (Low) or arithmetic right (High)
+//   0 0: low word of bits1 * bits2
+// carry and overflow are those of alu_flags for the standard alu and Low for the other units.
+pub fn extended_alu<const BITS: usize, S: Logic>(
+  bits1: Signals<BITS, S>,
+  bits2: Signals<BITS, S>,
+  x1: S,
+  x0: S,
+  zx: S,
+  nx: S,
+  zy: S,
+  ny: S,
+  f: S,
+  no: S
+) -> (Signals<BITS, S>, S, S, S, S) {
+  let (standard, _, _, carry, overflow) = alu_flags(bits1, bits2, zx, nx, zy, ny, f, no);
+  let operand = mux_n(bits1, bits2, zx);
+  let shifted = mux_n(shift_left(operand, [S::HIGH]), shift_right_arithmetic(operand, [S::HIGH]), nx);
+  let (_, product) = multiplier(bits1, bits2);
+  let out = mux_4_way_n(product, shifted, standard, standard, x0, x1);
+  let zr = not(or_m_way(out));
+  let ng = out[0];
+  (out, zr, ng, and(x1, carry), and(x1, overflow))
+}
+
+// Jump condition of an extended C-instruction from its unit x1 x0 and jump bits j2 j1 j0.
+// Units 11, 01 and 00 jump on the output as the standard cpu (j2 out < 0, j1 out = 0,
+// j0 out > 0), unit 10 on the carry and overflow of the flags register (j2 carry,
+// j1 overflow, j0 no carry).
+pub fn extended_jump<S: Logic>(x1: S, x0: S, j2: S, j1: S, j0: S, zr: S, ng: S, carry: S, overflow: S) -> S {
+  let output = or(or(and(j2, ng), and(j1, zr)), and(j0, not(or(zr, ng))));
+  let flags = or(or(and(j2, carry), and(j1, overflow)), and(j0, not(carry)));
+  mux(output, flags, and(x1, not(x0)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use Signal::*;
     use crate::utilities::*;  
+    use crate::bits::bits;
+    use std::convert::TryInto;
     #[test]
     fn thalf_adder() {
         assert_eq!(half_adder(Low, Low), (Low, Low));
@@ -117,6 +277,128 @@ mod tests {
         ), ([Low, High, Low, Low]));
     }
 
+    #[test]
+    fn tcla_adder() {
+        for a in 0..=255 {
+            for b in 0..=255 {
+                let (a, b) = (int_to_binary16(a), int_to_binary16(b));
+                let (a, b): (Signals<8>, Signals<8>) = (a[8..].try_into().unwrap(), b[8..].try_into().unwrap());
+                assert_eq!(cla_adder(a, b), n_adder(a, b));
+            }
+        }
+        for a in 0..32 {
+            for b in 0..32 {
+                let (a, b) = (int_to_binary16(a), int_to_binary16(b));
+                let (a, b): (Signals<5>, Signals<5>) = (a[11..].try_into().unwrap(), b[11..].try_into().unwrap());
+                assert_eq!(cla_adder(a, b), n_adder(a, b));
+            }
+        }
+    }
+    #[test]
+    fn tcarry_select_adder() {
+        for a in 0..=255 {
+            for b in 0..=255 {
+                let (a, b) = (int_to_binary16(a), int_to_binary16(b));
+                let (a, b): (Signals<8>, Signals<8>) = (a[8..].try_into().unwrap(), b[8..].try_into().unwrap());
+                assert_eq!(carry_select_adder(a, b), n_adder(a, b));
+            }
+        }
+        for a in 0..32 {
+            for b in 0..32 {
+                let (a, b) = (int_to_binary16(a), int_to_binary16(b));
+                let (a, b): (Signals<5>, Signals<5>) = (a[11..].try_into().unwrap(), b[11..].try_into().unwrap());
+                assert_eq!(carry_select_adder(a, b), n_adder(a, b));
+            }
+        }
+    }
+    #[test]
+    fn talu_with() {
+        for &(a, b) in [(0, 0), (1, -1), (5, -9), (1234, 4321), (-32768, 32767), (-1, -1)].iter() {
+            let (a, b) = (int_to_binary16(a), int_to_binary16(b));
+            for &f in [Low, High].iter() {
+                for &no in [Low, High].iter() {
+                    let expected = alu(a, b, Low, no, Low, Low, f, no);
+                    assert_eq!(alu_with(cla_adder, a, b, Low, no, Low, Low, f, no), expected);
+                    assert_eq!(alu_with(carry_select_adder, a, b, Low, no, Low, Low, f, no), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tn_adder_carry() {
+        assert_eq!(n_adder_carry(bits::<2>("11"), bits("01"), Low), (bits("00"), High));
+        assert_eq!(n_adder_carry(bits::<2>("10"), bits("01"), Low), (bits("11"), Low));
+        assert_eq!(n_adder_carry(bits::<2>("10"), bits("01"), High), (bits("00"), High));
+        assert_eq!(n_adder_carry(bits::<2>("00"), bits("00"), High), (bits("01"), Low));
+    }
+    #[test]
+    fn tmulti_word() {
+        for &(a, b) in [(0i32, 0i32), (65535, 1), (123456, 654321), (-1, 1), (-70000, 3), (i32::MAX, 1)].iter() {
+            let (a_high, a_low) = (int_to_binary16((a >> 16) as i16), int_to_binary16(a as i16));
+            let (b_high, b_low) = (int_to_binary16((b >> 16) as i16), int_to_binary16(b as i16));
+            let (low, carry) = n_adder_carry(a_low, b_low, Low);
+            let (high, _) = n_adder_carry(a_high, b_high, carry);
+            let sum = a.wrapping_add(b);
+            assert_eq!(low, int_to_binary16(sum as i16));
+            assert_eq!(high, int_to_binary16((sum >> 16) as i16));
+        }
+    }
+    #[test]
+    fn talu_flags() {
+        for a in -8..8 {
+            for b in -8..8 {
+                let (x, y): (Signals<4>, Signals<4>) = (int_to_binary16(a)[12..].try_into().unwrap(), int_to_binary16(b)[12..].try_into().unwrap());
+                let (out, zr, ng, carry, overflow) = alu_flags(x, y, Low, Low, Low, Low, High, Low);
+                assert_eq!((out, zr, ng), alu(x, y, Low, Low, Low, Low, High, Low));
+                let unsigned = (a & 15) + (b & 15);
+                assert_eq!(carry == High, unsigned > 15);
+                assert_eq!(overflow == High, a + b > 7 || a + b < -8);
+                let (_, _, _, carry, overflow) = alu_flags(x, y, Low, Low, Low, Low, Low, Low);
+                assert_eq!((carry, overflow), (Low, Low));
+            }
+        }
+        let (out, _, ng, carry, overflow) = alu_flags(int_to_binary16(32767), int_to_binary16(1), Low, Low, Low, Low, High, Low);
+        assert_eq!((out, ng, carry, overflow), (int_to_binary16(-32768), High, Low, High));
+        let (out, zr, _, carry, overflow) = alu_flags(int_to_binary16(-1), int_to_binary16(1), Low, Low, Low, Low, High, Low);
+        assert_eq!((out, zr, carry, overflow), (int_to_binary16(0), High, High, Low));
+    }
+
+    #[test]
+    fn textended_alu() {
+        let (x, y) = (int_to_binary16(-300), int_to_binary16(7));
+        assert_eq!(extended_alu(x, y, High, High, Low, Low, Low, Low, High, Low), alu_flags(x, y, Low, Low, Low, Low, High, Low));
+        assert_eq!(extended_alu(x, y, High, Low, Low, High, Low, High, High, High), alu_flags(x, y, Low, High, Low, High, High, High));
+        assert_eq!(extended_alu(x, y, Low, Low, Low, Low, Low, Low, Low, Low), (int_to_binary16(-2100), Low, High, Low, Low));
+        assert_eq!(extended_alu(x, y, Low, High, Low, Low, Low, Low, Low, Low), (int_to_binary16(-600), Low, High, Low, Low));
+        assert_eq!(extended_alu(x, y, Low, High, Low, High, Low, Low, Low, Low), (int_to_binary16(-150), Low, High, Low, Low));
+        assert_eq!(extended_alu(x, y, Low, High, High, High, Low, Low, Low, Low), (int_to_binary16(3), Low, Low, Low, Low));
+        assert_eq!(extended_alu(x, int_to_binary16(0), Low, Low, Low, Low, Low, Low, Low, Low), (int_to_binary16(0), High, Low, Low, Low));
+        // the shift and multiply units have no flags, even when the adder of the standard alu carries
+        let (_, _, _, carry, overflow) = extended_alu(int_to_binary16(-1), int_to_binary16(-1), Low, High, Low, Low, Low, Low, High, Low);
+        assert_eq!((carry, overflow), (Low, Low));
+    }
+    #[test]
+    fn textended_jump() {
+        let jump = |unit: &str, j: &str, zr, ng, carry, overflow| {
+            let (x, j) = (bits::<2>(unit), bits::<3>(j));
+            extended_jump(x[0], x[1], j[0], j[1], j[2], zr, ng, carry, overflow)
+        };
+        for &unit in ["11", "01", "00"].iter() {
+            assert_eq!(jump(unit, "100", Low, High, Low, Low), High);
+            assert_eq!(jump(unit, "011", Low, High, High, High), Low);
+            assert_eq!(jump(unit, "010", High, Low, Low, Low), High);
+            assert_eq!(jump(unit, "001", Low, Low, Low, Low), High);
+            assert_eq!(jump(unit, "111", High, Low, Low, Low), High);
+        }
+        assert_eq!(jump("10", "100", Low, High, Low, Low), Low);
+        assert_eq!(jump("10", "100", Low, Low, High, Low), High);
+        assert_eq!(jump("10", "010", High, Low, High, High), High);
+        assert_eq!(jump("10", "001", Low, Low, High, Low), Low);
+        assert_eq!(jump("10", "001", Low, Low, Low, High), High);
+        assert_eq!(jump("10", "000", Low, High, High, High), Low);
+    }
+
     fn check_alu(x: i16, y: i16, zx: Signal, nx: Signal, zy: Signal, ny: Signal, f: Signal, no: Signal, expected: i16) {
         let (out, zr, ng) = alu(int_to_binary16(x), int_to_binary16(y), zx, nx, zy, ny, f, no);
         assert_eq!(binary_to_int16(out), expected);
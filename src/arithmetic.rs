@@ -0,0 +1,100 @@
+use crate::gates::*;
+use crate::alu::*;
+
+// Unsigned array multiplier: one row of and gates and one n_adder per bit of in2, each row
+// shifting its lowest bit out into the low word. Returns (high, low) words of the product.
+pub fn multiplier<const BITS: usize, S: Logic>(in1: Signals<BITS, S>, in2: Signals<BITS, S>) -> (Signals<BITS, S>, Signals<BITS, S>) {
+  let mut high = [S::LOW; BITS];
+  let mut low = [S::LOW; BITS];
+  for i in (0..BITS).rev() {
+    let (sum, carry) = n_adder_carry(high, and_n(in1, [in2[i]; BITS]), S::LOW);
+    low[i] = sum[BITS - 1];
+    high[0] = carry;
+    high[1..].copy_from_slice(&sum[..BITS - 1]);
+  }
+  (high, low)
+}
+
+// Unsigned restoring divider returning (quotient, remainder). Each row subtracts the divisor
+// from the partial remainder and keeps the difference only if it did not borrow.
+// Dividing by zero gives a quotient of all ones and the dividend as remainder.
+pub fn restoring_divider<const BITS: usize, S: Logic>(in1: Signals<BITS, S>, in2: Signals<BITS, S>) -> (Signals<BITS, S>, Signals<BITS, S>) {
+  let mut quotient = [S::LOW; BITS];
+  let mut remainder = [S::LOW; BITS];
+  let divisor = not_n(in2);
+  for i in 0..BITS {
+    let overflow = remainder[0];
+    let mut shifted = [in1[i]; BITS];
+    shifted[..BITS - 1].copy_from_slice(&remainder[1..]);
+    let (difference, no_borrow) = n_adder_carry(shifted, divisor, S::HIGH);
+    quotient[i] = or(overflow, no_borrow);
+    remainder = mux_n(shifted, difference, quotient[i]);
+  }
+  (quotient, remainder)
+}
+
+// Unsigned non-restoring divider returning (quotient, remainder). The partial remainder is
+// kept in BITS + 1 bits (sign, remainder) and each row adds or subtracts the divisor depending
+// on its sign, with a single correcting addition at the end instead of a restore every row.
+pub fn non_restoring_divider<const BITS: usize, S: Logic>(in1: Signals<BITS, S>, in2: Signals<BITS, S>) -> (Signals<BITS, S>, Signals<BITS, S>) {
+  let mut quotient = [S::LOW; BITS];
+  let mut remainder = [S::LOW; BITS];
+  let mut sign = S::LOW;
+  for i in 0..BITS {
+    let shifted_sign = remainder[0];
+    let mut shifted = [in1[i]; BITS];
+    shifted[..BITS - 1].copy_from_slice(&remainder[1..]);
+    let subtract = not(sign);
+    let operand = mux_n(in2, not_n(in2), subtract);
+    let (sum, carry) = n_adder_carry(shifted, operand, subtract);
+    sign = xor(xor(shifted_sign, subtract), carry);
+    remainder = sum;
+    quotient[i] = not(sign);
+  }
+  (quotient, mux_n(remainder, n_adder(remainder, in2), sign))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::*;
+    use std::convert::TryInto;
+
+    fn bits6(value: i16) -> Signals<6> {
+        int_to_binary16(value)[10..].try_into().unwrap()
+    }
+
+    #[test]
+    fn tmultiplier() {
+        for a in 0..64 {
+            for b in 0..64 {
+                let (high, low) = multiplier(bits6(a), bits6(b));
+                assert_eq!((high, low), (bits6((a * b) >> 6), bits6((a * b) & 63)));
+            }
+        }
+        let (high, low) = multiplier(int_to_binary16(-1), int_to_binary16(-1));
+        assert_eq!((high, low), (int_to_binary16(-2), int_to_binary16(1)));
+        let (high, low) = multiplier(int_to_binary16(300), int_to_binary16(1000));
+        assert_eq!((high, low), (int_to_binary16(4), int_to_binary16(-27680)));
+    }
+    #[test]
+    fn trestoring_divider() {
+        for a in 0..64 {
+            for b in 1..64 {
+                assert_eq!(restoring_divider(bits6(a), bits6(b)), (bits6(a / b), bits6(a % b)));
+            }
+            assert_eq!(restoring_divider(bits6(a), bits6(0)), (bits6(63), bits6(a)));
+        }
+        assert_eq!(restoring_divider(int_to_binary16(-1), int_to_binary16(7)), (int_to_binary16(9362), int_to_binary16(1)));
+    }
+    #[test]
+    fn tnon_restoring_divider() {
+        for a in 0..64 {
+            for b in 1..64 {
+                assert_eq!(non_restoring_divider(bits6(a), bits6(b)), (bits6(a / b), bits6(a % b)));
+            }
+        }
+        assert_eq!(non_restoring_divider(int_to_binary16(-1), int_to_binary16(-2)), (int_to_binary16(1), int_to_binary16(1)));
+        assert_eq!(non_restoring_divider(int_to_binary16(12345), int_to_binary16(100)), (int_to_binary16(123), int_to_binary16(45)));
+    }
+}
@@ -0,0 +1,518 @@
+use crate::gates::*;
+use crate::netlist::{Netlist, Port};
+use crate::fault::{self, Fault};
+use crate::equivalence::{self, Equivalence};
+use crate::truth_table::{self, TruthTable};
+use crate::bitslice::LANES;
+use crate::bits::ParseBitsError;
+use Signal::*;
+use std::fmt;
+
+// Automatic test pattern generation for single stuck-at faults. Random vectors first catch
+// the easy faults, then every fault they missed gets a sat query on the miter of the good
+// and faulty netlists: a counterexample is a vector detecting it, equivalence proves it
+// redundant. An exact set cover finally keeps the fewest of these vectors detecting every
+// detectable fault, or the smallest cover found when the search runs out of budget. The
+// vectors are written as a nand2tetris .tst script and .cmp file, and the vectors of
+// combinational scripts and the rows of binary .cmp files can be read back.
+
+// Batches of 64 random vectors tried before falling back to sat, fewer if one finds nothing
+const RANDOM_BATCHES: usize = 16;
+
+// Nodes of the branch and bound of the set cover before giving up on proving it minimal
+const COVER_NODES: usize = 200_000;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TestSet {
+    pub name: String,
+    pub vectors: Vec<Vec<Signal>>,
+    pub faults: usize,
+    // Faults no input can detect, the logic driving them can be simplified away
+    pub redundant: Vec<Fault>,
+    // No fewer of the generated vectors detect every detectable fault
+    pub minimal: bool,
+}
+
+impl fmt::Display for TestSet {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}: {} {} vectors detect {} of {} faults, {} redundant",
+      self.name, self.vectors.len(), if self.minimal { "minimal" } else { "compacted" },
+      self.faults - self.redundant.len(), self.faults, self.redundant.len())
+  }
+}
+
+fn random_vectors(width: usize, count: usize, seed: &mut u64) -> Vec<Vec<Signal>> {
+  (0..count).map(|_| (0..width).map(|_| {
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 7;
+    *seed ^= *seed << 17;
+    Signal::from(*seed >> 32 & 1 == 1)
+  }).collect()).collect()
+}
+
+fn detects(mask: &[u64], vector: usize) -> bool {
+  mask[vector / LANES] >> (vector % LANES) & 1 == 1
+}
+
+// Vectors detecting every detectable fault of the netlist, and the redundant faults
+pub fn generate(netlist: &Netlist) -> TestSet {
+  let width = netlist.input_width();
+  let faults = fault::faults(netlist);
+  let mut remaining = faults.clone();
+  let mut vectors: Vec<Vec<Signal>> = vec![];
+  let mut seed = 0x2545_f491_4f6c_dd1d;
+  for _ in 0..RANDOM_BATCHES {
+    if remaining.is_empty() {
+      break;
+    }
+    let batch = random_vectors(width, LANES, &mut seed);
+    let masks = fault::detections(netlist, &remaining, &batch);
+    let found = masks.iter().filter(|mask| mask.iter().any(|&word| word != 0)).count();
+    if found == 0 {
+      break;
+    }
+    vectors.extend(batch);
+    remaining = remaining.into_iter().zip(masks.iter()).filter(|(_, mask)| mask.iter().all(|&word| word == 0)).map(|(fault, _)| fault).collect();
+  }
+  let mut redundant = vec![];
+  while let Some(fault) = remaining.pop() {
+    match equivalence::check(netlist, &fault::inject(netlist, fault)).unwrap() {
+        Equivalence::Equivalent => redundant.push(fault),
+        Equivalence::Counterexample { input, .. } => {
+          let masks = fault::detections(netlist, &remaining, std::slice::from_ref(&input));
+          remaining = remaining.into_iter().zip(masks.iter()).filter(|(_, mask)| mask[0] == 0).map(|(fault, _)| fault).collect();
+          vectors.push(input);
+        },
+    }
+  }
+  redundant.sort_by_key(|fault| (fault.wire, fault.stuck == High));
+  let detectable: Vec<Fault> = faults.iter().copied().filter(|fault| !redundant.contains(fault)).collect();
+  let (vectors, minimal) = compact(netlist, &detectable, vectors);
+  TestSet { name: netlist.name.clone(), vectors, faults: faults.len(), redundant, minimal }
+}
+
+// Minimum set cover of the faults by the vectors, and whether it is proven minimal. It is
+// first reduced until nothing changes: a fault detected by a single vector makes it
+// essential, a fault detected by every vector detecting another one is dropped, and so is a
+// vector detecting a subset of the faults of another one. A branch and bound then covers
+// what is left, starting from the greedy cover. It proves the covers of the alu minimal,
+// while the 16 slices of mux_8_way_n share vectors in too many ways and use up the budget.
+fn compact(netlist: &Netlist, faults: &[Fault], vectors: Vec<Vec<Signal>>) -> (Vec<Vec<Signal>>, bool) {
+  let masks = fault::detections(netlist, faults, &vectors);
+  let (mut kept, rows) = reduce(masks, vectors.len());
+  let mut search = Search::new(&rows, vectors.len());
+  let uncovered = search.all_rows();
+  let allowed = (0..vectors.len().div_ceil(LANES)).map(|_| !0).collect();
+  search.branch(&uncovered, allowed, &mut vec![]);
+  kept.extend(search.best.iter().copied());
+  kept.sort_unstable();
+  (kept.into_iter().map(|v| vectors[v].clone()).collect(), search.nodes <= COVER_NODES)
+}
+
+// Rows are the vectors detecting a fault, as sorted indices
+fn reduce(masks: Vec<Vec<u64>>, vectors: usize) -> (Vec<usize>, Vec<Vec<usize>>) {
+  let mut rows: Vec<Vec<usize>> = masks.iter().map(|mask| (0..vectors).filter(|&v| detects(mask, v)).collect()).collect();
+  let subset = |a: &[usize], b: &[usize]| a.iter().all(|v| b.binary_search(v).is_ok());
+  let mut essential = vec![];
+  loop {
+    let before = (essential.len(), rows.len(), rows.iter().map(Vec::len).sum::<usize>());
+    while let Some(v) = rows.iter().find(|row| row.len() == 1).map(|row| row[0]) {
+      essential.push(v);
+      rows.retain(|row| !row.contains(&v));
+    }
+    rows.sort_by_key(Vec::len);
+    rows.dedup();
+    let mut minimal: Vec<Vec<usize>> = vec![];
+    for row in rows.into_iter() {
+      if !minimal.iter().any(|smaller| subset(smaller, &row)) {
+        minimal.push(row);
+      }
+    }
+    rows = minimal;
+    let mut columns: Vec<Vec<usize>> = vec![vec![]; vectors];
+    for (r, row) in rows.iter().enumerate() {
+      for &v in row.iter() {
+        columns[v].push(r);
+      }
+    }
+    let dominated: Vec<bool> = (0..vectors).map(|v| {
+      (0..vectors).any(|u| u != v && subset(&columns[v], &columns[u]) && (u < v || columns[u].len() > columns[v].len()))
+    }).collect();
+    for row in rows.iter_mut() {
+      row.retain(|&v| !dominated[v]);
+    }
+    if (essential.len(), rows.len(), rows.iter().map(Vec::len).sum::<usize>()) == before {
+      return (essential, rows);
+    }
+  }
+}
+
+fn intersects(a: &[u64], b: &[u64]) -> bool {
+  a.iter().zip(b.iter()).any(|(&a, &b)| a & b != 0)
+}
+
+// Branch and bound over bit masks: the vectors of each row and the rows of each vector
+struct Search {
+    rows: Vec<Vec<u64>>,
+    columns: Vec<Vec<u64>>,
+    // Rows from the fewest vectors to the most, the order of the lower bound
+    order: Vec<usize>,
+    best: Vec<usize>,
+    nodes: usize,
+}
+
+impl Search {
+  fn new(rows: &[Vec<usize>], vectors: usize) -> Search {
+    let mut masks = vec![vec![0u64; vectors.div_ceil(LANES)]; rows.len()];
+    let mut columns = vec![vec![0u64; rows.len().div_ceil(LANES)]; vectors];
+    for (r, row) in rows.iter().enumerate() {
+      for &v in row.iter() {
+        masks[r][v / LANES] |= 1 << (v % LANES);
+        columns[v][r / LANES] |= 1 << (r % LANES);
+      }
+    }
+    let mut order: Vec<usize> = (0..rows.len()).collect();
+    order.sort_by_key(|&r| rows[r].len());
+    let mut search = Search { rows: masks, columns, order, best: vec![], nodes: 0 };
+    search.best = search.greedy(&search.all_rows());
+    search
+  }
+
+  fn all_rows(&self) -> Vec<u64> {
+    let mut rows = vec![0u64; self.rows.len().div_ceil(LANES)];
+    for r in 0..self.rows.len() {
+      rows[r / LANES] |= 1 << (r % LANES);
+    }
+    rows
+  }
+
+  fn covered(&self, v: usize, uncovered: &[u64]) -> usize {
+    self.columns[v].iter().zip(uncovered.iter()).map(|(&c, &u)| (c & u).count_ones() as usize).sum()
+  }
+
+  fn cover(&self, v: usize, uncovered: &[u64]) -> Vec<u64> {
+    uncovered.iter().zip(self.columns[v].iter()).map(|(&u, &c)| u & !c).collect()
+  }
+
+  // The vector in the most rows still uncovered, until none is left
+  fn greedy(&self, uncovered: &[u64]) -> Vec<usize> {
+    let mut uncovered = uncovered.to_vec();
+    let mut kept = vec![];
+    while uncovered.iter().any(|&word| word != 0) {
+      let best = (0..self.columns.len()).max_by_key(|&v| (self.covered(v, &uncovered), std::cmp::Reverse(v))).unwrap();
+      uncovered = self.cover(best, &uncovered);
+      kept.push(best);
+    }
+    kept
+  }
+
+  // Uncovered rows without any allowed vector in common each need their own vector,
+  // None when a row has no allowed vector left
+  fn lower_bound(&self, uncovered: &[u64], allowed: &[u64]) -> Option<usize> {
+    let mut used = vec![0u64; allowed.len()];
+    let mut bound = 0;
+    for &r in self.order.iter().filter(|&&r| detects(uncovered, r)) {
+      let row = &self.rows[r];
+      if !intersects(row, allowed) {
+        return None;
+      }
+      if !row.iter().zip(allowed.iter()).zip(used.iter()).any(|((&row, &a), &u)| row & a & u != 0) {
+        used.iter_mut().zip(row.iter().zip(allowed.iter())).for_each(|(u, (&row, &a))| *u |= row & a);
+        bound += 1;
+      }
+    }
+    Some(bound)
+  }
+
+  // Branches on the vectors of the uncovered row with the fewest, each vector being left out
+  // of the branches after its own
+  fn branch(&mut self, uncovered: &[u64], mut allowed: Vec<u64>, chosen: &mut Vec<usize>) {
+    self.nodes += 1;
+    if self.nodes > COVER_NODES {
+      return;
+    }
+    if uncovered.iter().all(|&word| word == 0) {
+      if chosen.len() < self.best.len() {
+        self.best = chosen.clone();
+      }
+      return;
+    }
+    match self.lower_bound(uncovered, &allowed) {
+        Some(bound) if chosen.len() + bound < self.best.len() => (),
+        _ => return,
+    }
+    let &row = self.order.iter().filter(|&&r| detects(uncovered, r))
+      .min_by_key(|&&r| self.rows[r].iter().zip(allowed.iter()).map(|(&row, &a)| (row & a).count_ones()).sum::<u32>()).unwrap();
+    let mut vectors: Vec<usize> = (0..self.columns.len()).filter(|&v| detects(&self.rows[row], v) && detects(&allowed, v)).collect();
+    vectors.sort_by_key(|&v| std::cmp::Reverse(self.covered(v, uncovered)));
+    for v in vectors.into_iter() {
+      chosen.push(v);
+      self.branch(&self.cover(v, uncovered), allowed.clone(), chosen);
+      chosen.pop();
+      if self.nodes > COVER_NODES {
+        return;
+      }
+      allowed[v / LANES] &= !(1 << (v % LANES));
+    }
+  }
+}
+
+// Expected outputs in the layout of truth tables, which is the one of the .out files the
+// output-list of `tst` makes the simulator write
+pub fn cmp(netlist: &Netlist, vectors: &[Vec<Signal>]) -> String {
+  let table = TruthTable {
+    name: netlist.name.clone(),
+    inputs: truth_table::columns(&netlist.inputs),
+    outputs: truth_table::columns(&netlist.outputs),
+    rows: vectors.iter().map(|input| (input.clone(), netlist.evaluate(input))).collect(),
+  };
+  table.to_string()
+}
+
+// Test script for the chip `stem`.hdl, comparing against `stem`.cmp
+pub fn tst(netlist: &Netlist, vectors: &[Vec<Signal>], stem: &str) -> String {
+  let format = |port: &Port| {
+    let width = port.wires.len();
+    let column = port.name.len().max(width) + 2;
+    let left = (column - width) / 2;
+    format!("{}%B{}.{}.{}", port.name, left, width, column - width - left)
+  };
+  let output_list: Vec<String> = netlist.inputs.iter().chain(netlist.outputs.iter()).map(format).collect();
+  let mut script = format!("load {stem}.hdl,\noutput-file {stem}.out,\ncompare-to {stem}.cmp,\noutput-list {};\n", output_list.join(" "), stem = stem);
+  for vector in vectors.iter() {
+    script.push('\n');
+    let mut signals = vector.iter();
+    for port in netlist.inputs.iter() {
+      let bits: String = signals.by_ref().take(port.wires.len()).map(|s| s.to_string()).collect();
+      match port.wires.len() {
+          1 => script.push_str(&format!("set {} {},\n", port.name, bits)),
+          _ => script.push_str(&format!("set {} %B{},\n", port.name, bits)),
+      }
+    }
+    script.push_str("eval,\noutput;\n");
+  }
+  script
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TstError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TstError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "line {}: {}", self.line, self.message)
+  }
+}
+
+impl std::error::Error for TstError {}
+
+// Commands of a .tst script with the line they start on, without the comments
+fn commands(text: &str) -> Vec<(usize, String)> {
+  let mut commands = vec![];
+  let (mut line, mut start) = (1, 1);
+  let mut command = String::new();
+  let mut chars = text.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+        '/' if chars.peek() == Some(&'/') => {
+          while chars.peek().is_some_and(|&c| c != '\n') {
+            chars.next();
+          }
+        },
+        '/' if chars.peek() == Some(&'*') => {
+          chars.next();
+          let mut previous = ' ';
+          for c in chars.by_ref() {
+            line += (c == '\n') as usize;
+            if previous == '*' && c == '/' {
+              break;
+            }
+            previous = c;
+          }
+        },
+        ',' | ';' => commands.push((start, std::mem::take(&mut command))),
+        _ => {
+          line += (c == '\n') as usize;
+          if command.trim().is_empty() {
+            start = line;
+          }
+          command.push(c);
+        },
+    }
+  }
+  commands.push((start, command));
+  commands.into_iter().filter(|(_, command)| !command.trim().is_empty()).collect()
+}
+
+// Value of a set command on `width` pins, %B binary padded with 0s, %X hexadecimal, or
+// decimal with an optional %D, negative ones in two's complement
+fn pin_value(value: &str, width: usize) -> Option<Vec<Signal>> {
+  if let Some(bits) = value.strip_prefix("%B") {
+    if bits.is_empty() || bits.len() > width || bits.chars().any(|c| c != '0' && c != '1') {
+      return None;
+    }
+    return Some(vec![Low; width - bits.len()].into_iter().chain(bits.chars().map(|c| Signal::from(c == '1'))).collect());
+  }
+  let value = match value.strip_prefix("%X") {
+      Some(hex) => i128::from_str_radix(hex, 16).ok()?,
+      None => value.strip_prefix("%D").unwrap_or(value).parse::<i128>().ok()?,
+  };
+  let bits = width.min(64) as u32;
+  if value < -(1 << (bits - 1)) || value >= 1 << bits {
+    return None;
+  }
+  Some((0..width).rev().map(|i| Signal::from(value >> i.min(127) & 1 == 1)).collect())
+}
+
+// Input vectors of a test script for the netlist, one per output command with the inputs
+// set so far, Low until set. Clocked scripts, with tick, tock or loops, are rejected.
+pub fn parse_tst(text: &str, netlist: &Netlist) -> Result<Vec<Vec<Signal>>, TstError> {
+  let mut values: Vec<Vec<Signal>> = netlist.inputs.iter().map(|port| vec![Low; port.wires.len()]).collect();
+  let mut vectors = vec![];
+  for (line, command) in commands(text) {
+    let error = |message: String| TstError { line, message };
+    let words: Vec<&str> = command.split_whitespace().collect();
+    match words[0] {
+        "set" if words.len() == 3 => {
+          let port = netlist.inputs.iter().position(|port| port.name == words[1])
+            .ok_or_else(|| error(format!("{} has no input {}", netlist.name, words[1])))?;
+          values[port] = pin_value(words[2], values[port].len())
+            .ok_or_else(|| error(format!("invalid value {} for {}[{}]", words[2], words[1], values[port].len())))?;
+        },
+        "output" => vectors.push(values.concat()),
+        "eval" | "load" | "output-file" | "compare-to" | "output-list" | "echo" | "clear-echo" => (),
+        "tick" | "tock" | "ticktock" | "repeat" | "while" => return Err(error(format!("{} is not supported, the chip is combinational", words[0]))),
+        _ => return Err(error(format!("invalid command {}", command.trim()))),
+    }
+  }
+  Ok(vectors)
+}
+
+// File named by the compare-to command of a test script
+pub fn compare_to(text: &str) -> Option<String> {
+  commands(text).into_iter().find_map(|(_, command)| {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    match words[..] {
+        ["compare-to", file] => Some(file.to_string()),
+        _ => None,
+    }
+  })
+}
+
+// Inputs and expected outputs of a line of a .cmp file
+pub type Row = (Vec<Signal>, Vec<Signal>);
+
+// Every row of a .cmp file in binary, as written by `cmp`
+pub fn parse_cmp(text: &str, netlist: &Netlist) -> Result<Vec<Row>, ParseBitsError> {
+  let width = netlist.input_width();
+  let rows = fault::parse_vectors(text, width + netlist.output_width())?;
+  Ok(rows.into_iter().map(|mut row| {
+    let outputs = row.split_off(width);
+    (row, outputs)
+  }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netlist::{self, Builder};
+
+    fn check(netlist: &Netlist) -> TestSet {
+        let tests = generate(netlist);
+        let report = fault::coverage(netlist, &tests.vectors);
+        assert_eq!(report.undetected(), tests.redundant);
+        tests
+    }
+
+    #[test]
+    fn tgenerate() {
+        // 01, 10 and 11 test every fault of xor, 00 adds nothing
+        let xor = check(&netlist::xor());
+        assert_eq!((xor.vectors.len(), xor.redundant.len()), (3, 0));
+        // the redundant faults found by simulation in fault.rs, proven so by sat
+        let full_adder = check(&netlist::full_adder());
+        assert_eq!(full_adder.redundant.len(), 4);
+        assert_eq!(format!("{}", full_adder), format!("full_adder: {} minimal vectors detect 40 of 44 faults, 4 redundant", full_adder.vectors.len()));
+        let mux = check(&netlist::mux_8_way_n::<16>());
+        assert!(mux.vectors.len() < 40);
+    }
+    #[test]
+    fn tredundant() {
+        // or(a, and(a, b)) is just a, the and can be stuck at 0 without any effect
+        let mut b = Builder::new("absorption");
+        let (a, x) = (b.input("a"), b.input("b"));
+        let and = b.and(a, x);
+        let out = b.or(a, and);
+        b.output("out", out);
+        let tests = check(&b.build());
+        assert!(tests.redundant.contains(&Fault { wire: and, stuck: Low }));
+        assert!(tests.vectors.len() <= 2);
+    }
+    #[test]
+    fn talu() {
+        // a few dozen vectors, where the grid of talu takes 288 and still misses faults
+        let alu = check(&netlist::alu::<4>());
+        assert!(alu.vectors.len() < 40);
+        let alu = check(&netlist::alu::<16>());
+        assert!(alu.vectors.len() < 64 && alu.minimal);
+    }
+    #[test]
+    fn tcompact() {
+        // from every input vector, no smaller subset than the cover detects every detectable fault
+        for netlist in [netlist::full_adder(), netlist::n_adder::<2>(), netlist::dmux_4_way(), netlist::mux()].iter() {
+            let width = netlist.input_width();
+            let vectors: Vec<Vec<Signal>> = truth_table::inputs(width).collect();
+            let faults = fault::faults(netlist);
+            let masks = fault::detections(netlist, &faults, &vectors);
+            let detectable: Vec<Fault> = faults.iter().zip(masks.iter()).filter(|(_, mask)| mask.iter().any(|&word| word != 0)).map(|(&fault, _)| fault).collect();
+            let (cover, minimal) = compact(netlist, &detectable, vectors.clone());
+            assert!(minimal);
+            assert_eq!(fault::coverage(netlist, &cover).undetected().len(), faults.len() - detectable.len());
+            // subsets of a few vectors at a time, as bit masks over the vectors
+            let mut subsets: Vec<u64> = vec![0];
+            for _ in 1..cover.len() {
+                subsets = subsets.iter().flat_map(|&subset| (64 - subset.leading_zeros() as usize..vectors.len()).map(move |v| subset | 1 << v)).collect();
+                assert!(subsets.iter().all(|&subset| masks.iter().any(|mask| mask[0] & subset == 0 && mask[0] != 0)), "{}", netlist.name);
+            }
+        }
+    }
+    #[test]
+    fn tfiles() {
+        let and = netlist::and();
+        let vectors = vec![vec![Low, High], vec![High, High]];
+        assert_eq!(tst(&and, &vectors, "And"), "load And.hdl,\noutput-file And.out,\ncompare-to And.cmp,\n\
+            output-list in1%B2.1.2 in2%B2.1.2 out%B2.1.2;\n\nset in1 0,\nset in2 1,\neval,\noutput;\n\nset in1 1,\nset in2 1,\neval,\noutput;\n");
+        assert_eq!(cmp(&and, &vectors), "| in1 | in2 | out |\n|  0  |  1  |  0  |\n|  1  |  1  |  1  |\n");
+        let adder = netlist::n_adder::<4>();
+        let vectors = vec![vec![Low, High, Low, High, High, High, High, High]];
+        assert!(tst(&adder, &vectors, "Add4").contains("output-list in1%B1.4.1 in2%B1.4.1 out%B1.4.1;\n\nset in1 %B0101,\nset in2 %B1111,\n"));
+        assert_eq!(cmp(&adder, &vectors), "| in1  | in2  | out  |\n| 0101 | 1111 | 0100 |\n");
+        assert_eq!(fault::parse_vectors(&cmp(&adder, &vectors), 8), Ok(vectors));
+    }
+    #[test]
+    fn tparse_tst() {
+        let adder = netlist::n_adder::<4>();
+        let tests = generate(&adder);
+        assert_eq!(parse_tst(&tst(&adder, &tests.vectors, "Add4"), &adder), Ok(tests.vectors.clone()));
+        let script = "// Add4.tst\nload Add4.hdl,\n/* two vectors,\n   in1 kept */\nset in1 %D-2, set in2 %X3,\neval, output;\n\nset in2 %B1;\noutput;";
+        assert_eq!(parse_tst(script, &adder), Ok(vec![
+            vec![High, High, High, Low, Low, Low, High, High],
+            vec![High, High, High, Low, Low, Low, Low, High],
+        ]));
+        assert_eq!(parse_tst("set in1 16,", &adder), Err(TstError { line: 1, message: "invalid value 16 for in1[4]".to_string() }));
+        assert_eq!(parse_tst("output;\n\nset sel 1", &adder), Err(TstError { line: 3, message: "n_adder::<4> has no input sel".to_string() }));
+        assert_eq!(parse_tst("set in1 1,\ntick,", &adder).unwrap_err().to_string(), "line 2: tick is not supported, the chip is combinational");
+        assert_eq!(compare_to(&tst(&adder, &tests.vectors, "Add4")), Some("Add4.cmp".to_string()));
+        assert_eq!(compare_to("// compare-to X.cmp\nload Add4.hdl,"), None);
+    }
+    #[test]
+    fn tparse_cmp() {
+        let adder = netlist::n_adder::<4>();
+        let vectors = vec![vec![Low, High, Low, High, High, High, High, High], vec![Low; 8]];
+        let rows = parse_cmp(&cmp(&adder, &vectors), &adder).unwrap();
+        assert_eq!(rows, vectors.iter().map(|vector| (vector.clone(), adder.evaluate(vector))).collect::<Vec<_>>());
+        assert_eq!(parse_cmp("| 0101 | 1111 |\n", &adder), Err(ParseBitsError::Length { expected: 12, found: 8 }));
+    }
+}
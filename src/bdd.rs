@@ -0,0 +1,363 @@
+use crate::gates::*;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+// Reduced ordered binary decision diagrams. A Bdd is the index of a node in a manager shared
+// by the whole thread, so it is Copy and implements Logic: any chip of gates.rs or alu.rs
+// evaluates symbolically when given Bdd signals. Nodes are unique, two Bdds are equal exactly
+// when they are the same function. Variables are ordered by index, the manager is never
+// garbage collected and lives until the thread ends or `clear` is called.
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub struct Bdd(u32);
+
+const TERMINAL: u32 = u32::MAX;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+struct Node {
+    var: u32,
+    low: Bdd,
+    high: Bdd,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+enum Op {
+    And,
+    Or,
+    Xor,
+    Nand,
+}
+
+impl Op {
+  fn apply(self, a: bool, b: bool) -> bool {
+    match self {
+        Op::And => a && b,
+        Op::Or => a || b,
+        Op::Xor => a != b,
+        Op::Nand => !(a && b),
+    }
+  }
+}
+
+#[derive(Debug)]
+struct Manager {
+    nodes: Vec<Node>,
+    unique: HashMap<Node, Bdd>,
+    computed: HashMap<(Op, Bdd, Bdd), Bdd>,
+}
+
+impl Manager {
+  fn new() -> Manager {
+    let terminal = |b| Node { var: TERMINAL, low: b, high: b };
+    Manager { nodes: vec![terminal(Bdd::LOW), terminal(Bdd::HIGH)], unique: HashMap::new(), computed: HashMap::new() }
+  }
+
+  fn node(&mut self, var: u32, low: Bdd, high: Bdd) -> Bdd {
+    if low == high {
+      return low;
+    }
+    let node = Node { var, low, high };
+    let nodes = &mut self.nodes;
+    *self.unique.entry(node).or_insert_with(|| {
+      nodes.push(node);
+      Bdd(nodes.len() as u32 - 1)
+    })
+  }
+
+  fn apply(&mut self, op: Op, f: Bdd, g: Bdd) -> Bdd {
+    if f.is_constant() && g.is_constant() {
+      return Bdd::from(op.apply(f == Bdd::HIGH, g == Bdd::HIGH));
+    }
+    // every operation is commutative
+    let key = (op, f.min(g), f.max(g));
+    if let Some(&result) = self.computed.get(&key) {
+      return result;
+    }
+    let (nf, ng) = (self.nodes[f.0 as usize], self.nodes[g.0 as usize]);
+    let var = nf.var.min(ng.var);
+    let (f0, f1) = if nf.var == var { (nf.low, nf.high) } else { (f, f) };
+    let (g0, g1) = if ng.var == var { (ng.low, ng.high) } else { (g, g) };
+    let low = self.apply(op, f0, g0);
+    let high = self.apply(op, f1, g1);
+    let result = self.node(var, low, high);
+    self.computed.insert(key, result);
+    result
+  }
+}
+
+thread_local! {
+  static MANAGER: RefCell<Manager> = RefCell::new(Manager::new());
+}
+
+fn with<T>(f: impl FnOnce(&mut Manager) -> T) -> T {
+  MANAGER.with(|manager| f(&mut manager.borrow_mut()))
+}
+
+impl Logic for Bdd {
+  const LOW: Bdd = Bdd(0);
+  const HIGH: Bdd = Bdd(1);
+  fn nand(self, other: Bdd) -> Bdd {
+    with(|manager| manager.apply(Op::Nand, self, other))
+  }
+}
+
+impl From<bool> for Bdd {
+  fn from(value: bool) -> Bdd {
+    if value { Bdd::HIGH } else { Bdd::LOW }
+  }
+}
+
+impl From<Signal> for Bdd {
+  fn from(signal: Signal) -> Bdd {
+    Bdd::from(signal == Signal::High)
+  }
+}
+
+impl Not for Bdd {
+  type Output = Bdd;
+  fn not(self) -> Bdd {
+    self ^ Bdd::HIGH
+  }
+}
+
+macro_rules! operator {
+  ($trait:ident, $method:ident, $op:expr) => {
+    impl $trait for Bdd {
+      type Output = Bdd;
+      fn $method(self, other: Bdd) -> Bdd {
+        with(|manager| manager.apply($op, self, other))
+      }
+    }
+  };
+}
+
+operator!(BitAnd, bitand, Op::And);
+operator!(BitOr, bitor, Op::Or);
+operator!(BitXor, bitxor, Op::Xor);
+
+impl Bdd {
+  pub fn var(index: usize) -> Bdd {
+    with(|manager| manager.node(index as u32, Bdd::LOW, Bdd::HIGH))
+  }
+
+  pub fn is_constant(self) -> bool {
+    self.0 < 2
+  }
+
+  // Top variable, low and high children, None for the constants
+  pub fn decompose(self) -> Option<(usize, Bdd, Bdd)> {
+    if self.is_constant() {
+      return None;
+    }
+    let node = with(|manager| manager.nodes[self.0 as usize]);
+    Some((node.var as usize, node.low, node.high))
+  }
+
+  // Value under an assignment of every variable, indexed by variable
+  pub fn evaluate(self, assignment: &[Signal]) -> Signal {
+    let mut bdd = self;
+    while let Some((var, low, high)) = bdd.decompose() {
+      bdd = if assignment[var] == Signal::High { high } else { low };
+    }
+    if bdd == Bdd::HIGH { Signal::High } else { Signal::Low }
+  }
+
+  // Nodes reachable from this one, the constants included
+  fn reachable(self) -> HashSet<Bdd> {
+    let mut seen: HashSet<Bdd> = [self].iter().copied().collect();
+    let mut stack = vec![self];
+    while let Some(bdd) = stack.pop() {
+      if let Some((_, low, high)) = bdd.decompose() {
+        stack.extend([low, high].iter().filter(|&&child| seen.insert(child)));
+      }
+    }
+    seen
+  }
+
+  pub fn size(self) -> usize {
+    self.reachable().len()
+  }
+
+  // Variables the function depends on, in order
+  pub fn support(self) -> Vec<usize> {
+    let mut vars: Vec<usize> = self.reachable().iter().filter_map(|bdd| bdd.decompose().map(|(var, _, _)| var)).collect();
+    vars.sort_unstable();
+    vars.dedup();
+    vars
+  }
+
+  // Number of assignments of the variables 0..vars making the function High (#SAT)
+  pub fn sat_count(self, vars: usize) -> u128 {
+    fn count(bdd: Bdd, vars: usize, cache: &mut HashMap<Bdd, u128>) -> (u128, usize) {
+      match bdd.decompose() {
+          None => ((bdd == Bdd::HIGH) as u128, vars),
+          Some((var, low, high)) => {
+            if let Some(&n) = cache.get(&bdd) {
+              return (n, var);
+            }
+            let (n0, var0) = count(low, vars, cache);
+            let (n1, var1) = count(high, vars, cache);
+            let n = (n0 << (var0 - var - 1)) + (n1 << (var1 - var - 1));
+            cache.insert(bdd, n);
+            (n, var)
+          },
+      }
+    }
+    assert!(self.support().iter().all(|&var| var < vars), "the function depends on variables beyond {}", vars);
+    let (n, var) = count(self, vars, &mut HashMap::new());
+    n << var
+  }
+
+  // Some assignment making the function High, variables not listed are free
+  pub fn any_sat(self) -> Option<Vec<(usize, Signal)>> {
+    let mut bdd = self;
+    let mut assignment = vec![];
+    while let Some((var, low, high)) = bdd.decompose() {
+      let (value, next) = if low != Bdd::LOW { (Signal::Low, low) } else { (Signal::High, high) };
+      assignment.push((var, value));
+      bdd = next;
+    }
+    match bdd == Bdd::HIGH {
+        true => Some(assignment),
+        false => None,
+    }
+  }
+
+  // Every path from the root to High, as the variables fixed along the way
+  fn paths(self, path: &mut Vec<(usize, bool)>, visit: &mut impl FnMut(&[(usize, bool)])) {
+    match self.decompose() {
+        None if self == Bdd::HIGH => visit(path),
+        None => (),
+        Some((var, low, high)) => {
+          for (value, child) in [(false, low), (true, high)].iter() {
+            path.push((var, *value));
+            child.paths(path, visit);
+            path.pop();
+          }
+        },
+    }
+  }
+
+  // Disjoint sum of products read off the paths to High, e.g. "a & !b | !a & b"
+  pub fn formula(self, names: &[&str]) -> String {
+    let mut products = vec![];
+    self.paths(&mut vec![], &mut |path| {
+      let literals: Vec<String> = path.iter().map(|&(var, value)| {
+        let name = names.get(var).map(|name| name.to_string()).unwrap_or_else(|| format!("x{}", var));
+        if value { name } else { format!("!{}", name) }
+      }).collect();
+      products.push(if literals.is_empty() { "1".to_string() } else { literals.join(" & ") });
+    });
+    if products.is_empty() { "0".to_string() } else { products.join(" | ") }
+  }
+}
+
+// Formula with the variables named x0, x1...
+impl fmt::Display for Bdd {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.formula(&[]))
+  }
+}
+
+// A bus of variables, bit i (msb first) being variable first + i * stride. A stride of 2
+// interleaves two buses, the order that keeps adders and comparators linear in size.
+pub fn variables<const BITS: usize>(first: usize, stride: usize) -> Signals<BITS, Bdd> {
+  let mut bus = [Bdd::LOW; BITS];
+  for (i, bdd) in bus.iter_mut().enumerate() {
+    *bdd = Bdd::var(first + i * stride);
+  }
+  bus
+}
+
+// Number of nodes in the manager of the thread
+pub fn node_count() -> usize {
+  with(|manager| manager.nodes.len())
+}
+
+// Empties the manager of the thread, every Bdd created before is invalid afterwards
+pub fn clear() {
+  with(|manager| *manager = Manager::new());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{alu, gates, netlist};
+    use crate::netlist::Builder;
+    use Signal::*;
+    use std::convert::TryInto;
+
+    #[test]
+    fn tgates() {
+        let (a, b) = (Bdd::var(0), Bdd::var(1));
+        assert_eq!(gates::xor(a, b), a ^ b);
+        assert_eq!(gates::or(a, b), a | b);
+        assert_eq!(gates::not(gates::not(a)), a);
+        assert_eq!(gates::and(a, gates::not(a)), Bdd::LOW);
+        assert_eq!(gates::mux(a, b, Bdd::var(2)).formula(&["a", "b", "sel"]), "!a & b & sel | a & !b & !sel | a & b");
+        assert_eq!(gates::xor(a, b).to_string(), "!x0 & x1 | x0 & !x1");
+        assert_eq!(gates::and(a, b).sat_count(2), 1);
+        assert_eq!(gates::or(a, b).sat_count(4), 12);
+        assert_eq!(Bdd::var(3).support(), vec![3]);
+        assert_eq!(Bdd::LOW.to_string(), "0");
+        assert_eq!(Bdd::HIGH.sat_count(3), 8);
+    }
+    #[test]
+    fn tnetlist() {
+        // the library xor and a four nand arrangement sharing nothing but the function
+        let mut b = Builder::new("xor");
+        let (in1, in2) = (b.input("in1"), b.input("in2"));
+        let nand = b.nand(in1, in2);
+        let left = b.nand(in1, nand);
+        let right = b.nand(nand, in2);
+        let out = b.nand(left, right);
+        b.output("out", out);
+        let inputs = [Bdd::var(0), Bdd::var(1)];
+        assert_eq!(b.build().evaluate(&inputs), netlist::xor().evaluate(&inputs));
+        let dmux = netlist::dmux_8_way().evaluate(&[Bdd::var(0), Bdd::var(1), Bdd::var(2), Bdd::var(3)]);
+        assert!(dmux.iter().all(|out| out.sat_count(4) == 1));
+    }
+    #[test]
+    fn tadders() {
+        let (x, y) = (variables::<16>(0, 2), variables::<16>(1, 2));
+        let ripple = alu::n_adder(x, y);
+        assert_eq!(netlist::cla_adder::<16>().evaluate(&[x, y].concat()), ripple.to_vec());
+        // the msb of a sum is High for half of the inputs
+        assert_eq!(ripple[0].sat_count(32), 1 << 31);
+        assert!(ripple.iter().all(|bit| bit.size() < 100));
+        let assignment: Vec<Signal> = (0..32).map(|i| Signal::from(i % 3 == 0)).collect();
+        let (x_bits, y_bits): (Vec<Signal>, Vec<Signal>) = (0..16).map(|i| (assignment[2 * i], assignment[2 * i + 1])).unzip();
+        let sum = alu::n_adder::<16, Signal>(x_bits.try_into().unwrap(), y_bits.try_into().unwrap());
+        assert_eq!(ripple.iter().map(|bit| bit.evaluate(&assignment)).collect::<Vec<Signal>>(), sum.to_vec());
+    }
+    #[test]
+    fn talu() {
+        // controls first, then the two operands interleaved
+        let c = variables::<6>(0, 1);
+        let (x, y) = (variables::<16>(6, 2), variables::<16>(7, 2));
+        let (out, zr, ng) = alu::alu(x, y, c[0], c[1], c[2], c[3], c[4], c[5]);
+        let mut inputs = x.to_vec();
+        inputs.extend_from_slice(&y);
+        inputs.extend_from_slice(&c);
+        let mut expected = out.to_vec();
+        expected.extend_from_slice(&[zr, ng]);
+        let cla = netlist::alu_with::<16>("alu_cla", Builder::cla_adder);
+        assert_eq!(cla.evaluate(&inputs), expected);
+        // out is 0 when zx nx zy ny f no = 1 0 1 0 1 0 whatever x and y
+        let constant = c[0] & !c[1] & c[2] & !c[3] & c[4] & !c[5];
+        assert_eq!(constant & !zr, Bdd::LOW);
+        assert_eq!(ng.any_sat().map(|assignment| assignment.iter().all(|&(var, _)| var < 38)), Some(true));
+        assert!(node_count() > 2);
+    }
+    #[test]
+    fn tclear() {
+        Bdd::var(7);
+        clear();
+        assert_eq!(node_count(), 2);
+        assert_eq!(Bdd::var(0).evaluate(&[High]), High);
+        assert_eq!(Bdd::var(0).any_sat(), Some(vec![(0, High)]));
+        assert_eq!(Bdd::LOW.any_sat(), None);
+    }
+}
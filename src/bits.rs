@@ -0,0 +1,215 @@
+use crate::gates::*;
+use Signal::*;
+use std::error::Error;
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitXor, Deref, DerefMut, Not};
+use std::str::FromStr;
+
+// Operators and bit string literals for signals. Every operator goes through the nand built
+// chips of gates.rs. Signals<BITS> is an array, a foreign type, so the bus operators are
+// implemented on the Bits wrapper, which converts from and to Signals and derefs to them.
+
+impl Not for Signal {
+  type Output = Signal;
+  fn not(self) -> Signal {
+    not(self)
+  }
+}
+
+impl BitAnd for Signal {
+  type Output = Signal;
+  fn bitand(self, other: Signal) -> Signal {
+    and(self, other)
+  }
+}
+
+impl BitOr for Signal {
+  type Output = Signal;
+  fn bitor(self, other: Signal) -> Signal {
+    or(self, other)
+  }
+}
+
+impl BitXor for Signal {
+  type Output = Signal;
+  fn bitxor(self, other: Signal) -> Signal {
+    xor(self, other)
+  }
+}
+
+impl From<bool> for Signal {
+  fn from(bit: bool) -> Signal {
+    if bit { High } else { Low }
+  }
+}
+
+impl From<Signal> for bool {
+  fn from(signal: Signal) -> bool {
+    signal == High
+  }
+}
+
+impl fmt::Display for Signal {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", if *self == High { '1' } else { '0' })
+  }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ParseBitsError {
+    Length { expected: usize, found: usize },
+    Character(char),
+}
+
+impl fmt::Display for ParseBitsError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+        ParseBitsError::Length { expected, found } => write!(f, "expected {} bits, found {}", expected, found),
+        ParseBitsError::Character(c) => write!(f, "invalid bit {:?}, expected 0 or 1", c),
+    }
+  }
+}
+
+impl Error for ParseBitsError {}
+
+impl FromStr for Signal {
+  type Err = ParseBitsError;
+  fn from_str(s: &str) -> Result<Signal, ParseBitsError> {
+    let [signal] = Bits::<1>::from_str(s)?.0;
+    Ok(signal)
+  }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Bits<const BITS: usize>(pub Signals<BITS>);
+
+impl<const BITS: usize> From<Signals<BITS>> for Bits<BITS> {
+  fn from(signals: Signals<BITS>) -> Bits<BITS> {
+    Bits(signals)
+  }
+}
+
+impl<const BITS: usize> From<Bits<BITS>> for Signals<BITS> {
+  fn from(bits: Bits<BITS>) -> Signals<BITS> {
+    bits.0
+  }
+}
+
+impl<const BITS: usize> Deref for Bits<BITS> {
+  type Target = Signals<BITS>;
+  fn deref(&self) -> &Signals<BITS> {
+    &self.0
+  }
+}
+
+impl<const BITS: usize> DerefMut for Bits<BITS> {
+  fn deref_mut(&mut self) -> &mut Signals<BITS> {
+    &mut self.0
+  }
+}
+
+impl<const BITS: usize> Not for Bits<BITS> {
+  type Output = Bits<BITS>;
+  fn not(self) -> Bits<BITS> {
+    Bits(not_n(self.0))
+  }
+}
+
+impl<const BITS: usize> BitAnd for Bits<BITS> {
+  type Output = Bits<BITS>;
+  fn bitand(self, other: Bits<BITS>) -> Bits<BITS> {
+    Bits(and_n(self.0, other.0))
+  }
+}
+
+impl<const BITS: usize> BitOr for Bits<BITS> {
+  type Output = Bits<BITS>;
+  fn bitor(self, other: Bits<BITS>) -> Bits<BITS> {
+    Bits(or_n(self.0, other.0))
+  }
+}
+
+impl<const BITS: usize> BitXor for Bits<BITS> {
+  type Output = Bits<BITS>;
+  fn bitxor(self, other: Bits<BITS>) -> Bits<BITS> {
+    Bits(xor_n(self.0, other.0))
+  }
+}
+
+// Msb first, like the arrays: "0101" is [Low, High, Low, High]
+impl<const BITS: usize> fmt::Display for Bits<BITS> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for signal in self.0.iter() {
+      write!(f, "{}", signal)?;
+    }
+    Ok(())
+  }
+}
+
+// Underscores can separate groups of bits, as in Rust literals: "0101_1100"
+impl<const BITS: usize> FromStr for Bits<BITS> {
+  type Err = ParseBitsError;
+  fn from_str(s: &str) -> Result<Bits<BITS>, ParseBitsError> {
+    let mut signals = [Low; BITS];
+    let mut found = 0;
+    for c in s.chars().filter(|&c| c != '_') {
+      let signal = match c {
+          '0' => Low,
+          '1' => High,
+          _ => return Err(ParseBitsError::Character(c)),
+      };
+      if found < BITS {
+        signals[found] = signal;
+      }
+      found += 1;
+    }
+    match found {
+        _ if found == BITS => Ok(Bits(signals)),
+        _ => Err(ParseBitsError::Length { expected: BITS, found }),
+    }
+  }
+}
+
+// Signals literal for tests, panics on a malformed string
+pub fn bits<const BITS: usize>(literal: &str) -> Signals<BITS> {
+  match literal.parse::<Bits<BITS>>() {
+      Ok(bits) => bits.0,
+      Err(error) => panic!("{:?}: {}", literal, error),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::*;
+
+    #[test]
+    fn tsignal_operators() {
+        assert_eq!(!High, Low);
+        assert_eq!(High & Low, Low);
+        assert_eq!(High | Low, High);
+        assert_eq!(High ^ High, Low);
+        assert_eq!(Signal::from(true), High);
+        assert!(!bool::from(Low));
+        assert_eq!("1".parse::<Signal>(), Ok(High));
+        assert_eq!(format!("{}{}", Low, High), "01");
+    }
+    #[test]
+    fn tbits_operators() {
+        let (a, b) = (Bits(bits("0101")), Bits(bits("0011")));
+        assert_eq!(!a, Bits(bits("1010")));
+        assert_eq!(a & b, Bits(bits("0001")));
+        assert_eq!(a | b, Bits(bits("0111")));
+        assert_eq!(a ^ b, Bits(bits("0110")));
+        assert_eq!(a[1], High);
+        assert_eq!(Signals::from(a), [Low, High, Low, High]);
+    }
+    #[test]
+    fn tparse() {
+        assert_eq!(bits::<16>("0011_0000_0011_1001"), int_to_binary16(12345));
+        assert_eq!(format!("{}", Bits(int_to_binary16(-2))), "1111111111111110");
+        assert_eq!("012".parse::<Bits<3>>(), Err(ParseBitsError::Character('2')));
+        assert_eq!("0101".parse::<Bits<3>>(), Err(ParseBitsError::Length { expected: 3, found: 4 }));
+        assert_eq!(format!("{}", "".parse::<Signal>().unwrap_err()), "expected 1 bits, found 0");
+    }
+}
@@ -0,0 +1,142 @@
+use crate::gates::*;
+use Signal::*;
+
+// Bitsliced simulation: every wire is a u64 whose bit k belongs to the k-th of 64 independent
+// input vectors, so one evaluation of a chip or netlist with u64 signals checks 64 vectors.
+
+pub const LANES: usize = 64;
+
+pub fn lane(value: u64, k: usize) -> Signal {
+  if value >> k & 1 == 1 { High } else { Low }
+}
+
+// Packs up to 64 vectors, vectors[k] going to lane k. Unused lanes are Low.
+pub fn pack<const BITS: usize>(vectors: &[Signals<BITS>]) -> Signals<BITS, u64> {
+  assert!(vectors.len() <= LANES, "at most {} vectors fit in a u64", LANES);
+  let mut packed = [0; BITS];
+  for (k, vector) in vectors.iter().enumerate() {
+    for (word, &signal) in packed.iter_mut().zip(vector.iter()) {
+      *word |= ((signal == High) as u64) << k;
+    }
+  }
+  packed
+}
+
+pub fn unpack<const BITS: usize>(packed: Signals<BITS, u64>, lanes: usize) -> Vec<Signals<BITS>> {
+  (0..lanes).map(|k| unpack_lane(packed, k)).collect()
+}
+
+pub fn unpack_lane<const BITS: usize>(packed: Signals<BITS, u64>, k: usize) -> Signals<BITS> {
+  let mut vector = [Low; BITS];
+  for (signal, &word) in vector.iter_mut().zip(packed.iter()) {
+    *signal = lane(word, k);
+  }
+  vector
+}
+
+// Every vector of BITS signals in 2^BITS / 64 batches, lane k of batch b holding the msb first
+// binary of b * 64 + k. The 6 lowest bits follow the same pattern in every batch and the higher
+// ones are constant in a batch. Below 6 bits a single batch repeats the vectors across lanes.
+pub fn exhaustive<const BITS: usize>() -> impl Iterator<Item = Signals<BITS, u64>> {
+  assert!(BITS < 64 + 6, "too many inputs to enumerate");
+  let batches = 1u64 << BITS.saturating_sub(6);
+  (0..batches).map(|batch| {
+    let mut packed = [0; BITS];
+    for (i, word) in packed.iter_mut().enumerate() {
+      let bit = BITS - 1 - i;
+      *word = match bit {
+        0..=5 => (0..LANES as u64).filter(|k| k >> bit & 1 == 1).fold(0, |acc, k| acc | 1 << k),
+        _ if batch >> (bit - 6) & 1 == 1 => u64::MAX,
+        _ => 0,
+      };
+    }
+    packed
+  })
+}
+
+// Runs chip and reference on every input vector and returns the first one where they differ
+pub fn find_mismatch<const BITS: usize, F, G>(chip: F, reference: G) -> Option<Signals<BITS>>
+where
+  F: Fn(Signals<BITS, u64>) -> Vec<u64>,
+  G: Fn(Signals<BITS, u64>) -> Vec<u64>,
+{
+  exhaustive::<BITS>().find_map(|batch| {
+    let differences = chip(batch).iter().zip(reference(batch).iter()).fold(0, |acc, (a, b)| acc | (a ^ b));
+    match differences {
+      0 => None,
+      _ => Some(unpack_lane(batch, differences.trailing_zeros() as usize)),
+    }
+  })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alu;
+    use crate::netlist;
+    use crate::utilities::*;
+    use std::convert::TryInto;
+
+    fn split<const BITS: usize, const HALF: usize>(in1: Signals<BITS, u64>) -> (Signals<HALF, u64>, Signals<HALF, u64>) {
+        (in1[..HALF].try_into().unwrap(), in1[HALF..].try_into().unwrap())
+    }
+
+    #[test]
+    fn tpack() {
+        let vectors = [int_to_binary16(5), int_to_binary16(-1), int_to_binary16(12345)];
+        let packed = pack(&vectors);
+        assert_eq!(packed[15], 0b111);
+        assert_eq!(packed[0], 0b010);
+        assert_eq!(unpack(packed, 3), vectors.to_vec());
+        assert_eq!(unpack_lane(packed, 3), [Low; 16]);
+    }
+    #[test]
+    fn texhaustive() {
+        let batches: Vec<Signals<8, u64>> = exhaustive().collect();
+        assert_eq!(batches.len(), 4);
+        for (b, &batch) in batches.iter().enumerate() {
+            for k in 0..LANES {
+                assert_eq!(unpack_lane(batch, k), int_to_binary16((b * 64 + k) as i16)[8..]);
+            }
+        }
+        let small: Vec<Signals<2, u64>> = exhaustive().collect();
+        assert_eq!(unpack(small[0], 5), vec![[Low, Low], [Low, High], [High, Low], [High, High], [Low, Low]]);
+    }
+    #[test]
+    fn tbitsliced_gates() {
+        let vectors: Vec<Signals<2>> = vec![[Low, Low], [Low, High], [High, Low], [High, High]];
+        let [a, b] = pack(&vectors);
+        let expected: Vec<Signal> = vectors.iter().map(|v| xor(v[0], v[1])).collect();
+        assert_eq!((0..4).map(|k| lane(xor(a, b), k)).collect::<Vec<_>>(), expected);
+    }
+    #[test]
+    fn tadders() {
+        let mismatch = find_mismatch::<16, _, _>(
+            |in1| { let (a, b) = split::<16, 8>(in1); alu::cla_adder(a, b).to_vec() },
+            |in1| { let (a, b) = split::<16, 8>(in1); alu::n_adder(a, b).to_vec() },
+        );
+        assert_eq!(mismatch, None);
+        let mismatch = find_mismatch::<16, _, _>(
+            |in1| { let (a, b) = split::<16, 8>(in1); alu::carry_select_adder(a, b).to_vec() },
+            |in1| { let (a, b) = split::<16, 8>(in1); alu::n_adder(a, b).to_vec() },
+        );
+        assert_eq!(mismatch, None);
+        let mismatch = find_mismatch::<4, _, _>(|in1| in1.to_vec(), |in1| alu::n_incrementor(in1).to_vec());
+        assert_eq!(mismatch, Some([Low, Low, Low, Low]));
+    }
+    #[test]
+    fn talu_netlist() {
+        let netlist = netlist::alu::<4>();
+        let mismatch = find_mismatch::<14, _, _>(
+            |in1| netlist.evaluate(&in1),
+            |in1| {
+                let (a, b) = (in1[..4].try_into().unwrap(), in1[4..8].try_into().unwrap());
+                let (out, zr, ng) = alu::alu::<4, u64>(a, b, in1[8], in1[9], in1[10], in1[11], in1[12], in1[13]);
+                let mut outputs = out.to_vec();
+                outputs.extend_from_slice(&[zr, ng]);
+                outputs
+            },
+        );
+        assert_eq!(mismatch, None);
+    }
+}
@@ -0,0 +1,428 @@
+use crate::gates::{self, Signal, Signals};
+use crate::{alu, arithmetic, comparator, netlist, shifter};
+use crate::netlist::Netlist;
+use Signal::*;
+
+// Chips as values: every function of gates.rs, alu.rs and the arithmetic modules has a unit
+// struct implementing Chip, with typed input and output pin bundles, so testers, exporters or
+// counters can be written once for any chip. Sequential chips keep their state in the struct.
+
+// A bundle of pins with a fixed width: a Signal, an array of bundles or a tuple of bundles.
+// Pins are flattened in order, the same order as the ports of the chip netlists.
+pub trait Pins: Sized {
+    const WIDTH: usize;
+    fn to_signals(&self) -> Vec<Signal>;
+    fn from_signals(signals: &[Signal]) -> Self;
+}
+
+impl Pins for Signal {
+  const WIDTH: usize = 1;
+  fn to_signals(&self) -> Vec<Signal> {
+    vec![*self]
+  }
+  fn from_signals(signals: &[Signal]) -> Signal {
+    assert_eq!(signals.len(), 1, "a Signal is a single pin");
+    signals[0]
+  }
+}
+
+impl<P: Pins + Copy, const N: usize> Pins for [P; N] {
+  const WIDTH: usize = N * P::WIDTH;
+  fn to_signals(&self) -> Vec<Signal> {
+    self.iter().flat_map(|pins| pins.to_signals()).collect()
+  }
+  fn from_signals(signals: &[Signal]) -> [P; N] {
+    assert_eq!(signals.len(), Self::WIDTH, "wrong number of pins");
+    let mut pins = [P::from_signals(&vec![Low; P::WIDTH]); N];
+    for (i, p) in pins.iter_mut().enumerate() {
+      *p = P::from_signals(&signals[i * P::WIDTH..(i + 1) * P::WIDTH]);
+    }
+    pins
+  }
+}
+
+macro_rules! tuple_pins {
+  ($($p:ident),+) => {
+    impl<$($p: Pins),+> Pins for ($($p,)+) {
+      const WIDTH: usize = 0 $(+ $p::WIDTH)+;
+      #[allow(non_snake_case)]
+      fn to_signals(&self) -> Vec<Signal> {
+        let ($($p,)+) = self;
+        let mut signals = vec![];
+        $(signals.extend($p.to_signals());)+
+        signals
+      }
+      #[allow(unused_assignments)]
+      fn from_signals(signals: &[Signal]) -> Self {
+        assert_eq!(signals.len(), Self::WIDTH, "wrong number of pins");
+        let mut start = 0;
+        ($({
+          let pins = $p::from_signals(&signals[start..start + $p::WIDTH]);
+          start += $p::WIDTH;
+          pins
+        },)+)
+      }
+    }
+  };
+}
+
+tuple_pins!(A);
+tuple_pins!(A, B);
+tuple_pins!(A, B, C);
+tuple_pins!(A, B, C, D);
+tuple_pins!(A, B, C, D, E);
+tuple_pins!(A, B, C, D, E, F);
+tuple_pins!(A, B, C, D, E, F, G);
+tuple_pins!(A, B, C, D, E, F, G, H);
+tuple_pins!(A, B, C, D, E, F, G, H, I);
+tuple_pins!(A, B, C, D, E, F, G, H, I, J);
+
+// What every chip has whatever its pins, shared by Chip and AnyChip
+pub trait Component {
+    // Same as the name of the chip netlist, e.g. "alu::<16>"
+    fn name(&self) -> String;
+    // Back to the power on state, nothing to do for combinational chips
+    fn reset(&mut self) {}
+    fn netlist(&self) -> Option<Netlist> {
+      None
+    }
+}
+
+pub trait Chip: Component {
+    type Input: Pins;
+    type Output: Pins;
+    fn compute(&mut self, input: Self::Input) -> Self::Output;
+}
+
+// Chip with its pin bundles flattened, for tools handling chips of different types together
+pub trait AnyChip: Component {
+    fn input_width(&self) -> usize;
+    fn output_width(&self) -> usize;
+    fn compute_signals(&mut self, input: &[Signal]) -> Vec<Signal>;
+}
+
+impl<C: Chip> AnyChip for C {
+  fn input_width(&self) -> usize {
+    C::Input::WIDTH
+  }
+  fn output_width(&self) -> usize {
+    C::Output::WIDTH
+  }
+  fn compute_signals(&mut self, input: &[Signal]) -> Vec<Signal> {
+    self.compute(C::Input::from_signals(input)).to_signals()
+  }
+}
+
+// A netlist is a chip too, with its ports flattened in declaration order
+impl Component for Netlist {
+  fn name(&self) -> String {
+    self.name.clone()
+  }
+  fn netlist(&self) -> Option<Netlist> {
+    Some(self.clone())
+  }
+}
+
+impl AnyChip for Netlist {
+  fn input_width(&self) -> usize {
+    Netlist::input_width(self)
+  }
+  fn output_width(&self) -> usize {
+    Netlist::output_width(self)
+  }
+  fn compute_signals(&mut self, input: &[Signal]) -> Vec<Signal> {
+    self.evaluate(input)
+  }
+}
+
+macro_rules! chip {
+  ($chip:ident $(<$(const $n:ident),*>)?, $name:expr, $input:ty => $output:ty, |$args:pat| $compute:expr, $netlist:expr) => {
+    #[derive(Debug, PartialEq, Clone, Copy, Default)]
+    pub struct $chip $(<$(const $n: usize),*>)?;
+
+    impl $(<$(const $n: usize),*>)? Component for $chip $(<$($n),*>)? {
+      fn name(&self) -> String {
+        $name
+      }
+      fn netlist(&self) -> Option<Netlist> {
+        $netlist
+      }
+    }
+
+    impl $(<$(const $n: usize),*>)? Chip for $chip $(<$($n),*>)? {
+      type Input = $input;
+      type Output = $output;
+      fn compute(&mut self, $args: $input) -> $output {
+        $compute
+      }
+    }
+  };
+}
+
+type S = Signal;
+
+chip!(Nand, "nand".to_string(), (S, S) => S, |(in1, in2)| gates::nand(in1, in2), Some(netlist::nand()));
+chip!(Not, "not".to_string(), S => S, |in1| gates::not(in1), Some(netlist::not()));
+chip!(And, "and".to_string(), (S, S) => S, |(in1, in2)| gates::and(in1, in2), Some(netlist::and()));
+chip!(Or, "or".to_string(), (S, S) => S, |(in1, in2)| gates::or(in1, in2), Some(netlist::or()));
+chip!(Xor, "xor".to_string(), (S, S) => S, |(in1, in2)| gates::xor(in1, in2), Some(netlist::xor()));
+chip!(Mux, "mux".to_string(), (S, S, S) => S, |(in1, in2, sel)| gates::mux(in1, in2, sel), Some(netlist::mux()));
+chip!(Dmux, "dmux".to_string(), (S, S) => (S, S), |(in1, sel)| gates::dmux(in1, sel), Some(netlist::dmux()));
+chip!(NotN<const BITS>, format!("not_n::<{}>", BITS), Signals<BITS> => Signals<BITS>,
+  |in1| gates::not_n(in1), Some(netlist::not_n::<BITS>()));
+chip!(AndN<const BITS>, format!("and_n::<{}>", BITS), (Signals<BITS>, Signals<BITS>) => Signals<BITS>,
+  |(in1, in2)| gates::and_n(in1, in2), Some(netlist::and_n::<BITS>()));
+chip!(OrN<const BITS>, format!("or_n::<{}>", BITS), (Signals<BITS>, Signals<BITS>) => Signals<BITS>,
+  |(in1, in2)| gates::or_n(in1, in2), Some(netlist::or_n::<BITS>()));
+chip!(XorN<const BITS>, format!("xor_n::<{}>", BITS), (Signals<BITS>, Signals<BITS>) => Signals<BITS>,
+  |(in1, in2)| gates::xor_n(in1, in2), Some(netlist::xor_n::<BITS>()));
+chip!(MuxN<const BITS>, format!("mux_n::<{}>", BITS), (Signals<BITS>, Signals<BITS>, S) => Signals<BITS>,
+  |(in1, in2, sel)| gates::mux_n(in1, in2, sel), Some(netlist::mux_n::<BITS>()));
+chip!(OrMWay<const BITS>, format!("or_m_way::<{}>", BITS), Signals<BITS> => S,
+  |in1| gates::or_m_way(in1), Some(netlist::or_m_way::<BITS>()));
+chip!(AndMWay<const BITS>, format!("and_m_way::<{}>", BITS), Signals<BITS> => S,
+  |in1| gates::and_m_way(in1), Some(netlist::and_m_way::<BITS>()));
+chip!(Mux4WayN<const BITS>, format!("mux_4_way_n::<{}>", BITS), ([Signals<BITS>; 4], S, S) => Signals<BITS>,
+  |(ins, sel1, sel2)| gates::mux_4_way_n(ins[0], ins[1], ins[2], ins[3], sel1, sel2), Some(netlist::mux_4_way_n::<BITS>()));
+chip!(Mux8WayN<const BITS>, format!("mux_8_way_n::<{}>", BITS), ([Signals<BITS>; 8], S, S, S) => Signals<BITS>,
+  |(ins, sel1, sel2, sel3)| gates::mux_8_way_n(ins[0], ins[1], ins[2], ins[3], ins[4], ins[5], ins[6], ins[7], sel1, sel2, sel3),
+  Some(netlist::mux_8_way_n::<BITS>()));
+chip!(Dmux4Way, "dmux_4_way".to_string(), (S, S, S) => Signals<4>,
+  |(in1, sel1, sel2)| gates::dmux_4_way(in1, sel1, sel2), Some(netlist::dmux_4_way()));
+chip!(Dmux8Way, "dmux_8_way".to_string(), (S, S, S, S) => Signals<8>,
+  |(in1, sel1, sel2, sel3)| gates::dmux_8_way(in1, sel1, sel2, sel3), Some(netlist::dmux_8_way()));
+chip!(Decoder<const N, const WAYS>, format!("decoder::<{}, {}>", N, WAYS), Signals<N> => Signals<WAYS>,
+  |sel| gates::decoder(sel), None);
+chip!(Encoder<const N, const WAYS>, format!("encoder::<{}, {}>", N, WAYS), Signals<WAYS> => Signals<N>,
+  |in1| gates::encoder(in1), None);
+chip!(PriorityEncoder<const N, const WAYS>, format!("priority_encoder::<{}, {}>", N, WAYS), Signals<WAYS> => (Signals<N>, S),
+  |in1| gates::priority_encoder(in1), None);
+chip!(Mux2nWay<const BITS, const N, const WAYS>, format!("mux_2n_way::<{}, {}, {}>", BITS, N, WAYS),
+  ([Signals<BITS>; WAYS], Signals<N>) => Signals<BITS>, |(ins, sel)| gates::mux_2n_way(ins, sel), None);
+chip!(Dmux2nWay<const N, const WAYS>, format!("dmux_2n_way::<{}, {}>", N, WAYS), (S, Signals<N>) => Signals<WAYS>,
+  |(in1, sel)| gates::dmux_2n_way(in1, sel), None);
+
+chip!(HalfAdder, "half_adder".to_string(), (S, S) => (S, S), |(in1, in2)| alu::half_adder(in1, in2), Some(netlist::half_adder()));
+chip!(FullAdder, "full_adder".to_string(), (S, S, S) => (S, S),
+  |(in1, in2, carry)| alu::full_adder(in1, in2, carry), Some(netlist::full_adder()));
+chip!(NAdder<const BITS>, format!("n_adder::<{}>", BITS), (Signals<BITS>, Signals<BITS>) => Signals<BITS>,
+  |(in1, in2)| alu::n_adder(in1, in2), Some(netlist::n_adder::<BITS>()));
+chip!(NAdderCarry<const BITS>, format!("n_adder_carry::<{}>", BITS), (Signals<BITS>, Signals<BITS>, S) => (Signals<BITS>, S),
+  |(in1, in2, carry)| alu::n_adder_carry(in1, in2, carry), Some(netlist::n_adder_carry::<BITS>()));
+chip!(ClaAdder<const BITS>, format!("cla_adder::<{}>", BITS), (Signals<BITS>, Signals<BITS>) => Signals<BITS>,
+  |(in1, in2)| alu::cla_adder(in1, in2), Some(netlist::cla_adder::<BITS>()));
+chip!(CarrySelectAdder<const BITS>, format!("carry_select_adder::<{}>", BITS), (Signals<BITS>, Signals<BITS>) => Signals<BITS>,
+  |(in1, in2)| alu::carry_select_adder(in1, in2), Some(netlist::carry_select_adder::<BITS>()));
+chip!(NIncrementor<const BITS>, format!("n_incrementor::<{}>", BITS), Signals<BITS> => Signals<BITS>,
+  |in1| alu::n_incrementor(in1), Some(netlist::n_incrementor::<BITS>()));
+chip!(Alu<const BITS>, format!("alu::<{}>", BITS), (Signals<BITS>, Signals<BITS>, S, S, S, S, S, S) => (Signals<BITS>, S, S),
+  |(bits1, bits2, zx, nx, zy, ny, f, no)| alu::alu(bits1, bits2, zx, nx, zy, ny, f, no), Some(netlist::alu::<BITS>()));
+chip!(AluFlags<const BITS>, format!("alu_flags::<{}>", BITS),
+  (Signals<BITS>, Signals<BITS>, S, S, S, S, S, S) => (Signals<BITS>, S, S, S, S),
+  |(bits1, bits2, zx, nx, zy, ny, f, no)| alu::alu_flags(bits1, bits2, zx, nx, zy, ny, f, no), None);
+chip!(ExtendedAlu<const BITS>, format!("extended_alu::<{}>", BITS),
+  (Signals<BITS>, Signals<BITS>, S, S, S, S, S, S, S, S) => (Signals<BITS>, S, S, S, S),
+  |(bits1, bits2, x1, x0, zx, nx, zy, ny, f, no)| alu::extended_alu(bits1, bits2, x1, x0, zx, nx, zy, ny, f, no), None);
+chip!(ExtendedJump, "extended_jump".to_string(), (S, S, S, S, S, S, S, S, S) => S,
+  |(x1, x0, j2, j1, j0, zr, ng, carry, overflow)| alu::extended_jump(x1, x0, j2, j1, j0, zr, ng, carry, overflow), None);
+
+chip!(Multiplier<const BITS>, format!("multiplier::<{}>", BITS), (Signals<BITS>, Signals<BITS>) => (Signals<BITS>, Signals<BITS>),
+  |(in1, in2)| arithmetic::multiplier(in1, in2), Some(netlist::multiplier::<BITS>()));
+chip!(RestoringDivider<const BITS>, format!("restoring_divider::<{}>", BITS),
+  (Signals<BITS>, Signals<BITS>) => (Signals<BITS>, Signals<BITS>),
+  |(in1, in2)| arithmetic::restoring_divider(in1, in2), Some(netlist::restoring_divider::<BITS>()));
+chip!(NonRestoringDivider<const BITS>, format!("non_restoring_divider::<{}>", BITS),
+  (Signals<BITS>, Signals<BITS>) => (Signals<BITS>, Signals<BITS>),
+  |(in1, in2)| arithmetic::non_restoring_divider(in1, in2), Some(netlist::non_restoring_divider::<BITS>()));
+chip!(ShiftLeft<const BITS, const SEL>, format!("shift_left::<{}, {}>", BITS, SEL), (Signals<BITS>, Signals<SEL>) => Signals<BITS>,
+  |(in1, amount)| shifter::shift_left(in1, amount), None);
+chip!(ShiftRight<const BITS, const SEL>, format!("shift_right::<{}, {}>", BITS, SEL), (Signals<BITS>, Signals<SEL>) => Signals<BITS>,
+  |(in1, amount)| shifter::shift_right(in1, amount), None);
+chip!(ShiftRightArithmetic<const BITS, const SEL>, format!("shift_right_arithmetic::<{}, {}>", BITS, SEL),
+  (Signals<BITS>, Signals<SEL>) => Signals<BITS>, |(in1, amount)| shifter::shift_right_arithmetic(in1, amount), None);
+chip!(RotateLeft<const BITS, const SEL>, format!("rotate_left::<{}, {}>", BITS, SEL), (Signals<BITS>, Signals<SEL>) => Signals<BITS>,
+  |(in1, amount)| shifter::rotate_left(in1, amount), None);
+chip!(RotateRight<const BITS, const SEL>, format!("rotate_right::<{}, {}>", BITS, SEL), (Signals<BITS>, Signals<SEL>) => Signals<BITS>,
+  |(in1, amount)| shifter::rotate_right(in1, amount), None);
+chip!(EqN<const BITS>, format!("eq_n::<{}>", BITS), (Signals<BITS>, Signals<BITS>) => S,
+  |(in1, in2)| comparator::eq_n(in1, in2), None);
+chip!(LtN<const BITS>, format!("lt_n::<{}>", BITS), (Signals<BITS>, Signals<BITS>) => S,
+  |(in1, in2)| comparator::lt_n(in1, in2), None);
+chip!(LtUnsignedN<const BITS>, format!("lt_unsigned_n::<{}>", BITS), (Signals<BITS>, Signals<BITS>) => S,
+  |(in1, in2)| comparator::lt_unsigned_n(in1, in2), None);
+
+// Sequential chips. compute is one clock cycle: the output is the state before the tick.
+
+// The primitive flip flop of the Hack platform, out(t) = in(t - 1)
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Dff {
+    pub state: Signal,
+}
+
+impl Default for Dff {
+  fn default() -> Dff {
+    Dff { state: Low }
+  }
+}
+
+impl Component for Dff {
+  fn name(&self) -> String {
+    "dff".to_string()
+  }
+  fn reset(&mut self) {
+    self.state = Low;
+  }
+}
+
+impl Chip for Dff {
+  type Input = S;
+  type Output = S;
+  fn compute(&mut self, in1: S) -> S {
+    std::mem::replace(&mut self.state, in1)
+  }
+}
+
+// One bit register, keeps its value until load is High
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Bit {
+    pub dff: Dff,
+}
+
+impl Component for Bit {
+  fn name(&self) -> String {
+    "bit".to_string()
+  }
+  fn reset(&mut self) {
+    self.dff.reset();
+  }
+}
+
+impl Chip for Bit {
+  type Input = (S, S);
+  type Output = S;
+  fn compute(&mut self, (in1, load): (S, S)) -> S {
+    let out = self.dff.state;
+    self.dff.compute(gates::mux(out, in1, load))
+  }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Register<const BITS: usize> {
+    pub bits: [Bit; BITS],
+}
+
+impl<const BITS: usize> Default for Register<BITS> {
+  fn default() -> Register<BITS> {
+    Register { bits: [Bit::default(); BITS] }
+  }
+}
+
+impl<const BITS: usize> Component for Register<BITS> {
+  fn name(&self) -> String {
+    format!("register::<{}>", BITS)
+  }
+  fn reset(&mut self) {
+    self.bits.iter_mut().for_each(Bit::reset);
+  }
+}
+
+impl<const BITS: usize> Chip for Register<BITS> {
+  type Input = (Signals<BITS>, S);
+  type Output = Signals<BITS>;
+  fn compute(&mut self, (in1, load): (Signals<BITS>, S)) -> Signals<BITS> {
+    let mut out = [Low; BITS];
+    for ((signal, bit), &s) in out.iter_mut().zip(self.bits.iter_mut()).zip(in1.iter()) {
+      *signal = bit.compute((s, load));
+    }
+    out
+  }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bits::bits;
+    use crate::utilities::*;
+
+    // Every chip with a netlist must compute the same outputs as its netlist
+    fn check_netlist(chip: &mut dyn AnyChip, vectors: usize) {
+        let netlist = chip.netlist().unwrap();
+        assert_eq!(netlist.name, chip.name());
+        assert_eq!((netlist.input_width(), netlist.output_width()), (chip.input_width(), chip.output_width()));
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..vectors {
+            let input: Vec<Signal> = (0..chip.input_width()).map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                Signal::from(seed & 1 == 1)
+            }).collect();
+            assert_eq!(chip.compute_signals(&input), netlist.evaluate(&input), "{} on {:?}", chip.name(), input);
+        }
+    }
+
+    #[test]
+    fn tgates() {
+        assert_eq!(Nand.compute((High, High)), Low);
+        assert_eq!(Not.compute(Low), High);
+        assert_eq!(And.compute((High, Low)), Low);
+        assert_eq!(Or.compute((High, Low)), High);
+        assert_eq!(Xor.compute((High, High)), Low);
+        assert_eq!(Mux.compute((High, Low, High)), Low);
+        assert_eq!(Dmux.compute((High, High)), (Low, High));
+        assert_eq!(NotN::<4>.compute(bits("0110")), bits("1001"));
+        assert_eq!(Mux4WayN::<2>.compute(([bits("00"), bits("01"), bits("10"), bits("11")], Low, High)), bits("10"));
+        assert_eq!(Decoder::<2, 4>.compute(bits("10")), bits("0010"));
+        assert_eq!(Alu::<16>.name(), "alu::<16>");
+    }
+    #[test]
+    fn tpins() {
+        assert_eq!(<(Signals<16>, Signals<16>, S, S, S, S, S, S)>::WIDTH, 38);
+        let input = (bits::<3>("101"), High, [bits::<2>("01"), bits("10")]);
+        let signals = input.to_signals();
+        assert_eq!(signals, vec![High, Low, High, High, Low, High, High, Low]);
+        assert_eq!(<(Signals<3>, S, [Signals<2>; 2])>::from_signals(&signals), input);
+    }
+    #[test]
+    fn tnetlists() {
+        let mut chips: Vec<Box<dyn AnyChip>> = vec![
+            Box::new(Nand), Box::new(Not), Box::new(And), Box::new(Or), Box::new(Xor), Box::new(Mux), Box::new(Dmux),
+            Box::new(NotN::<4>), Box::new(AndN::<4>), Box::new(OrN::<4>), Box::new(XorN::<4>), Box::new(MuxN::<4>),
+            Box::new(OrMWay::<8>), Box::new(AndMWay::<8>), Box::new(Mux4WayN::<3>), Box::new(Mux8WayN::<3>),
+            Box::new(Dmux4Way), Box::new(Dmux8Way), Box::new(HalfAdder), Box::new(FullAdder),
+            Box::new(NAdder::<6>), Box::new(NAdderCarry::<6>), Box::new(ClaAdder::<6>), Box::new(CarrySelectAdder::<6>),
+            Box::new(NIncrementor::<6>), Box::new(Alu::<6>), Box::new(Multiplier::<4>),
+            Box::new(RestoringDivider::<4>), Box::new(NonRestoringDivider::<4>),
+        ];
+        for chip in chips.iter_mut() {
+            check_netlist(chip.as_mut(), 64);
+        }
+        assert!(ExtendedAlu::<16>.netlist().is_none());
+    }
+    #[test]
+    fn talu() {
+        let (a, b) = (int_to_binary16(1234), int_to_binary16(-77));
+        let mut chip: Box<dyn AnyChip> = Box::new(Alu::<16>);
+        let mut input = a.to_vec();
+        input.extend_from_slice(&b);
+        input.extend_from_slice(&[Low, Low, Low, Low, High, Low]);
+        assert_eq!(chip.compute_signals(&input), (int_to_binary16(1157), Low, Low).to_signals());
+        assert_eq!(Alu.compute((a, b, Low, Low, Low, Low, High, Low)), alu::alu(a, b, Low, Low, Low, Low, High, Low));
+    }
+    #[test]
+    fn tsequential() {
+        let mut dff = Dff::default();
+        assert_eq!([dff.compute(High), dff.compute(Low), dff.compute(Low)], [Low, High, Low]);
+        let mut register = Register::<4>::default();
+        assert_eq!(register.compute((bits("1010"), High)), bits("0000"));
+        assert_eq!(register.compute((bits("0110"), Low)), bits("1010"));
+        assert_eq!(register.compute((bits("0110"), High)), bits("1010"));
+        assert_eq!(register.compute((bits("0000"), Low)), bits("0110"));
+        register.reset();
+        assert_eq!(register.compute((bits("0000"), Low)), bits("0000"));
+        let mut chip: Box<dyn AnyChip> = Box::new(Bit::default());
+        assert_eq!((chip.input_width(), chip.output_width()), (2, 1));
+        chip.compute_signals(&[High, High]);
+        assert_eq!(chip.compute_signals(&[Low, Low]), vec![High]);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::gates::*;
+use crate::alu::n_adder_carry;
+
+pub fn eq_n<const BITS: usize, S: Logic>(in1: Signals<BITS, S>, in2: Signals<BITS, S>) -> S {
+  not(or_m_way(xor_n(in1, in2)))
+}
+
+// in1 - in2 computed as in1 + !in2 + 1 borrows exactly when in1 < in2
+pub fn lt_unsigned_n<const BITS: usize, S: Logic>(in1: Signals<BITS, S>, in2: Signals<BITS, S>) -> S {
+  let (_, carry) = n_adder_carry(in1, not_n(in2), S::HIGH);
+  not(carry)
+}
+
+// Two's complement comparison: the unsigned order is reversed when the signs differ
+pub fn lt_n<const BITS: usize, S: Logic>(in1: Signals<BITS, S>, in2: Signals<BITS, S>) -> S {
+  xor(lt_unsigned_n(in1, in2), xor(in1[0], in2[0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Signal::*;
+    use crate::utilities::*;
+    use std::convert::TryInto;
+
+    fn bits5(value: i16) -> Signals<5> {
+        int_to_binary16(value)[11..].try_into().unwrap()
+    }
+    fn signal(value: bool) -> Signal {
+        if value { High } else { Low }
+    }
+
+    #[test]
+    fn teq_n() {
+        assert_eq!(eq_n([Low, High], [Low, High]), High);
+        assert_eq!(eq_n([Low, High], [High, High]), Low);
+        for a in 0..32 {
+            for b in 0..32 {
+                assert_eq!(eq_n(bits5(a), bits5(b)), signal(a == b));
+            }
+        }
+    }
+    #[test]
+    fn tlt_unsigned_n() {
+        assert_eq!(lt_unsigned_n([Low, High], [High, Low]), High);
+        assert_eq!(lt_unsigned_n([High, High], [Low, High]), Low);
+        for a in 0..32 {
+            for b in 0..32 {
+                assert_eq!(lt_unsigned_n(bits5(a), bits5(b)), signal(a < b));
+            }
+        }
+        assert_eq!(lt_unsigned_n(int_to_binary16(1), int_to_binary16(-1)), High);
+    }
+    #[test]
+    fn tlt_n() {
+        assert_eq!(lt_n([High, High], [Low, High]), High);
+        for a in -16..16 {
+            for b in -16..16 {
+                assert_eq!(lt_n(bits5(a), bits5(b)), signal(a < b));
+            }
+        }
+        for &(a, b) in [(-32768i16, 32767i16), (32767, -32768), (-1, 0), (0, -1), (1234, 1234), (-300, -299)].iter() {
+            assert_eq!(lt_n(int_to_binary16(a), int_to_binary16(b)), signal(a < b));
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use crate::netlist::*;
+use std::fmt;
+
+// Nand count of a chip, broken down by subchip. Instances of the same subchip at the
+// same level of the hierarchy are merged, so a 16-bit mux_n shows up as "mux x16".
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Breakdown {
+    pub name: String,
+    pub instances: usize,
+    pub nands: usize,
+    pub subchips: Vec<Breakdown>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CostReport {
+    pub chip: String,
+    pub nands: usize,
+    pub depth: usize,
+    pub breakdown: Vec<Breakdown>,
+}
+
+// Length in nands of the longest path from an input or constant to an output
+pub fn depth(netlist: &Netlist) -> usize {
+  let mut depths = vec![0; netlist.nodes.len()];
+  for (wire, node) in netlist.nodes.iter().enumerate() {
+    if let Gate::Nand(in1, in2) = node.gate {
+      depths[wire] = 1 + depths[in1].max(depths[in2]);
+    }
+  }
+  netlist.outputs.iter().flat_map(|port| port.wires.iter()).map(|&wire| depths[wire]).max().unwrap_or(0)
+}
+
+fn merge(into: &mut Vec<Breakdown>, breakdown: Breakdown) {
+  match into.iter_mut().find(|other| other.name == breakdown.name) {
+    Some(other) => {
+      other.instances += breakdown.instances;
+      other.nands += breakdown.nands;
+      for subchip in breakdown.subchips {
+        merge(&mut other.subchips, subchip);
+      }
+    },
+    None => into.push(breakdown),
+  }
+}
+
+fn breakdown(scope: usize, own: &[usize], children: &[Vec<usize>], netlist: &Netlist) -> Breakdown {
+  let mut subchips = vec![];
+  for &child in children[scope].iter() {
+    merge(&mut subchips, breakdown(child, own, children, netlist));
+  }
+  Breakdown {
+    name: netlist.scopes[scope].name.clone(),
+    instances: 1,
+    nands: own[scope] + subchips.iter().map(|subchip| subchip.nands).sum::<usize>(),
+    subchips,
+  }
+}
+
+pub fn cost(netlist: &Netlist) -> CostReport {
+  let mut own = vec![0; netlist.scopes.len()];
+  for node in netlist.nodes.iter() {
+    if let Gate::Nand(_, _) = node.gate {
+      own[node.scope] += 1;
+    }
+  }
+  let mut children = vec![vec![]; netlist.scopes.len()];
+  for (scope, parent) in netlist.scopes.iter().enumerate().filter_map(|(i, scope)| scope.parent.map(|parent| (i, parent))) {
+    children[parent].push(scope);
+  }
+  CostReport {
+    chip: netlist.name.clone(),
+    nands: netlist.nand_count(),
+    depth: depth(netlist),
+    breakdown: breakdown(0, &own, &children, netlist).subchips,
+  }
+}
+
+fn write_breakdown(f: &mut fmt::Formatter, breakdown: &[Breakdown], indent: usize) -> fmt::Result {
+  for subchip in breakdown {
+    writeln!(f, "{:indent$}{} x{}: {} nands", "", subchip.name, subchip.instances, subchip.nands, indent = indent)?;
+    write_breakdown(f, &subchip.subchips, indent + 2)?;
+  }
+  Ok(())
+}
+
+impl fmt::Display for CostReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "{}: {} nands, depth {}", self.chip, self.nands, self.depth)?;
+    write_breakdown(f, &self.breakdown, 2)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tdepth() {
+        assert_eq!(depth(&not()), 1);
+        assert_eq!(depth(&and()), 2);
+        assert_eq!(depth(&xor()), 3);
+        assert_eq!(depth(&mux()), 3);
+    }
+    #[test]
+    fn tcost_xor() {
+        let report = cost(&xor());
+        assert_eq!(report.nands, 5);
+        assert_eq!(report.breakdown, vec![Breakdown { name: "xor".to_string(), instances: 1, nands: 5, subchips: vec![] }]);
+    }
+    #[test]
+    fn tcost_mux_8_way_n() {
+        let report = cost(&mux_8_way_n::<16>());
+        assert_eq!(report.nands, 7 * 16 * 4);
+        let mux_8_way = &report.breakdown[0];
+        assert_eq!(mux_8_way.subchips.iter().map(|s| (s.name.as_str(), s.instances, s.nands)).collect::<Vec<_>>(),
+            vec![("mux_4_way_n", 2, 6 * 16 * 4), ("mux_n", 1, 16 * 4)]);
+        assert_eq!(mux_8_way.subchips[1].subchips[0].instances, 16);
+    }
+    #[test]
+    fn tcost_n_adder() {
+        let report = cost(&n_adder::<16>());
+        let full_adder = &report.breakdown[0].subchips[0];
+        assert_eq!((full_adder.name.as_str(), full_adder.instances), ("full_adder", 16));
+        assert_eq!(report.nands, 16 * (2 * (5 + 3) + 3));
+    }
+    #[test]
+    fn tcost_alu() {
+        let report = cost(&alu::<16>());
+        assert_eq!(report.nands, report.breakdown[0].subchips.iter().map(|s| s.nands).sum::<usize>());
+        let text = format!("{}", report);
+        assert!(text.starts_with(&format!("alu::<16>: {} nands, depth {}\n  alu x1:", report.nands, report.depth)));
+        assert!(text.contains("    n_adder x1: 304 nands\n      full_adder x16: 304 nands\n"));
+    }
+}
@@ -0,0 +1,267 @@
+use crate::gates::*;
+use crate::netlist::{Gate, Netlist};
+use crate::sat::{Lit, Solver};
+use crate::chip::AnyChip;
+use crate::bdd::Bdd;
+use Signal::*;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+// Combinational equivalence of two netlists with the same interface. Both are encoded in a
+// single sat problem sharing the input variables, a miter asks for an input where any pair
+// of outputs differs: unsatisfiable means equivalent, a model is a counterexample. Nands are
+// hashed on their inputs so the logic the two chips have in common is encoded only once,
+// and nots are folded into negated literals.
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum EquivalenceError {
+    Interface { inputs: (usize, usize), outputs: (usize, usize) },
+    // A chip without a netlist cannot be encoded
+    NoNetlist(String),
+}
+
+impl fmt::Display for EquivalenceError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+        EquivalenceError::Interface { inputs, outputs } => write!(f, "{} inputs and {} outputs against {} inputs and {} outputs",
+          inputs.0, outputs.0, inputs.1, outputs.1),
+        EquivalenceError::NoNetlist(name) => write!(f, "{} has no netlist", name),
+    }
+  }
+}
+
+impl Error for EquivalenceError {}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Equivalence {
+    Equivalent,
+    Counterexample { input: Vec<Signal>, outputs1: Vec<Signal>, outputs2: Vec<Signal> },
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Value {
+    Constant(bool),
+    Literal(Lit),
+}
+
+impl std::ops::Not for Value {
+  type Output = Value;
+  fn not(self) -> Value {
+    match self {
+        Value::Constant(value) => Value::Constant(!value),
+        Value::Literal(lit) => Value::Literal(!lit),
+    }
+  }
+}
+
+#[derive(Debug, Default)]
+struct Encoder {
+    solver: Solver,
+    // Variable of the and of two literals, the nand being its negation
+    ands: HashMap<(Lit, Lit), Lit>,
+}
+
+impl Encoder {
+  fn nand(&mut self, in1: Value, in2: Value) -> Value {
+    let (a, b) = match (in1, in2) {
+        (Value::Constant(false), _) | (_, Value::Constant(false)) => return Value::Constant(true),
+        (Value::Constant(true), other) | (other, Value::Constant(true)) => return !other,
+        (Value::Literal(a), Value::Literal(b)) if a == b => return Value::Literal(!a),
+        (Value::Literal(a), Value::Literal(b)) if a == !b => return Value::Constant(true),
+        (Value::Literal(a), Value::Literal(b)) => (a.min(b), a.max(b)),
+    };
+    let solver = &mut self.solver;
+    let and = *self.ands.entry((a, b)).or_insert_with(|| {
+      let and = Lit::positive(solver.new_var());
+      solver.add_clause(&[!and, a]);
+      solver.add_clause(&[!and, b]);
+      solver.add_clause(&[and, !a, !b]);
+      and
+    });
+    Value::Literal(!and)
+  }
+
+  fn xor(&mut self, in1: Value, in2: Value) -> Value {
+    let (a, b) = match (in1, in2) {
+        (Value::Constant(value), other) | (other, Value::Constant(value)) => return if value { !other } else { other },
+        (Value::Literal(a), Value::Literal(b)) if a == b => return Value::Constant(false),
+        (Value::Literal(a), Value::Literal(b)) if a == !b => return Value::Constant(true),
+        (Value::Literal(a), Value::Literal(b)) => (a, b),
+    };
+    let xor = Lit::positive(self.solver.new_var());
+    self.solver.add_clause(&[!xor, a, b]);
+    self.solver.add_clause(&[!xor, !a, !b]);
+    self.solver.add_clause(&[xor, !a, b]);
+    self.solver.add_clause(&[xor, a, !b]);
+    Value::Literal(xor)
+  }
+
+  // Values of the outputs of the netlist, given the values of its inputs in port order
+  fn encode(&mut self, netlist: &Netlist, inputs: &[Value]) -> Vec<Value> {
+    let mut values = vec![Value::Constant(false); netlist.nodes.len()];
+    for (&wire, &value) in netlist.inputs.iter().flat_map(|port| port.wires.iter()).zip(inputs.iter()) {
+      values[wire] = value;
+    }
+    for (wire, node) in netlist.nodes.iter().enumerate() {
+      match node.gate {
+          Gate::Input => (),
+          Gate::Constant(signal) => values[wire] = Value::Constant(signal == High),
+          Gate::Nand(in1, in2) => values[wire] = self.nand(values[in1], values[in2]),
+      }
+    }
+    netlist.outputs.iter().flat_map(|port| port.wires.iter()).map(|&wire| values[wire]).collect()
+  }
+}
+
+fn check_interface(netlist1: &Netlist, netlist2: &Netlist) -> Result<(), EquivalenceError> {
+  match netlist1.input_width() == netlist2.input_width() && netlist1.output_width() == netlist2.output_width() {
+      true => Ok(()),
+      false => Err(EquivalenceError::Interface {
+        inputs: (netlist1.input_width(), netlist2.input_width()),
+        outputs: (netlist1.output_width(), netlist2.output_width()),
+      }),
+  }
+}
+
+// Proves the netlists compute the same function of their flattened inputs, or finds an input
+// where they differ. Port names do not matter, only their order and widths.
+pub fn check(netlist1: &Netlist, netlist2: &Netlist) -> Result<Equivalence, EquivalenceError> {
+  check_interface(netlist1, netlist2)?;
+  let mut encoder = Encoder::default();
+  let inputs: Vec<Value> = (0..netlist1.input_width()).map(|_| Value::Literal(Lit::positive(encoder.solver.new_var()))).collect();
+  let outputs1 = encoder.encode(netlist1, &inputs);
+  let outputs2 = encoder.encode(netlist2, &inputs);
+  let mut differences = vec![];
+  let mut always = false;
+  for (&out1, &out2) in outputs1.iter().zip(outputs2.iter()) {
+    match encoder.xor(out1, out2) {
+        Value::Constant(false) => (),
+        // differ whatever the input, any model will do
+        Value::Constant(true) => always = true,
+        Value::Literal(lit) => differences.push(lit),
+    }
+  }
+  if !always {
+    if differences.is_empty() {
+      return Ok(Equivalence::Equivalent);
+    }
+    encoder.solver.add_clause(&differences);
+  }
+  match encoder.solver.solve() {
+      None => Ok(Equivalence::Equivalent),
+      Some(model) => {
+        let input: Vec<Signal> = (0..netlist1.input_width()).map(|var| Signal::from(model[var])).collect();
+        Ok(Equivalence::Counterexample { outputs1: netlist1.evaluate(&input), outputs2: netlist2.evaluate(&input), input })
+      },
+  }
+}
+
+// Same check evaluating both netlists on bdds, input i being variable order[i]. Fast when the
+// order suits the chip, e.g. interleaved operands for adders, hopeless otherwise.
+pub fn check_with_bdds(netlist1: &Netlist, netlist2: &Netlist, order: &[usize]) -> Result<Equivalence, EquivalenceError> {
+  check_interface(netlist1, netlist2)?;
+  assert_eq!(order.len(), netlist1.input_width(), "one variable per input");
+  let inputs: Vec<Bdd> = order.iter().map(|&var| Bdd::var(var)).collect();
+  let outputs1 = netlist1.evaluate(&inputs);
+  let outputs2 = netlist2.evaluate(&inputs);
+  let difference = outputs1.iter().zip(outputs2.iter()).fold(Bdd::LOW, |acc, (&out1, &out2)| acc | (out1 ^ out2));
+  match difference.any_sat() {
+      None => Ok(Equivalence::Equivalent),
+      Some(assignment) => {
+        let mut values = vec![Low; order.iter().max().map_or(0, |&var| var + 1)];
+        for (var, value) in assignment {
+          values[var] = value;
+        }
+        let input: Vec<Signal> = order.iter().map(|&var| values[var]).collect();
+        Ok(Equivalence::Counterexample { outputs1: netlist1.evaluate(&input), outputs2: netlist2.evaluate(&input), input })
+      },
+  }
+}
+
+// Same check on chips, through their netlists
+pub fn check_chips(chip1: &dyn AnyChip, chip2: &dyn AnyChip) -> Result<Equivalence, EquivalenceError> {
+  let netlist = |chip: &dyn AnyChip| chip.netlist().ok_or_else(|| EquivalenceError::NoNetlist(chip.name()));
+  check(&netlist(chip1)?, &netlist(chip2)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netlist::{self, Builder};
+    use crate::synthesis;
+    use crate::chip::{Dff, NAdder, ClaAdder};
+
+    fn counterexample(result: Result<Equivalence, EquivalenceError>) -> (Vec<Signal>, Vec<Signal>, Vec<Signal>) {
+        match result {
+            Ok(Equivalence::Counterexample { input, outputs1, outputs2 }) => (input, outputs1, outputs2),
+            other => panic!("expected a counterexample, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn tgates() {
+        let function = synthesis::from_expressions(&[("out", "a & !b | !a & b")]).unwrap();
+        assert_eq!(check(&netlist::xor(), &synthesis::synthesize("xor", &function)), Ok(Equivalence::Equivalent));
+        assert_eq!(check(&netlist::xor(), &netlist::xor()), Ok(Equivalence::Equivalent));
+        let (input, outputs1, outputs2) = counterexample(check(&netlist::and(), &netlist::or()));
+        assert_ne!(input[0], input[1]);
+        assert_eq!((outputs1, outputs2), (vec![Low], vec![High]));
+        // the outputs of not(nand(a, a)) and a are the same literal
+        let mut b = Builder::new("buffer");
+        let a = b.input("a");
+        let not = b.not(a);
+        let out = b.not(not);
+        b.output("out", out);
+        let buffer = b.build();
+        let mut b = Builder::new("wire");
+        let a = b.input("a");
+        b.output("out", a);
+        assert_eq!(check(&buffer, &b.build()), Ok(Equivalence::Equivalent));
+        // and constant outputs are folded away
+        let mut b = Builder::new("one");
+        b.input("a");
+        let high = b.constant(High);
+        b.output("out", high);
+        let (_, outputs1, outputs2) = counterexample(check(&netlist::not(), &b.build()));
+        assert_eq!((outputs1, outputs2), (vec![Low], vec![High]));
+    }
+    #[test]
+    fn tinterface() {
+        assert_eq!(check(&netlist::not(), &netlist::and()), Err(EquivalenceError::Interface { inputs: (1, 2), outputs: (1, 1) }));
+        assert_eq!(check_chips(&Dff::default(), &Dff::default()), Err(EquivalenceError::NoNetlist("dff".to_string())));
+        assert_eq!(format!("{}", EquivalenceError::Interface { inputs: (1, 2), outputs: (1, 1) }),
+            "1 inputs and 1 outputs against 2 inputs and 1 outputs");
+    }
+    #[test]
+    fn tadders() {
+        assert_eq!(check_chips(&NAdder::<16>, &ClaAdder::<16>), Ok(Equivalence::Equivalent));
+        assert_eq!(check(&netlist::n_adder::<16>(), &netlist::carry_select_adder::<16>()), Ok(Equivalence::Equivalent));
+        // a ripple adder dropping the carry into the msb
+        let mut b = Builder::new("broken_adder");
+        let (in1, in2) = (b.input_bus::<16>("in1"), b.input_bus::<16>("in2"));
+        let mut out = b.n_adder(in1, in2);
+        out[0] = b.xor(in1[0], in2[0]);
+        b.output_bus("out", &out);
+        let (input, outputs1, outputs2) = counterexample(check(&netlist::n_adder::<16>(), &b.build()));
+        assert_ne!(outputs1, outputs2);
+        assert_eq!(netlist::n_adder::<16>().evaluate(&input), outputs1);
+    }
+    #[test]
+    fn tbdds() {
+        // bit i of in1 is variable 2i, bit i of in2 variable 2i + 1
+        let interleaved: Vec<usize> = (0..16).map(|i| 2 * i).chain((0..16).map(|i| 2 * i + 1)).collect();
+        assert_eq!(check_with_bdds(&netlist::n_adder::<16>(), &netlist::cla_adder::<16>(), &interleaved), Ok(Equivalence::Equivalent));
+        let (input, outputs1, outputs2) = counterexample(check_with_bdds(&netlist::and(), &netlist::or(), &[0, 1]));
+        assert_ne!(input[0], input[1]);
+        assert_ne!(outputs1, outputs2);
+        assert_eq!(check_with_bdds(&netlist::not(), &netlist::and(), &[0]), Err(EquivalenceError::Interface { inputs: (1, 2), outputs: (1, 1) }));
+    }
+    #[test]
+    fn talu() {
+        // 38 inputs, far out of reach of exhaustive simulation
+        assert_eq!(check(&netlist::alu::<16>(), &netlist::alu_with::<16>("alu_cla", Builder::cla_adder)), Ok(Equivalence::Equivalent));
+        let (_, outputs1, outputs2) = counterexample(check(&netlist::alu::<16>(), &netlist::alu_with::<16>("alu_or", Builder::or_n)));
+        assert_ne!(outputs1, outputs2);
+    }
+}
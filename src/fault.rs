@@ -0,0 +1,233 @@
+use crate::gates::*;
+use crate::netlist::{Gate, Netlist, Wire};
+use crate::bits::ParseBitsError;
+use crate::bitslice::LANES;
+use Signal::*;
+use std::fmt;
+
+// Single stuck-at faults: one wire of the netlist, an input or a nand output, held at a
+// constant whatever drives it. A test vector detects a fault when the outputs of the faulty
+// netlist differ from the good one, the fault coverage of a test suite is the share of all
+// the faults it detects. Simulation is bitsliced, 64 vectors per evaluation.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Fault {
+    pub wire: Wire,
+    pub stuck: Signal,
+}
+
+impl fmt::Display for Fault {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "w{} stuck at {}", self.wire, self.stuck)
+  }
+}
+
+// Both faults of every input and nand output, in wire order
+pub fn faults(netlist: &Netlist) -> Vec<Fault> {
+  netlist.nodes.iter().enumerate()
+    .filter(|(_, node)| !matches!(node.gate, Gate::Constant(_)))
+    .flat_map(|(wire, _)| vec![Fault { wire, stuck: Low }, Fault { wire, stuck: High }])
+    .collect()
+}
+
+// The netlist with the driver of the wire replaced by a constant
+pub fn inject(netlist: &Netlist, fault: Fault) -> Netlist {
+  let mut faulty = netlist.clone();
+  faulty.nodes[fault.wire].gate = Gate::Constant(fault.stuck);
+  faulty
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Coverage {
+    pub name: String,
+    pub vectors: usize,
+    pub faults: Vec<Fault>,
+    // Index of the first vector detecting each fault
+    pub detected_by: Vec<Option<usize>>,
+    // Scope path of the driver of each fault wire, e.g. "alu/mux_n/mux"
+    pub scopes: Vec<String>,
+}
+
+impl Coverage {
+  pub fn detected(&self) -> usize {
+    self.detected_by.iter().filter(|by| by.is_some()).count()
+  }
+
+  pub fn ratio(&self) -> f64 {
+    match self.faults.len() {
+        0 => 1.0,
+        total => self.detected() as f64 / total as f64,
+    }
+  }
+
+  pub fn undetected(&self) -> Vec<Fault> {
+    self.faults.iter().zip(self.detected_by.iter()).filter(|(_, by)| by.is_none()).map(|(&fault, _)| fault).collect()
+  }
+}
+
+impl fmt::Display for Coverage {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "{}: {} of {} faults detected ({:.1}%) by {} vectors",
+      self.name, self.detected(), self.faults.len(), 100.0 * self.ratio(), self.vectors)?;
+    for (i, fault) in self.faults.iter().enumerate() {
+      if self.detected_by[i].is_none() {
+        writeln!(f, "  {} in {}", fault, self.scopes[i])?;
+      }
+    }
+    Ok(())
+  }
+}
+
+type Batch = (usize, u64, Vec<u64>);
+
+// Vectors 64 at a time, bit k of every word being vector first + k
+fn batches(vectors: &[Vec<Signal>], width: usize) -> Vec<Batch> {
+  vectors.chunks(LANES).enumerate().map(|(batch, chunk)| {
+    let mut packed = vec![0u64; width];
+    for (k, vector) in chunk.iter().enumerate() {
+      for (word, &s) in packed.iter_mut().zip(vector.iter()) {
+        *word |= ((s == High) as u64) << k;
+      }
+    }
+    let mask = if chunk.len() == LANES { u64::MAX } else { (1 << chunk.len()) - 1 };
+    (batch * LANES, mask, packed)
+  }).collect()
+}
+
+// Per batch, the vectors whose outputs differ between the faulty netlist and the good outputs
+fn differences<'a>(faulty: &'a Netlist, batches: &'a [Batch], good: &'a [Vec<u64>]) -> impl Iterator<Item = u64> + 'a {
+  batches.iter().zip(good.iter()).map(move |((_, mask, packed), good)| {
+    faulty.evaluate(packed).iter().zip(good.iter()).fold(0, |acc, (bad, good)| acc | ((good ^ bad) & mask))
+  })
+}
+
+// First vector detecting the fault, the remaining batches are not simulated
+fn detection(faulty: &Netlist, batches: &[Batch], good: &[Vec<u64>]) -> Option<usize> {
+  differences(faulty, batches, good).zip(batches.iter()).find_map(|(differences, (first, _, _))| match differences {
+      0 => None,
+      _ => Some(first + differences.trailing_zeros() as usize),
+  })
+}
+
+fn good_outputs(netlist: &Netlist, batches: &[Batch]) -> Vec<Vec<u64>> {
+  batches.iter().map(|(_, _, packed)| netlist.evaluate(packed)).collect()
+}
+
+// Index of the first vector detecting the fault
+pub fn first_detection(netlist: &Netlist, fault: Fault, vectors: &[Vec<Signal>]) -> Option<usize> {
+  let batches = batches(vectors, netlist.input_width());
+  detection(&inject(netlist, fault), &batches, &good_outputs(netlist, &batches))
+}
+
+// Every vector detecting each fault, bit k of word w standing for vector 64 * w + k
+pub fn detections(netlist: &Netlist, faults: &[Fault], vectors: &[Vec<Signal>]) -> Vec<Vec<u64>> {
+  let batches = batches(vectors, netlist.input_width());
+  let good = good_outputs(netlist, &batches);
+  faults.iter().map(|&fault| differences(&inject(netlist, fault), &batches, &good).collect()).collect()
+}
+
+pub fn coverage(netlist: &Netlist, vectors: &[Vec<Signal>]) -> Coverage {
+  let width = netlist.input_width();
+  assert!(vectors.iter().all(|vector| vector.len() == width), "{} takes {} inputs", netlist.name, width);
+  let faults = faults(netlist);
+  let batches = batches(vectors, width);
+  let good = good_outputs(netlist, &batches);
+  let detected_by = faults.iter().map(|&fault| detection(&inject(netlist, fault), &batches, &good)).collect();
+  let scopes = faults.iter().map(|fault| netlist.scope_path(netlist.nodes[fault.wire].scope)).collect();
+  Coverage { name: netlist.name.clone(), vectors: vectors.len(), faults, detected_by, scopes }
+}
+
+// Test vectors, one per line as a bit string of `width` inputs. Spaces, `|` and `_` are
+// ignored and lines with anything else are skipped, so a .cmp file with the inputs in its
+// first columns can be read as is, the outputs that follow being dropped.
+pub fn parse_vectors(text: &str, width: usize) -> Result<Vec<Vec<Signal>>, ParseBitsError> {
+  let mut vectors = vec![];
+  for line in text.lines() {
+    let bits: Vec<char> = line.chars().filter(|c| !matches!(c, ' ' | '\t' | '|' | '_')).collect();
+    if bits.is_empty() || bits.iter().any(|&c| c != '0' && c != '1') {
+      continue;
+    }
+    if bits.len() < width {
+      return Err(ParseBitsError::Length { expected: width, found: bits.len() });
+    }
+    vectors.push(bits[..width].iter().map(|&c| if c == '1' { High } else { Low }).collect());
+  }
+  Ok(vectors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netlist;
+    use crate::truth_table;
+    use crate::utilities::*;
+
+    #[test]
+    fn tinject() {
+        let and = netlist::and();
+        let output = and.outputs[0].wires[0];
+        let faulty = inject(&and, Fault { wire: output, stuck: High });
+        assert!(truth_table::inputs(2).all(|input| faulty.evaluate(&input) == vec![High]));
+        let input = and.inputs[0].wires[0];
+        let faulty = inject(&and, Fault { wire: input, stuck: Low });
+        assert_eq!(faulty.evaluate(&[High, High]), vec![Low]);
+        assert_eq!(faults(&and).len(), 2 * and.nodes.len());
+        assert_eq!(format!("{}", Fault { wire: 3, stuck: Low }), "w3 stuck at 0");
+    }
+    #[test]
+    fn tcoverage() {
+        let xor = netlist::xor();
+        let all: Vec<Vec<Signal>> = truth_table::inputs(2).collect();
+        let report = coverage(&xor, &all);
+        assert_eq!(report.detected(), report.faults.len());
+        assert_eq!(report.ratio(), 1.0);
+        let masks = detections(&xor, &report.faults, &all);
+        assert!(masks.iter().zip(report.detected_by.iter()).all(|(mask, &by)| Some(mask[0].trailing_zeros() as usize) == by));
+        let report = coverage(&xor, &all[..1]);
+        assert!(report.ratio() < 0.5);
+        assert_eq!(report.undetected().len(), report.faults.len() - report.detected());
+        let fault = report.undetected()[0];
+        assert_eq!(first_detection(&xor, fault, &all).map(|i| all[i].clone()), first_detection(&xor, fault, &all[1..]).map(|i| all[i + 1].clone()));
+        assert!(format!("{}", report).starts_with("xor: 5 of 14 faults detected (35.7%) by 1 vectors\n  w0 stuck at 0 in xor\n  w1 stuck at 0 in xor\n  w2 stuck at 0 in xor/xor\n"));
+        // the carries of the two half adders of a full adder are never High together, so even
+        // exhaustive vectors miss the faults that would only show when they are
+        let full_adder = netlist::full_adder();
+        let report = coverage(&full_adder, &truth_table::inputs(3).collect::<Vec<_>>());
+        assert_eq!((report.detected(), report.faults.len()), (40, 44));
+        assert!(report.undetected().iter().all(|fault| fault.stuck == High));
+        assert!((0..44).all(|i| report.detected_by[i].is_some() || report.scopes[i].ends_with("half_adder/and")));
+    }
+    #[test]
+    fn talu() {
+        // the 4x4 grid of talu in alu.rs over the 18 functions of the hack alu
+        let alu = netlist::alu::<4>();
+        let controls = ["101010", "111111", "111010", "001100", "110000", "001101", "110001", "001111", "110011",
+            "011111", "110111", "001110", "110010", "000010", "010011", "000111", "000000", "010101"];
+        let mut vectors: Vec<Vec<Signal>> = vec![];
+        for a in 1..=4u8 {
+            for b in 1..=4u8 {
+                for control in controls.iter() {
+                    let mut vector = to_signals_truncated::<4, _>(a, Order::MsbFirst).to_vec();
+                    vector.extend_from_slice(&to_signals_truncated::<4, _>(b, Order::MsbFirst));
+                    vector.extend(control.chars().map(|c| if c == '1' { High } else { Low }));
+                    vectors.push(vector);
+                }
+            }
+        }
+        let report = coverage(&alu, &vectors);
+        assert_eq!(report.vectors, 288);
+        // operands up to 4 never set the msb, whose stuck-at-0 goes unnoticed
+        assert!(report.ratio() < 0.9);
+        assert_eq!(report.detected_by[0], None);
+        assert_eq!(report.faults[0], Fault { wire: alu.inputs[0].wires[0], stuck: Low });
+        let exhaustive: Vec<Vec<Signal>> = truth_table::inputs(14).collect();
+        assert!(coverage(&alu, &exhaustive).ratio() > report.ratio());
+    }
+    #[test]
+    fn tparse_vectors() {
+        let cmp = "|  a  |  b  | out |\n|  0  |  1  |  1  |\n|  1  |  1  |  0  |\n";
+        assert_eq!(parse_vectors(cmp, 2), Ok(vec![vec![Low, High], vec![High, High]]));
+        assert_eq!(parse_vectors("0000_1111\n\n1010 0101\n", 8).unwrap().len(), 2);
+        assert_eq!(parse_vectors("01\n", 3), Err(ParseBitsError::Length { expected: 3, found: 2 }));
+    }
+}
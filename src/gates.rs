@@ -9,35 +9,60 @@ pub enum Signal {
     Low,
 }
 
-pub type Signals<const BITS: usize> = [Signal; BITS];
-
-pub fn nand(in1: Signal, in2: Signal) -> Signal {
-  match (in1, in2) {
-      (Low, Low) => High,
-      (Low, High) => High,
-      (High, Low) => High,
-      (High, High) => Low,
+pub type Signals<const BITS: usize, S = Signal> = [S; BITS];
+
+// Every chip is built from nand alone, so it can be simulated with any type that implements
+// nand: Signal for a single evaluation, or e.g. a u64 carrying 64 evaluations in its bits.
+pub trait Logic: Copy + PartialEq + std::fmt::Debug {
+    const LOW: Self;
+    const HIGH: Self;
+    fn nand(self, other: Self) -> Self;
+}
+
+impl Logic for Signal {
+  const LOW: Signal = Low;
+  const HIGH: Signal = High;
+  fn nand(self, other: Signal) -> Signal {
+    match (self, other) {
+        (Low, Low) => High,
+        (Low, High) => High,
+        (High, Low) => High,
+        (High, High) => Low,
+    }
+  }
+}
+
+// Bitsliced signals, bit k of every wire belongs to the k-th of 64 independent evaluations
+impl Logic for u64 {
+  const LOW: u64 = 0;
+  const HIGH: u64 = u64::MAX;
+  fn nand(self, other: u64) -> u64 {
+    !(self & other)
   }
 }
 
+pub fn nand<S: Logic>(in1: S, in2: S) -> S {
+  in1.nand(in2)
+}
 
-pub fn not(in1: Signal) -> Signal {
+
+pub fn not<S: Logic>(in1: S) -> S {
   nand(in1, in1)
 }
 
-pub fn and(in1: Signal, in2: Signal) -> Signal {
+pub fn and<S: Logic>(in1: S, in2: S) -> S {
   nand(nand(in1, in2), nand(in1, in2))
 }
 
-pub fn or(in1: Signal, in2: Signal) -> Signal {
+pub fn or<S: Logic>(in1: S, in2: S) -> S {
   nand(nand(in1, in1), nand(in2, in2))
 }
 
-pub fn xor(in1: Signal, in2: Signal) -> Signal {
+pub fn xor<S: Logic>(in1: S, in2: S) -> S {
   nand(nand(nand(in1, in2), in1), nand(nand(in1, in2), in2))
 }
 
-pub fn mux(in1: Signal, in2: Signal, sel: Signal) -> Signal {
+pub fn mux<S: Logic>(in1: S, in2: S, sel: S) -> S {
   nand(
       nand(
           in1, 
@@ -47,7 +72,7 @@ pub fn mux(in1: Signal, in2: Signal, sel: Signal) -> Signal {
   )
 }
 
-pub fn dmux(in1: Signal, sel: Signal) -> (Signal, Signal) {
+pub fn dmux<S: Logic>(in1: S, sel: S) -> (S, S) {
   (nand(
       nand(in1, nand(sel, sel)),
       nand(in1, nand(sel, sel))
@@ -62,50 +87,54 @@ pub fn dmux(in1: Signal, sel: Signal) -> (Signal, Signal) {
 //     in1.iter().map(|&s| not(s)).collect::<Vec<Signal>>().try_into().unwrap()
 // }
 
-pub fn not_n<const BITS: usize> (in1: Signals<BITS>) -> Signals<BITS> {
-  in1.iter().map(|&s| not(s)).collect::<Vec<Signal>>().try_into().unwrap()
+pub fn not_n<const BITS: usize, S: Logic>(in1: Signals<BITS, S>) -> Signals<BITS, S> {
+  in1.iter().map(|&s| not(s)).collect::<Vec<S>>().try_into().unwrap()
+}
+
+pub fn and_n<const BITS: usize, S: Logic>(in1: Signals<BITS, S>, in2: Signals<BITS, S>) -> Signals<BITS, S> {
+  in1.iter().zip(in2.iter()).map(|(&s1, &s2)| and(s1, s2)).collect::<Vec<S>>().try_into().unwrap()
 }
 
-pub fn and_n<const BITS: usize> (in1: Signals<BITS>, in2: Signals<BITS>) -> Signals<BITS> {
-  in1.iter().zip(in2.iter()).map(|(&s1, &s2)| and(s1, s2)).collect::<Vec<Signal>>().try_into().unwrap()
+pub fn or_n<const BITS: usize, S: Logic>(in1: Signals<BITS, S>, in2: Signals<BITS, S>) -> Signals<BITS, S> {
+  in1.iter().zip(in2.iter()).map(|(&s1, &s2)| or(s1, s2)).collect::<Vec<S>>().try_into().unwrap()
 }
 
-pub fn or_n<const BITS: usize> (in1: Signals<BITS>, in2: Signals<BITS>) -> Signals<BITS> {
-  in1.iter().zip(in2.iter()).map(|(&s1, &s2)| or(s1, s2)).collect::<Vec<Signal>>().try_into().unwrap()
+pub fn xor_n<const BITS: usize, S: Logic>(in1: Signals<BITS, S>, in2: Signals<BITS, S>) -> Signals<BITS, S> {
+  in1.iter().zip(in2.iter()).map(|(&s1, &s2)| xor(s1, s2)).collect::<Vec<S>>().try_into().unwrap()
 }
 
-pub fn mux_n<const BITS: usize> (in1: Signals<BITS>, in2: Signals<BITS>, sel: Signal) -> Signals<BITS> {
-  in1.iter().zip(in2.iter()).map(|(&s1, &s2)| mux(s1, s2, sel)).collect::<Vec<Signal>>().try_into().unwrap()
+pub fn mux_n<const BITS: usize, S: Logic>(in1: Signals<BITS, S>, in2: Signals<BITS, S>, sel: S) -> Signals<BITS, S> {
+  in1.iter().zip(in2.iter()).map(|(&s1, &s2)| mux(s1, s2, sel)).collect::<Vec<S>>().try_into().unwrap()
 }
 
-pub fn or_m_way<const BITS: usize> (in1: Signals<BITS>) -> Signal {
-  in1.iter().fold(Low, |acc, &s| or(acc, s))
+pub fn or_m_way<const BITS: usize, S: Logic>(in1: Signals<BITS, S>) -> S {
+  in1.iter().fold(S::LOW, |acc, &s| or(acc, s))
 }
 
-pub fn and_m_way<const BITS: usize> (in1: Signals<BITS>) -> Signal {
-  in1.iter().fold(High, |acc, &s| and(acc, s))
+pub fn and_m_way<const BITS: usize, S: Logic>(in1: Signals<BITS, S>) -> S {
+  in1.iter().fold(S::HIGH, |acc, &s| and(acc, s))
 }
 
-pub fn mux_4_way_n<const BITS: usize>(in1: Signals<BITS>, in2: Signals<BITS>, in3: Signals<BITS>, in4: Signals<BITS>, sel1: Signal, sel2: Signal) -> Signals<BITS> {
+pub fn mux_4_way_n<const BITS: usize, S: Logic>(in1: Signals<BITS, S>, in2: Signals<BITS, S>, in3: Signals<BITS, S>, in4: Signals<BITS, S>, sel1: S, sel2: S) -> Signals<BITS, S> {
   mux_n(
       mux_n(in1, in2, sel1),
       mux_n(in3, in4, sel1), 
       sel2
   )
 }
-pub fn mux_8_way_n<const BITS: usize>(
-  in1: Signals<BITS>,
-  in2: Signals<BITS>,
-  in3: Signals<BITS>,
-  in4: Signals<BITS>, 
-  in5: Signals<BITS>,
-  in6: Signals<BITS>,
-  in7: Signals<BITS>,
-  in8: Signals<BITS>,
-  sel1: Signal,
-  sel2: Signal,
-  sel3: Signal
-) -> Signals<BITS> {
+pub fn mux_8_way_n<const BITS: usize, S: Logic>(
+  in1: Signals<BITS, S>,
+  in2: Signals<BITS, S>,
+  in3: Signals<BITS, S>,
+  in4: Signals<BITS, S>, 
+  in5: Signals<BITS, S>,
+  in6: Signals<BITS, S>,
+  in7: Signals<BITS, S>,
+  in8: Signals<BITS, S>,
+  sel1: S,
+  sel2: S,
+  sel3: S
+) -> Signals<BITS, S> {
   mux_n(
       mux_4_way_n(in1, in2, in3, in4, sel1, sel2),
       mux_4_way_n(in5, in6, in7, in8, sel1, sel2),
@@ -113,7 +142,7 @@ pub fn mux_8_way_n<const BITS: usize>(
   )
 }
 
-pub fn dmux_4_way(in1: Signal, sel1: Signal, sel2: Signal) -> Signals<4> {
+pub fn dmux_4_way<S: Logic>(in1: S, sel1: S, sel2: S) -> Signals<4, S> {
   [
       and_m_way([in1, not(sel1), not(sel2)]),
       and_m_way([in1, not(sel1), sel2]),
@@ -121,7 +150,7 @@ pub fn dmux_4_way(in1: Signal, sel1: Signal, sel2: Signal) -> Signals<4> {
       and_m_way([in1, sel1, sel2]),        
   ]
 }
-pub fn dmux_8_way(in1: Signal, sel1: Signal, sel2: Signal, sel3: Signal) -> Signals<8> {
+pub fn dmux_8_way<S: Logic>(in1: S, sel1: S, sel2: S, sel3: S) -> Signals<8, S> {
   [
       and_m_way([in1, not(sel1), not(sel2), not(sel3)]),
       and_m_way([in1, not(sel1), not(sel2), sel3]),
@@ -134,9 +163,70 @@ pub fn dmux_8_way(in1: Signal, sel1: Signal, sel2: Signal, sel3: Signal) -> Sign
   ]
 }
 
+// Generic versions of the chips above, with a msb first selection bus: input or output i is
+// the one selected when sel holds i. WAYS must be 2^N, which const generics cannot express.
+
+pub fn decoder<const N: usize, const WAYS: usize, S: Logic>(sel: Signals<N, S>) -> Signals<WAYS, S> {
+  assert_eq!(WAYS, 1 << N, "a decoder of {} bits has {} outputs", N, 1 << N);
+  let mut out = [S::LOW; WAYS];
+  for (i, signal) in out.iter_mut().enumerate() {
+      let mut literals = sel;
+      for (k, literal) in literals.iter_mut().enumerate() {
+          if i >> (N - 1 - k) & 1 == 0 {
+              *literal = not(*literal);
+          }
+      }
+      *signal = and_m_way(literals);
+  }
+  out
+}
+
+// One-hot to binary, bit k of the output is set by every input whose index has bit k set
+pub fn encoder<const N: usize, const WAYS: usize, S: Logic>(in1: Signals<WAYS, S>) -> Signals<N, S> {
+  assert_eq!(WAYS, 1 << N, "an encoder of {} bits has {} inputs", N, 1 << N);
+  let mut out = [S::LOW; N];
+  for (k, signal) in out.iter_mut().enumerate() {
+      *signal = in1.iter().enumerate()
+          .filter(|(i, _)| i >> (N - 1 - k) & 1 == 1)
+          .fold(S::LOW, |acc, (_, &s)| or(acc, s));
+  }
+  out
+}
+
+// Encodes the lowest index set, with a valid output that is Low when no input is set
+pub fn priority_encoder<const N: usize, const WAYS: usize, S: Logic>(in1: Signals<WAYS, S>) -> (Signals<N, S>, S) {
+  let mut first = [S::LOW; WAYS];
+  let mut seen = S::LOW;
+  for (i, &s) in in1.iter().enumerate() {
+      first[i] = and(s, not(seen));
+      seen = or(seen, s);
+  }
+  (encoder(first), seen)
+}
+
+pub fn mux_2n_way<const BITS: usize, const N: usize, const WAYS: usize, S: Logic>(in1: [Signals<BITS, S>; WAYS], sel: Signals<N, S>) -> Signals<BITS, S> {
+  assert_eq!(WAYS, 1 << N, "a mux with {} selection bits has {} inputs", N, 1 << N);
+  let mut level = in1.to_vec();
+  for &s in sel.iter().rev() {
+      level = level.chunks(2).map(|pair| mux_n(pair[0], pair[1], s)).collect();
+  }
+  level[0]
+}
+
+pub fn dmux_2n_way<const N: usize, const WAYS: usize, S: Logic>(in1: S, sel: Signals<N, S>) -> Signals<WAYS, S> {
+  let selected = decoder::<N, WAYS, S>(sel);
+  let mut out = [S::LOW; WAYS];
+  for (signal, &s) in out.iter_mut().zip(selected.iter()) {
+      *signal = and(in1, s);
+  }
+  out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::chip::Mux8WayN;
+    use crate::truth_table::verify;
     #[test]
     fn tnand() {
         assert_eq!(nand(Low, Low), High);
@@ -205,6 +295,11 @@ mod tests {
         assert_eq!(or_n([High, High], [Low, Low]), [High, High]);
     }
     #[test]
+    fn txor_n() {
+        assert_eq!(xor_n([Low, High], [Low, High]), [Low, Low]);
+        assert_eq!(xor_n([High, High], [Low, High]), [High, Low]);
+    }
+    #[test]
     fn tmux_n() {
         assert_eq!(mux_n([Low, High], [High, High], Low), [Low, High]);
         assert_eq!(mux_n([Low, High], [High, High], High), [High, High]);
@@ -223,14 +318,11 @@ mod tests {
     }
     #[test]
     fn tmux_8_way_n() {
-        assert_eq!(mux_8_way_n([Low], [Low], [Low], [Low], [Low], [Low], [High], [Low], Low, Low, Low), [Low]);
-        assert_eq!(mux_8_way_n([Low], [Low], [Low], [Low], [Low], [Low], [High], [Low], Low, Low, Low), [Low]);
-        assert_eq!(mux_8_way_n([Low], [Low], [Low], [Low], [Low], [Low], [High], [Low], Low, Low, Low), [Low]);
-        assert_eq!(mux_8_way_n([Low], [Low], [Low], [Low], [Low], [Low], [High], [Low], Low, Low, Low), [Low]);
-        assert_eq!(mux_8_way_n([Low], [Low], [Low], [Low], [Low], [Low], [High], [Low], Low, Low, Low), [Low]);
-        assert_eq!(mux_8_way_n([Low], [Low], [Low], [Low], [Low], [Low], [High], [Low], Low, Low, Low), [Low]);
-        assert_eq!(mux_8_way_n([Low], [Low], [Low], [Low], [Low], [Low], [High], [Low], Low, Low, Low), [Low]);
         assert_eq!(mux_8_way_n([Low], [Low], [Low], [Low], [Low], [Low], [High], [Low], Low, High, High), [High]);
+        let rows = verify(&mut Mux8WayN::<1>, |(ins, sel1, sel2, sel3)| {
+            ins[bool::from(sel1) as usize + 2 * bool::from(sel2) as usize + 4 * bool::from(sel3) as usize]
+        });
+        assert_eq!(rows, Ok(1 << 11));
     }
     #[test]
     fn tdmux_4_way() {
@@ -244,6 +336,51 @@ mod tests {
         assert_eq!(dmux_4_way(High, High, High), [Low, Low, Low, High]);
     }
     #[test]
+    fn tdecoder() {
+        assert_eq!(decoder::<0, 1, Signal>([]), [High]);
+        assert_eq!(decoder([Low]), [High, Low]);
+        assert_eq!(decoder([High, Low]), [Low, Low, High, Low]);
+        for i in 0..8 {
+            let sel = [[Low, High][i >> 2], [Low, High][i >> 1 & 1], [Low, High][i & 1]];
+            let out: Signals<8> = decoder(sel);
+            assert_eq!(out, dmux_8_way(High, sel[0], sel[1], sel[2]));
+            assert_eq!(encoder::<3, 8, Signal>(out), sel);
+            assert_eq!(dmux_2n_way::<3, 8, Signal>(High, sel), out);
+            assert_eq!(dmux_2n_way::<3, 8, Signal>(Low, sel), [Low; 8]);
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn tdecoder_width() {
+        let _: Signals<6> = decoder([Low, High, Low]);
+    }
+    #[test]
+    fn tencoder() {
+        assert_eq!(encoder([Low, Low, Low, High]), [High, High]);
+        assert_eq!(encoder([Low, Low, High, Low]), [High, Low]);
+        assert_eq!(encoder([Low, Low, Low, Low]), [Low, Low]);
+    }
+    #[test]
+    fn tpriority_encoder() {
+        assert_eq!(priority_encoder([Low, Low, Low, Low]), ([Low, Low], Low));
+        assert_eq!(priority_encoder([Low, Low, Low, High]), ([High, High], High));
+        assert_eq!(priority_encoder([Low, High, Low, High]), ([Low, High], High));
+        assert_eq!(priority_encoder([High, High, High, High]), ([Low, Low], High));
+        assert_eq!(priority_encoder([Low, Low, High, Low, High, High, Low, Low]), ([Low, High, Low], High));
+    }
+    #[test]
+    fn tmux_2n_way() {
+        let ins = [[Low, Low], [Low, High], [High, Low], [High, High]];
+        assert_eq!(mux_2n_way(ins, [Low, Low]), [Low, Low]);
+        assert_eq!(mux_2n_way(ins, [Low, High]), [Low, High]);
+        assert_eq!(mux_2n_way(ins, [High, Low]), [High, Low]);
+        assert_eq!(mux_2n_way(ins, [High, High]), [High, High]);
+        assert_eq!(mux_2n_way([[High]], []), [High]);
+        let ins = [[Low], [Low], [Low], [Low], [Low], [Low], [High], [Low]];
+        assert_eq!(mux_2n_way(ins, [High, High, Low]), [High]);
+        assert_eq!(mux_2n_way(ins, [Low, High, High]), [Low]);
+    }
+    #[test]
     fn tdmux_8_way() {
         assert_eq!(dmux_8_way(Low, Low, Low, Low), [Low, Low, Low, Low, Low, Low, Low, Low]);
         assert_eq!(dmux_8_way(High, Low, Low, Low), [High, Low, Low, Low, Low, Low, Low, Low]);
@@ -0,0 +1,490 @@
+#![allow(clippy::unusual_byte_groupings)]
+
+use std::collections::HashMap;
+use std::fmt;
+
+// The Hack machine language with an opt-in extension. Bits 14 and 13 of a C-instruction are
+// always 1 in standard programs and ignored by the standard cpu; extended programs use them
+// to select another unit than the alu (see alu::extended_alu):
+//   111a cccc ccdd djjj  standard alu
+//   110a cccc ccdd djjj  standard alu jumping on the flags register instead of its output,
+//                        j = 100 on carry (JC), 010 on overflow (JV), 001 on no carry (JNC)
+//   1010 sr00 00dd djjj  shift, s = 0 shifts D and s = 1 shifts A (a = 0) or M (a = 1),
+//                        r = 0 shifts left and r = 1 shifts right (arithmetic), by one bit
+//   100a 0000 00dd djjj  multiply, D*A (a = 0) or D*M (a = 1), low word of the product
+// The flags register holds the carry and overflow (see alu::alu_flags) of the last 111
+// instruction whose alu adds (f = 1); the other instructions leave it unchanged.
+
+const STANDARD: [(&str, u16); 18] = [
+  ("0", 0b101010),
+  ("1", 0b111111),
+  ("-1", 0b111010),
+  ("D", 0b001100),
+  ("A", 0b110000),
+  ("!D", 0b001101),
+  ("!A", 0b110001),
+  ("-D", 0b001111),
+  ("-A", 0b110011),
+  ("D+1", 0b011111),
+  ("A+1", 0b110111),
+  ("D-1", 0b001110),
+  ("A-1", 0b110010),
+  ("D+A", 0b000010),
+  ("D-A", 0b010011),
+  ("A-D", 0b000111),
+  ("D&A", 0b000000),
+  ("D|A", 0b010101),
+];
+
+const EXTENDED: [(&str, u16); 6] = [
+  ("D<<", 0b01_0_000000),
+  ("D>>", 0b01_0_010000),
+  ("A<<", 0b01_0_100000),
+  ("A>>", 0b01_0_110000),
+  ("D*A", 0b00_0_000000),
+  ("D*M", 0b00_1_000000),
+];
+
+const JUMPS: [&str; 8] = ["", "JGT", "JEQ", "JGE", "JLT", "JNE", "JLE", "JMP"];
+
+const FLAG_JUMPS: [(&str, u16); 3] = [("JC", 0b100), ("JV", 0b010), ("JNC", 0b001)];
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "line {}: {}", self.line, self.message)
+  }
+}
+
+impl std::error::Error for AsmError {}
+
+// Bits 14 to 6 of a comp mnemonic: the unit, the a bit and the six alu control bits
+fn comp_bits(comp: &str, extended: bool) -> Option<u16> {
+  for &(mnemonic, bits) in STANDARD.iter() {
+    if mnemonic == comp {
+      return Some(0b11_0_000000 | bits);
+    }
+    if mnemonic.contains('A') && mnemonic.replace('A', "M") == comp {
+      return Some(0b11_1_000000 | bits);
+    }
+  }
+  if !extended {
+    return None;
+  }
+  for &(mnemonic, bits) in EXTENDED.iter() {
+    if mnemonic == comp {
+      return Some(bits);
+    }
+    if mnemonic.starts_with('A') && mnemonic.replace('A', "M") == comp {
+      return Some(bits | 0b00_1_000000);
+    }
+  }
+  None
+}
+
+fn comp_mnemonic(bits: u16, extended: bool) -> Option<String> {
+  let a = bits >> 6 & 1 == 1;
+  let unit = if extended { bits >> 7 } else { 0b11 };
+  if unit == 0b11 {
+    let &(mnemonic, _) = STANDARD.iter().find(|&&(_, code)| code == bits & 0b111111)?;
+    return match a {
+      false => Some(mnemonic.to_string()),
+      true if mnemonic.contains('A') => Some(mnemonic.replace('A', "M")),
+      true => None,
+    };
+  }
+  if let Some(&(mnemonic, _)) = EXTENDED.iter().find(|&&(_, code)| code == bits) {
+    return Some(mnemonic.to_string());
+  }
+  let &(mnemonic, _) = EXTENDED.iter().find(|&&(_, code)| a && code == bits & !0b1000000)?;
+  if mnemonic.starts_with('A') { Some(mnemonic.replace('A', "M")) } else { None }
+}
+
+fn predefined() -> HashMap<String, u16> {
+  let mut symbols = HashMap::new();
+  for (name, address) in [("SP", 0), ("LCL", 1), ("ARG", 2), ("THIS", 3), ("THAT", 4), ("SCREEN", 16384), ("KBD", 24576)].iter() {
+    symbols.insert(name.to_string(), *address);
+  }
+  for i in 0..16 {
+    symbols.insert(format!("R{}", i), i);
+  }
+  symbols
+}
+
+fn is_symbol(symbol: &str) -> bool {
+  !symbol.is_empty()
+    && !symbol.starts_with(|c: char| c.is_ascii_digit())
+    && symbol.chars().all(|c| c.is_ascii_alphanumeric() || "_.$:".contains(c))
+}
+
+fn c_instruction(text: &str, extended: bool) -> Result<u16, String> {
+  let (dest, rest) = match text.find('=') {
+    Some(i) => (&text[..i], &text[i + 1..]),
+    None => ("", text),
+  };
+  let (comp, jump) = match rest.find(';') {
+    Some(i) => (&rest[..i], &rest[i + 1..]),
+    None => (rest, ""),
+  };
+  let mut comp = comp_bits(comp, extended).ok_or(format!("unknown computation {}", comp))?;
+  let flag_jump = FLAG_JUMPS.iter().find(|&&(j, _)| extended && j == jump);
+  let jump = match (JUMPS.iter().position(|&j| j == jump), flag_jump) {
+    (Some(position), _) => position as u16,
+    (None, Some(&(_, bits))) if comp >> 7 == 0b11 => {
+      comp ^= 0b01_0_000000;
+      bits
+    },
+    (None, Some(_)) => return Err(format!("jump {} needs a computation of the alu", jump)),
+    (None, None) => return Err(format!("unknown jump {}", jump)),
+  };
+  let mut destination = 0;
+  for register in dest.chars() {
+    let bit = match register {
+      'A' => 0b100,
+      'D' => 0b010,
+      'M' => 0b001,
+      _ => return Err(format!("unknown destination {}", dest)),
+    };
+    if destination & bit != 0 {
+      return Err(format!("unknown destination {}", dest));
+    }
+    destination |= bit;
+  }
+  Ok(1 << 15 | comp << 6 | destination << 3 | jump)
+}
+
+// Two passes as in the book: labels first, then instructions with variables from address 16
+pub fn assemble(source: &str, extended: bool) -> Result<Vec<u16>, AsmError> {
+  let lines: Vec<(usize, String)> = source.lines().enumerate()
+    .map(|(i, line)| (i + 1, line.split("//").next().unwrap().chars().filter(|c| !c.is_whitespace()).collect::<String>()))
+    .filter(|(_, line)| !line.is_empty())
+    .collect();
+  let mut symbols = predefined();
+  let mut address = 0;
+  for (number, line) in lines.iter() {
+    if line.starts_with('(') {
+      let label = line.strip_prefix('(').and_then(|l| l.strip_suffix(')')).filter(|l| is_symbol(l));
+      let label = label.ok_or(AsmError { line: *number, message: format!("invalid label {}", line) })?;
+      if symbols.insert(label.to_string(), address).is_some() {
+        return Err(AsmError { line: *number, message: format!("duplicate symbol {}", label) });
+      }
+    } else {
+      address += 1;
+    }
+  }
+  let mut variable = 16;
+  let mut program = vec![];
+  for (number, line) in lines.iter().filter(|(_, line)| !line.starts_with('(')) {
+    let error = |message: String| AsmError { line: *number, message };
+    if let Some(value) = line.strip_prefix('@') {
+      let value = match value.parse::<u16>() {
+        Ok(value) if value < 1 << 15 => value,
+        Ok(_) => return Err(error(format!("constant {} does not fit in 15 bits", value))),
+        Err(_) if is_symbol(value) => *symbols.entry(value.to_string()).or_insert_with(|| {
+          variable += 1;
+          variable - 1
+        }),
+        Err(_) => return Err(error(format!("invalid address {}", value))),
+      };
+      program.push(value);
+    } else {
+      program.push(c_instruction(line, extended).map_err(error)?);
+    }
+  }
+  Ok(program)
+}
+
+// Assembly of a single instruction, None when its computation has no mnemonic
+pub fn disassemble(instruction: u16, extended: bool) -> Option<String> {
+  if instruction >> 15 == 0 {
+    return Some(format!("@{}", instruction));
+  }
+  let bits = instruction >> 6 & 0b111111111;
+  let flags = extended && bits >> 7 == 0b10;
+  let comp = comp_mnemonic(if flags { bits | 0b01_0_000000 } else { bits }, extended)?;
+  let dest: String = [(5, 'A'), (3, 'M'), (4, 'D')].iter()
+    .filter(|&&(bit, _)| instruction >> bit & 1 == 1)
+    .map(|&(_, register)| register)
+    .collect();
+  let mut text = String::new();
+  if !dest.is_empty() {
+    text.push_str(&dest);
+    text.push('=');
+  }
+  text.push_str(&comp);
+  let jump = match flags {
+    false => JUMPS[(instruction & 0b111) as usize],
+    true => FLAG_JUMPS.iter().find(|&&(_, code)| code == instruction & 0b111)?.0,
+  };
+  if !jump.is_empty() {
+    text.push(';');
+    text.push_str(jump);
+  }
+  Some(text)
+}
+
+// Standard alu on integers with its carry and overflow, bit for bit the behaviour of alu::alu_flags
+fn alu(x: i16, y: i16, control: u16) -> (i16, bool, bool) {
+  let bit = |i: u16| control >> (5 - i) & 1 == 1;
+  let x = if bit(0) { 0 } else { x };
+  let x = if bit(1) { !x } else { x };
+  let y = if bit(2) { 0 } else { y };
+  let y = if bit(3) { !y } else { y };
+  let out = if bit(4) { x.wrapping_add(y) } else { x & y };
+  let carry = bit(4) && (x as u16).checked_add(y as u16).is_none();
+  let overflow = bit(4) && x.checked_add(y).is_none();
+  (if bit(5) { !out } else { out }, carry, overflow)
+}
+
+// Output of the computation of a C-instruction, as computed by alu::extended_alu
+pub fn compute(instruction: u16, d: i16, a_or_m: i16, extended: bool) -> i16 {
+  let control = instruction >> 6 & 0b111111;
+  match if extended { instruction >> 13 & 0b11 } else { 0b11 } {
+    0b00 => d.wrapping_mul(a_or_m),
+    0b01 => {
+      let operand = if control & 0b100000 == 0 { d } else { a_or_m };
+      if control & 0b010000 == 0 { operand.wrapping_shl(1) } else { operand >> 1 }
+    },
+    _ => alu(d, a_or_m, control).0,
+  }
+}
+
+pub const RAM_SIZE: usize = 1 << 15;
+
+// Instruction level emulator of the Hack computer, one instruction per cycle
+pub struct Computer {
+    pub rom: Vec<u16>,
+    pub ram: Vec<i16>,
+    pub a: i16,
+    pub d: i16,
+    pub pc: u16,
+    pub carry: bool,
+    pub overflow: bool,
+    pub cycles: u64,
+    pub extended: bool,
+}
+
+impl Computer {
+  pub fn new(rom: Vec<u16>, extended: bool) -> Computer {
+    Computer { rom, ram: vec![0; RAM_SIZE], a: 0, d: 0, pc: 0, carry: false, overflow: false, cycles: 0, extended }
+  }
+
+  pub fn reset(&mut self) {
+    self.pc = 0;
+  }
+
+  pub fn step(&mut self) {
+    let instruction = self.rom.get(self.pc as usize).copied().unwrap_or(0);
+    self.cycles += 1;
+    if instruction >> 15 == 0 {
+      self.a = instruction as i16;
+      self.pc = self.pc.wrapping_add(1);
+      return;
+    }
+    let address = self.a as u16 as usize % RAM_SIZE;
+    let y = if instruction >> 12 & 1 == 1 { self.ram[address] } else { self.a };
+    let out = compute(instruction, self.d, y, self.extended);
+    let (_, carry, overflow) = alu(self.d, y, instruction >> 6 & 0b111111);
+    // the pc loads the A of before this instruction, on the same edge as the destinations
+    let target = self.a as u16;
+    if instruction >> 3 & 1 == 1 {
+      self.ram[address] = out;
+    }
+    if instruction >> 5 & 1 == 1 {
+      self.a = out;
+    }
+    if instruction >> 4 & 1 == 1 {
+      self.d = out;
+    }
+    let unit = if self.extended { instruction >> 13 & 0b11 } else { 0b11 };
+    let jump = match unit {
+      // the flags of before this instruction, which does not latch its own
+      0b10 => (instruction >> 2 & 1 == 1 && self.carry)
+        || (instruction >> 1 & 1 == 1 && self.overflow)
+        || (instruction & 1 == 1 && !self.carry),
+      _ => (instruction >> 2 & 1 == 1 && out < 0)
+        || (instruction >> 1 & 1 == 1 && out == 0)
+        || (instruction & 1 == 1 && out > 0),
+    };
+    if self.extended && unit == 0b11 && instruction >> 7 & 1 == 1 {
+      self.carry = carry;
+      self.overflow = overflow;
+    }
+    self.pc = if jump { target } else { self.pc.wrapping_add(1) };
+  }
+
+  // Runs until the pc leaves the program, or for at most `cycles` instructions
+  pub fn run(&mut self, cycles: u64) {
+    let end = self.cycles + cycles;
+    while self.cycles < end && (self.pc as usize) < self.rom.len() {
+      self.step();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alu::{extended_alu, extended_jump};
+    use crate::chip::{Chip, Register};
+    use crate::gates::Signal::{self, *};
+    use crate::utilities::*;
+
+    #[test]
+    fn tassemble() {
+        let program = assemble("// Adds R0 and R1\n@R0\nD=M\n@R1\nD=D+M // sum\n@2\nM=D\n(END)\n@END\n0;JMP\n@x\nAM=M-1\n@y\n", false).unwrap();
+        assert_eq!(program, vec![0, 0b1111110000010000, 1, 0b1111000010010000, 2, 0b1110001100001000, 6, 0b1110101010000111, 16, 0b1111110010101000, 17]);
+        assert_eq!(assemble("D=D*A", false), Err(AsmError { line: 1, message: "unknown computation D*A".to_string() }));
+        assert_eq!(assemble("@1\nDD=A", false).unwrap_err().line, 2);
+        assert!(assemble("(LOOP)\n(LOOP)", false).is_err());
+        assert!(assemble("@32768", false).is_err());
+        assert!(assemble("A=D;JUMP", false).is_err());
+    }
+    #[test]
+    fn textended() {
+        let program = assemble("D=D*A\nD=D*M\nD=D<<\nM=D>>\nAD=A<<\nD=M>>;JEQ", true).unwrap();
+        assert_eq!(program, vec![0b1000000000010000, 0b1001000000010000, 0b1010000000010000, 0b1010010000001000, 0b1010100000110000, 0b1011110000010010]);
+        for &instruction in program.iter() {
+            let text = disassemble(instruction, true).unwrap();
+            assert_eq!(assemble(&text, true).unwrap(), vec![instruction]);
+        }
+        assert_eq!(disassemble(0b1000000000010000, false), Some("D=D&A".to_string()));
+        assert_eq!(disassemble(0b1100000000010000, true), None);
+        // bits 14..13 = 10 jump on the flags but compute with the standard alu
+        let flags = 0b1100000010010000;
+        for &(d, a) in [(5i16, 7i16), (-3, 12), (32767, 1)].iter() {
+            assert_eq!(compute(flags, d, a, true), compute(flags | 0b0110000000000000, d, a, true));
+            let mut computer = Computer::new(vec![a as u16, flags], true);
+            computer.d = d;
+            computer.run(2);
+            assert_eq!(computer.d, d.wrapping_add(a));
+        }
+        assert_eq!(disassemble(0b1010000001010000, true), None);
+        let program = assemble("D=D+A;JC\n0;JNC\nD;JV\nAM=M-1;JC", true).unwrap();
+        assert_eq!(program, vec![0b1100000010010100, 0b1100101010000001, 0b1100001100000010, 0b1101110010101100]);
+        for &instruction in program.iter() {
+            let text = disassemble(instruction, true).unwrap();
+            assert_eq!(assemble(&text, true).unwrap(), vec![instruction]);
+        }
+        assert_eq!(disassemble(0b1100000010010110, true), None);
+        assert_eq!(assemble("D=D*A;JC", true).unwrap_err().message, "jump JC needs a computation of the alu");
+        assert_eq!(assemble("0;JC", false).unwrap_err().message, "unknown jump JC");
+    }
+    #[test]
+    fn tdisassemble() {
+        let source = "@21\nD=A\nM=!D\nAMD=D|M;JLE\n0;JMP\nD;JGT\nMD=M+1\nA=-1";
+        let program = assemble(source, false).unwrap();
+        let text: Vec<String> = program.iter().map(|&i| disassemble(i, false).unwrap()).collect();
+        assert_eq!(text.join("\n"), source);
+        assert_eq!(disassemble(0b1110111011000000, false), None);
+    }
+    #[test]
+    fn tcompute_matches_gates() {
+        let values = [0i16, 1, -1, 7, -300, 12345, -32768, 32767];
+        for instruction in (0b1000000000000000..=0b1111111111000000u16).step_by(64) {
+            for &x in values.iter() {
+                for &y in values.iter() {
+                    let bit = |i: u16| if instruction >> i & 1 == 1 { High } else { Low };
+                    let (out, _, _, carry, overflow) = extended_alu(int_to_binary16(x), int_to_binary16(y), bit(14), bit(13), bit(11), bit(10), bit(9), bit(8), bit(7), bit(6));
+                    assert_eq!(out, int_to_binary16(compute(instruction, x, y, true)));
+                    let (_, c, v) = alu(x, y, instruction >> 6 & 0b111111);
+                    let standard = instruction >> 14 & 1 == 1;
+                    assert_eq!((carry, overflow), (Signal::from(standard && c), Signal::from(standard && v)));
+                }
+            }
+        }
+    }
+    #[test]
+    fn tcomputer() {
+        let source = "@R0\nD=M\n@R1\nD=D*M\n@R2\nM=D";
+        let mut computer = Computer::new(assemble(source, true).unwrap(), true);
+        computer.ram[0] = 123;
+        computer.ram[1] = -45;
+        computer.run(100);
+        assert_eq!((computer.ram[2], computer.cycles), (-5535, 6));
+        let mut standard = Computer::new(assemble(source, true).unwrap(), false);
+        standard.ram[0] = 12;
+        standard.ram[1] = 10;
+        standard.run(100);
+        assert_eq!(standard.ram[2], 8);
+    }
+    #[test]
+    fn thardware_multiply() {
+        let software = "@R2\nM=0\n(LOOP)\n@R1\nD=M\n@END\nD;JEQ\n@R1\nM=M-1\n@R0\nD=M\n@R2\nM=D+M\n@LOOP\n0;JMP\n(END)";
+        let mut slow = Computer::new(assemble(software, false).unwrap(), false);
+        let mut fast = Computer::new(assemble("@R0\nD=M\n@R1\nD=D*M\n@R2\nM=D", true).unwrap(), true);
+        for computer in [&mut slow, &mut fast].iter_mut() {
+            computer.ram[0] = 37;
+            computer.ram[1] = 100;
+            computer.run(100_000);
+            assert_eq!(computer.ram[2], 3700);
+        }
+        assert!(slow.cycles > 100 * fast.cycles);
+    }
+    #[test]
+    fn tflags() {
+        // 32 bit addition of R1:R0 and R3:R2 into R5:R4, the carry of the low words goes to the high word
+        let source = "@R1\nD=M\n@R3\nD=D+M\n@R5\nM=D\n@R0\nD=M\n@R2\nD=D+M\n@R4\nM=D\n@END\n0;JNC\n@R5\nM=M+1\n(END)";
+        for &(x, y) in [(70000i32, 80000i32), (-1, 1), (123456, -654321), (65535, 65535), (-65536, -1)].iter() {
+            let mut computer = Computer::new(assemble(source, true).unwrap(), true);
+            computer.ram[..4].copy_from_slice(&[x as i16, (x >> 16) as i16, y as i16, (y >> 16) as i16]);
+            computer.run(100);
+            assert_eq!(computer.ram[4] as u16 as i32 | (computer.ram[5] as i32) << 16, x.wrapping_add(y));
+        }
+        // D+1 is !(!D + -1) in the alu, which carries here. Both flags are kept by the moves, which do not add
+        let mut computer = Computer::new(assemble("@32767\nD=A\nD=D+1\n@R0\nM=D\n@OVERFLOW\nD;JV\n@R1\nM=1\n(OVERFLOW)", true).unwrap(), true);
+        computer.run(100);
+        assert_eq!((computer.ram[0], computer.ram[1], computer.carry, computer.overflow), (-32768, 0, true, true));
+        // without the extension the flags are never latched
+        let mut standard = Computer::new(assemble("@32767\nD=A\nD=D+1", true).unwrap(), false);
+        standard.run(100);
+        assert!(!standard.overflow);
+    }
+    // The gate-level path (extended_alu, a two bit register of flags and extended_jump) follows the emulator
+    #[test]
+    fn tflags_match_gates() {
+        let source = "@R0\nD=M\n@R1\nD=D+M;JC\n@R2\nM=D\nD=D-1\n@8\nD;JNC\n@R1\nM=M-1\nD=D*M\nAD=A+1;JV\n@4\nD=M>>;JLT\n0;JMP";
+        let rom = assemble(source, true).unwrap();
+        for &(x, y) in [(-1i16, 1i16), (32767, 5), (1000, -3000), (-32768, -32768), (0, 0)].iter() {
+            let mut computer = Computer::new(rom.clone(), true);
+            computer.ram[0] = x;
+            computer.ram[1] = y;
+            let mut flags = Register::<2>::default();
+            while computer.cycles < 200 && (computer.pc as usize) < rom.len() {
+                let instruction = rom[computer.pc as usize];
+                let bit = |i: u16| Signal::from(instruction >> i & 1 == 1);
+                let y = if instruction >> 12 & 1 == 1 { computer.ram[computer.a as u16 as usize % RAM_SIZE] } else { computer.a };
+                let (_, zr, ng, carry, overflow) = extended_alu(int_to_binary16(computer.d), int_to_binary16(y), bit(14), bit(13), bit(11), bit(10), bit(9), bit(8), bit(7), bit(6));
+                let load = Signal::from(instruction >> 15 == 1 && instruction >> 13 & 0b11 == 0b11 && instruction >> 7 & 1 == 1);
+                let [c, v] = flags.compute(([carry, overflow], load));
+                assert_eq!([c, v], [Signal::from(computer.carry), Signal::from(computer.overflow)]);
+                let jump = extended_jump(bit(14), bit(13), bit(2), bit(1), bit(0), zr, ng, c, v);
+                let target = computer.a as u16;
+                let next = computer.pc + 1;
+                computer.step();
+                if instruction >> 15 == 1 {
+                    assert_eq!(computer.pc, if jump == High { target } else { next }, "{}", disassemble(instruction, true).unwrap());
+                }
+            }
+        }
+    }
+    #[test]
+    fn tjumps() {
+        let mut computer = Computer::new(assemble("@5\nD=A\n(LOOP)\n@LOOP\nD=D-1;JGT\n@R0\nM=D", false).unwrap(), false);
+        computer.run(1000);
+        assert_eq!((computer.ram[0], computer.pc), (0, 6));
+        computer.reset();
+        assert_eq!(computer.pc, 0);
+        // A=D;JMP jumps to the address in A before the instruction, not to D
+        let mut computer = Computer::new(assemble("@7\nD=A\n@5\nA=D;JMP\n@R0\nM=1\n@R1\nM=1", false).unwrap(), false);
+        computer.run(5);
+        assert_eq!((computer.pc, computer.a), (6, 7));
+        computer.run(100);
+        assert_eq!((computer.ram[0], computer.ram[1]), (0, 1));
+    }
+}
@@ -0,0 +1,168 @@
+use crate::gates::*;
+use std::convert::TryFrom;
+use std::fmt;
+
+// Four valued logic for simulations with unknown state: X is a signal that can be either
+// High or Low (an uninitialized register, a bus with conflicting drivers), Z an undriven
+// wire. Nand is the only gate, so its table below decides how X and Z propagate through
+// every chip: a Low input forces the output High, anything else unknown gives X.
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Level {
+    Low,
+    High,
+    X,
+    Z,
+}
+
+use Level::*;
+
+impl Logic for Level {
+  const LOW: Level = Low;
+  const HIGH: Level = High;
+  fn nand(self, other: Level) -> Level {
+    match (self, other) {
+        (Low, _) | (_, Low) => High,
+        (High, High) => Low,
+        _ => X,
+    }
+  }
+}
+
+impl From<Signal> for Level {
+  fn from(signal: Signal) -> Level {
+    match signal {
+        Signal::Low => Low,
+        Signal::High => High,
+    }
+  }
+}
+
+// Fails on X and Z, which have no two valued equivalent
+impl TryFrom<Level> for Signal {
+  type Error = Level;
+  fn try_from(level: Level) -> Result<Signal, Level> {
+    match level {
+        Low => Ok(Signal::Low),
+        High => Ok(Signal::High),
+        _ => Err(level),
+    }
+  }
+}
+
+impl fmt::Display for Level {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let c = match self {
+        Low => '0',
+        High => '1',
+        X => 'x',
+        Z => 'z',
+    };
+    write!(f, "{}", c)
+  }
+}
+
+pub fn levels<const BITS: usize>(in1: Signals<BITS>) -> Signals<BITS, Level> {
+  let mut out = [Low; BITS];
+  for (level, &signal) in out.iter_mut().zip(in1.iter()) {
+    *level = Level::from(signal);
+  }
+  out
+}
+
+pub fn is_known(level: Level) -> bool {
+  level == Low || level == High
+}
+
+// Tri-state buffer: drives in1 when enable is High, floats otherwise
+pub fn tristate(in1: Level, enable: Level) -> Level {
+  match enable {
+      High => match in1 {
+          Z => X,
+          _ => in1,
+      },
+      Low => Z,
+      _ => X,
+  }
+}
+
+pub fn tristate_n<const BITS: usize>(in1: Signals<BITS, Level>, enable: Level) -> Signals<BITS, Level> {
+  let mut out = in1;
+  for level in out.iter_mut() {
+    *level = tristate(*level, enable);
+  }
+  out
+}
+
+// Value of a wire connected to several drivers: floating drivers are ignored, agreeing ones
+// give their value and conflicting ones X. Z if nothing drives the wire.
+pub fn resolve(drivers: &[Level]) -> Level {
+  drivers.iter().fold(Z, |acc, &level| match (acc, level) {
+      (Z, level) | (level, Z) => level,
+      (a, b) if a == b => a,
+      _ => X,
+  })
+}
+
+pub fn resolve_n<const BITS: usize>(drivers: &[Signals<BITS, Level>]) -> Signals<BITS, Level> {
+  let mut out = [Z; BITS];
+  for (i, level) in out.iter_mut().enumerate() {
+    *level = resolve(&drivers.iter().map(|driver| driver[i]).collect::<Vec<Level>>());
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alu;
+    use crate::netlist;
+    use crate::utilities::*;
+
+    #[test]
+    fn tnand() {
+        assert_eq!(nand(Low, X), High);
+        assert_eq!(nand(Z, Low), High);
+        assert_eq!(nand(High, X), X);
+        assert_eq!(nand(High, Z), X);
+        assert_eq!(nand(High, High), Low);
+        assert_eq!(and(Low, X), Low);
+        assert_eq!(or(High, X), High);
+        assert_eq!(mux(High, High, X), X);
+        assert_eq!(mux(Low, Low, X), Low);
+        assert_eq!(Signal::try_from(X), Err(X));
+        assert_eq!(Signal::try_from(Level::from(Signal::High)), Ok(Signal::High));
+    }
+    #[test]
+    fn tunknown_leaks() {
+        let mut a = levels(int_to_binary16(1234));
+        a[15] = X;
+        // the and masks the unknown bit when the other operand is even
+        let (out, zr, ng) = alu::alu(a, levels(int_to_binary16(-2)), Low, Low, Low, Low, Low, Low);
+        assert!(out.iter().all(|&level| is_known(level)));
+        assert_eq!((zr, ng), (Low, Low));
+        // the sum does not, and zr stays known as long as a known bit of out is High
+        let (out, zr, ng) = alu::alu(a, levels(int_to_binary16(1)), Low, Low, Low, Low, High, Low);
+        assert_eq!(out[15], X);
+        assert_eq!((zr, ng), (Low, Low));
+        let mut zero = [Low; 16];
+        zero[15] = X;
+        let (out, zr, _) = alu::alu(zero, [Low; 16], Low, Low, Low, Low, High, Low);
+        assert_eq!((out, zr), (zero, X));
+        let netlist = netlist::n_adder::<4>();
+        assert_eq!(netlist.evaluate(&[Low, Low, Low, X, Low, Low, Low, Low]), vec![Low, Low, Low, X]);
+    }
+    #[test]
+    fn ttristate() {
+        let bus = levels([Signal::High, Signal::Low]);
+        assert_eq!(tristate_n(bus, Low), [Z, Z]);
+        assert_eq!(tristate_n(bus, High), bus);
+        assert_eq!(tristate(Low, X), X);
+        assert_eq!(resolve(&[]), Z);
+        assert_eq!(resolve(&[Z, High, Z]), High);
+        assert_eq!(resolve(&[High, High]), High);
+        assert_eq!(resolve(&[High, Low]), X);
+        assert_eq!(resolve_n(&[tristate_n(bus, Low), tristate_n(levels([Signal::Low, Signal::Low]), High)]), [Low, Low]);
+        assert_eq!(format!("{}{}{}{}", Low, High, X, Z), "01xz");
+    }
+}
@@ -0,0 +1,27 @@
+#![allow(clippy::too_many_arguments)]
+
+pub mod gates;
+pub mod alu;
+pub mod utilities;
+pub mod netlist;
+pub mod cost;
+pub mod timing;
+pub mod arithmetic;
+pub mod shifter;
+pub mod comparator;
+pub mod hack;
+pub mod packed;
+pub mod bitslice;
+pub mod level;
+pub mod bits;
+pub mod chip;
+pub mod truth_table;
+pub mod synthesis;
+pub mod sat;
+pub mod equivalence;
+pub mod bdd;
+pub mod fault;
+pub mod atpg;
+pub mod vcd;
+pub mod schematic;
+pub mod verilog;
@@ -1,11 +1,266 @@
-mod gates;
-mod alu;
-mod utilities;
-use gates::Signal::*;
-use alu::*;
-use utilities::*;
+use nand_to_tetris::gates::Signal::*;
+use nand_to_tetris::alu::*;
+use nand_to_tetris::utilities::*;
+use nand_to_tetris::{atpg, cost, equivalence, fault, hack, netlist, schematic, synthesis, timing, truth_table, vcd, verilog};
+use std::fs;
+use std::path::Path;
+use std::process;
 
-fn main() {
+fn usage() -> ! {
+    eprintln!("usage: nand_to_tetris [cost|timing|table [CHIP...]]");
+    eprintln!("       nand_to_tetris synth CHIP...|OUTPUT=EXPRESSION...");
+    eprintln!("       nand_to_tetris equiv CHIP CHIP");
+    eprintln!("       nand_to_tetris faults CHIP VECTORS");
+    eprintln!("       nand_to_tetris atpg CHIP [STEM]");
+    eprintln!("       nand_to_tetris dot|svg CHIP [DEPTH|flat]");
+    eprintln!("       nand_to_tetris verilog CHIP [FILE.tst]|register::<BITS>");
+    eprintln!("       nand_to_tetris asm|disasm [--extended] FILE");
+    eprintln!("       nand_to_tetris run [--extended] FILE.hack [CYCLES]");
+    eprintln!("       nand_to_tetris vcd [--extended] FILE.hack CYCLES FILE.vcd");
+    process::exit(1);
+}
+
+// Netlists named on the command line, or the whole library when none is given
+fn chips(names: &[String]) -> Vec<netlist::Netlist> {
+    if names.is_empty() {
+        return netlist::library();
+    }
+    names.iter().map(|name| netlist::find(name).unwrap_or_else(|| {
+        eprintln!("unknown chip {}, expected one of:", name);
+        for chip in netlist::library() {
+            eprintln!("  {}", chip.name);
+        }
+        process::exit(1);
+    })).collect()
+}
+
+fn read(path: &str) -> String {
+    fs::read_to_string(path).unwrap_or_else(|error| {
+        eprintln!("cannot read {}: {}", path, error);
+        process::exit(1);
+    })
+}
+
+// Binary programs are text files with one 16 characters word of 0 and 1 per line
+fn read_hack(path: &str) -> Vec<u16> {
+    read(path).lines().filter(|line| !line.trim().is_empty()).map(|line| u16::from_str_radix(line.trim(), 2).unwrap_or_else(|_| {
+        eprintln!("{} is not a binary instruction", line);
+        process::exit(1);
+    })).collect()
+}
+
+fn hack_command(command: &str, args: &[String]) {
+    let extended = args.first().map(|arg| arg == "--extended").unwrap_or(false);
+    let args = if extended { &args[1..] } else { args };
+    let path = args.first().unwrap_or_else(|| usage());
+    match command {
+        "asm" => match hack::assemble(&read(path), extended) {
+            Ok(program) => program.iter().for_each(|instruction| println!("{:016b}", instruction)),
+            Err(error) => {
+                eprintln!("{}: {}", path, error);
+                process::exit(1);
+            },
+        },
+        "disasm" => {
+            for instruction in read_hack(path) {
+                match hack::disassemble(instruction, extended) {
+                    Some(text) => println!("{}", text),
+                    None => println!("// invalid instruction {:016b}", instruction),
+                }
+            }
+        },
+        "vcd" => {
+            let cycles = args.get(1).map(|cycles| cycles.parse().unwrap_or_else(|_| usage())).unwrap_or_else(|| usage());
+            let output = args.get(2).unwrap_or_else(|| usage());
+            let mut computer = hack::Computer::new(read_hack(path), extended);
+            let tracer = vcd::trace_computer(&mut computer, cycles);
+            fs::write(output, tracer.to_string()).unwrap_or_else(|error| {
+                eprintln!("cannot write {}: {}", output, error);
+                process::exit(1);
+            });
+            println!("{} cycles traced to {}", computer.cycles, output);
+        },
+        _ => {
+            let cycles = args.get(1).map(|cycles| cycles.parse().unwrap_or_else(|_| usage())).unwrap_or(1_000_000);
+            let mut computer = hack::Computer::new(read_hack(path), extended);
+            computer.run(cycles);
+            println!("cycles {} pc {} a {} d {}", computer.cycles, computer.pc, computer.a, computer.d);
+            if extended {
+                println!("carry {} overflow {}", computer.carry as u8, computer.overflow as u8);
+            }
+            for (address, value) in computer.ram.iter().enumerate().take(16) {
+                println!("R{} {}", address, value);
+            }
+        },
+    }
+}
+
+// Quine-McCluskey is exponential in the number of inputs
+const SYNTH_LIMIT: usize = 12;
+
+// Synthesizes library chips to compare them with the hand written ones, or expressions
+fn synth(args: &[String]) {
+    if args.iter().any(|arg| arg.contains('=')) {
+        let outputs: Vec<(&str, &str)> = args.iter().map(|arg| match arg.find('=') {
+            Some(i) => (arg[..i].trim(), &arg[i + 1..]),
+            None => usage(),
+        }).collect();
+        match synthesis::from_expressions(&outputs) {
+            Ok(function) => print!("{}", synthesis::synthesize("synthesized", &function)),
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            },
+        }
+        return;
+    }
+    if args.is_empty() {
+        usage();
+    }
+    for mut chip in chips(args) {
+        match truth_table::truth_table_with_limit(&mut chip, SYNTH_LIMIT) {
+            Ok(table) => {
+                let synthesized = synthesis::synthesize(&chip.name, &synthesis::from_truth_table(&table));
+                println!("{}: {} nands synthesized, {} hand written", chip.name, synthesized.nand_count(), chip.nand_count());
+            },
+            Err(error) => println!("{}: {}", chip.name, error),
+        }
+    }
+}
+
+fn equiv(args: &[String]) {
+    if args.len() != 2 {
+        usage();
+    }
+    let pair = chips(args);
+    match equivalence::check(&pair[0], &pair[1]) {
+        Ok(equivalence::Equivalence::Equivalent) => println!("{} and {} are equivalent", pair[0].name, pair[1].name),
+        Ok(equivalence::Equivalence::Counterexample { input, outputs1, outputs2 }) => {
+            let bits = |signals: &[nand_to_tetris::gates::Signal]| signals.iter().map(|s| s.to_string()).collect::<String>();
+            println!("{} and {} differ on input {}: {} against {}", pair[0].name, pair[1].name, bits(&input), bits(&outputs1), bits(&outputs2));
+            process::exit(2);
+        },
+        Err(error) => {
+            eprintln!("{} and {}: {}", pair[0].name, pair[1].name, error);
+            process::exit(1);
+        },
+    }
+}
+
+// Grades a test file by the stuck-at faults its vectors detect, the file being a .cmp or
+// any text with one bit string of inputs per line
+fn faults(args: &[String]) {
+    if args.len() != 2 {
+        usage();
+    }
+    let chip = chips(&args[..1]).remove(0);
+    match fault::parse_vectors(&read(&args[1]), chip.input_width()) {
+        Ok(vectors) => print!("{}", fault::coverage(&chip, &vectors)),
+        Err(error) => {
+            eprintln!("{}: {}", args[1], error);
+            process::exit(1);
+        },
+    }
+}
+
+// Writes STEM.tst and STEM.cmp, the stem defaulting to the chip name in camel case,
+// e.g. Mux8WayN for mux_8_way_n::<16>
+fn generate_tests(args: &[String]) {
+    if args.is_empty() || args.len() > 2 {
+        usage();
+    }
+    let chip = chips(&args[..1]).remove(0);
+    let stem = args.get(1).cloned().unwrap_or_else(|| {
+        let name = chip.name.split("::").next().unwrap_or(&chip.name);
+        name.split('_').map(|word| word[..1].to_uppercase() + &word[1..]).collect()
+    });
+    let tests = atpg::generate(&chip);
+    for (extension, contents) in [("tst", atpg::tst(&chip, &tests.vectors, &stem)), ("cmp", atpg::cmp(&chip, &tests.vectors))].iter() {
+        let path = format!("{}.{}", stem, extension);
+        fs::write(&path, contents).unwrap_or_else(|error| {
+            eprintln!("cannot write {}: {}", path, error);
+            process::exit(1);
+        });
+    }
+    println!("{}", tests);
+    // redundant faults per subchip, a long list for the alu with its constant mux inputs
+    let mut scopes: Vec<(String, usize)> = vec![];
+    for fault in tests.redundant.iter() {
+        let scope = chip.scope_path(chip.nodes[fault.wire].scope);
+        match scopes.iter_mut().find(|(name, _)| *name == scope) {
+            Some((_, count)) => *count += 1,
+            None => scopes.push((scope, 1)),
+        }
+    }
+    for (scope, count) in scopes {
+        println!("  {} redundant in {}", count, scope);
+    }
+}
+
+// Subchips of the chip as blocks by default, the level below its top scope
+const SCHEMATIC_DEPTH: usize = 2;
+
+fn schematic(command: &str, args: &[String]) {
+    if args.is_empty() || args.len() > 2 {
+        usage();
+    }
+    let chip = chips(&args[..1]).remove(0);
+    let depth = match args.get(1).map(|arg| arg.as_str()) {
+        None => Some(SCHEMATIC_DEPTH),
+        Some("flat") => None,
+        Some(depth) => Some(depth.parse().unwrap_or_else(|_| usage())),
+    };
+    match command {
+        "dot" => print!("{}", schematic::dot(&chip, depth)),
+        _ => print!("{}", schematic::svg(&chip, depth)),
+    }
+}
+
+// Modules of the chip and a testbench for the vectors of the script, or the registers. The
+// expected outputs are the ones of the compare-to file of the script when it has one.
+fn verilog(args: &[String]) {
+    if args.is_empty() || args.len() > 2 {
+        usage();
+    }
+    if let Some(bits) = args[0].strip_prefix("register::<").and_then(|rest| rest.strip_suffix('>')) {
+        match bits.parse() {
+            Ok(bits) if bits > 0 && args.len() == 1 => print!("{}", verilog::register(bits)),
+            _ => usage(),
+        }
+        return;
+    }
+    let chip = chips(&args[..1]).remove(0);
+    let fail = |path: &str, error: &dyn std::fmt::Display| -> ! {
+        eprintln!("{}: {}", path, error);
+        process::exit(1);
+    };
+    let testbench = args.get(1).map(|path| {
+        let script = read(path);
+        let vectors = atpg::parse_tst(&script, &chip).unwrap_or_else(|error| fail(path, &error));
+        let expected = match atpg::compare_to(&script) {
+            Some(file) => {
+                let cmp = Path::new(path).with_file_name(file);
+                let cmp = cmp.to_string_lossy();
+                let rows = atpg::parse_cmp(&read(&cmp), &chip).unwrap_or_else(|error| fail(&cmp, &error));
+                if rows.len() != vectors.len() {
+                    fail(&cmp, &format!("{} binary rows for the {} outputs of {}", rows.len(), vectors.len(), path));
+                }
+                if let Some(row) = rows.iter().zip(vectors.iter()).position(|((inputs, _), vector)| inputs != vector) {
+                    fail(&cmp, &format!("the inputs of row {} are not the ones set by {}", row + 1, path));
+                }
+                rows.into_iter().map(|(_, outputs)| outputs).collect()
+            },
+            None => vectors.iter().map(|vector| chip.evaluate(vector)).collect::<Vec<_>>(),
+        };
+        verilog::testbench(&chip, &vectors, &expected)
+    });
+    print!("{}", verilog::modules(&chip));
+    if let Some(testbench) = testbench {
+        print!("\n{}", testbench);
+    }
+}
+
+fn demo() {
     let x = 5;
     let y = - 9;
     let a = int_to_binary16(x);
@@ -16,4 +271,40 @@ fn main() {
     println!("{:?}", b);
     println!("{:?}", result);
     println!("{:?}", binary_to_int16(result.0));
-}
\ No newline at end of file
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(|arg| arg.as_str()) {
+        None => demo(),
+        Some("cost") => {
+            for chip in chips(&args[1..]) {
+                println!("{}", cost::cost(&chip));
+            }
+        },
+        Some("timing") => {
+            for chip in chips(&args[1..]) {
+                match timing::critical_path(&chip) {
+                    Some(path) => println!("{}: {}\n", chip.name, path),
+                    None => println!("{}: no path from an input to an output\n", chip.name),
+                }
+            }
+        },
+        Some("table") => {
+            for mut chip in chips(&args[1..]) {
+                match truth_table::truth_table(&mut chip) {
+                    Ok(table) => println!("{}:\n{}", chip.name, table),
+                    Err(error) => println!("{}: {}\n", chip.name, error),
+                }
+            }
+        },
+        Some("synth") => synth(&args[1..]),
+        Some("equiv") => equiv(&args[1..]),
+        Some("faults") => faults(&args[1..]),
+        Some("atpg") => generate_tests(&args[1..]),
+        Some(command @ "dot") | Some(command @ "svg") => schematic(command, &args[1..]),
+        Some("verilog") => verilog(&args[1..]),
+        Some(command @ "asm") | Some(command @ "disasm") | Some(command @ "run") | Some(command @ "vcd") => hack_command(command, &args[1..]),
+        Some(_) => usage(),
+    }
+}
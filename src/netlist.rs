@@ -0,0 +1,1075 @@
+use crate::gates::{Logic, Signal};
+use crate::alu::GROUP;
+use Signal::*;
+use std::fmt;
+
+// A netlist is a flat list of nodes in topological order, every node drives exactly
+// one wire and the wire id is the index of its node. Subchips are kept as scopes so
+// the hierarchy of the gates.rs functions survives the flattening.
+
+pub type Wire = usize;
+pub type Bus<const BITS: usize> = [Wire; BITS];
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Gate {
+    Input,
+    Constant(Signal),
+    Nand(Wire, Wire),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Node {
+    pub gate: Gate,
+    pub scope: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Scope {
+    pub name: String,
+    pub parent: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Port {
+    pub name: String,
+    pub wires: Vec<Wire>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Netlist {
+    pub name: String,
+    pub nodes: Vec<Node>,
+    pub scopes: Vec<Scope>,
+    pub inputs: Vec<Port>,
+    pub outputs: Vec<Port>,
+}
+
+impl Netlist {
+  pub fn input_width(&self) -> usize {
+    self.inputs.iter().map(|port| port.wires.len()).sum()
+  }
+
+  pub fn output_width(&self) -> usize {
+    self.outputs.iter().map(|port| port.wires.len()).sum()
+  }
+
+  pub fn nand_count(&self) -> usize {
+    self.nodes.iter().filter(|node| matches!(node.gate, Gate::Nand(_, _))).count()
+  }
+
+  // Full path of a scope from the root, e.g. "alu/n_adder/full_adder/half_adder/xor"
+  pub fn scope_path(&self, scope: usize) -> String {
+    match self.scopes[scope].parent {
+      Some(parent) => format!("{}/{}", self.scope_path(parent), self.scopes[scope].name),
+      None => self.scopes[scope].name.clone(),
+    }
+  }
+
+  // Inputs are given port after port, in the order the ports were declared
+  // Generic over the signal type, with u64 wires every call simulates 64 input vectors at once
+  pub fn simulate<S: Logic>(&self, inputs: &[S]) -> Vec<S> {
+    assert_eq!(inputs.len(), self.input_width(), "wrong number of inputs for {}", self.name);
+    let mut values = vec![S::LOW; self.nodes.len()];
+    let input_wires = self.inputs.iter().flat_map(|port| port.wires.iter());
+    for (&wire, &signal) in input_wires.zip(inputs.iter()) {
+      values[wire] = signal;
+    }
+    for (wire, node) in self.nodes.iter().enumerate() {
+      match node.gate {
+        Gate::Input => (),
+        Gate::Constant(Low) => values[wire] = S::LOW,
+        Gate::Constant(High) => values[wire] = S::HIGH,
+        Gate::Nand(in1, in2) => values[wire] = values[in1].nand(values[in2]),
+      }
+    }
+    values
+  }
+
+  // Outputs are returned port after port, in the order the ports were declared
+  pub fn evaluate<S: Logic>(&self, inputs: &[S]) -> Vec<S> {
+    let values = self.simulate(inputs);
+    self.outputs.iter().flat_map(|port| port.wires.iter()).map(|&wire| values[wire]).collect()
+  }
+}
+
+impl fmt::Display for Netlist {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let ports = |ports: &[Port]| ports.iter().map(|port| match port.wires.len() {
+      1 => port.name.clone(),
+      width => format!("{}[{}]", port.name, width),
+    }).collect::<Vec<String>>().join(", ");
+    writeln!(f, "CHIP {} {{", self.name)?;
+    writeln!(f, "  IN {};", ports(&self.inputs))?;
+    writeln!(f, "  OUT {};", ports(&self.outputs))?;
+    writeln!(f, "  NODES:")?;
+    for (wire, node) in self.nodes.iter().enumerate() {
+      match node.gate {
+        Gate::Input => (),
+        Gate::Constant(signal) => writeln!(f, "  w{} = {:?};", wire, signal)?,
+        Gate::Nand(in1, in2) => writeln!(f, "  w{} = nand(w{}, w{}); // {}", wire, in1, in2, self.scope_path(node.scope))?,
+      }
+    }
+    for port in self.inputs.iter().chain(self.outputs.iter()) {
+      let wires = port.wires.iter().map(|wire| format!("w{}", wire)).collect::<Vec<String>>().join(", ");
+      writeln!(f, "  {} = [{}];", port.name, wires)?;
+    }
+    write!(f, "}}")
+  }
+}
+
+pub struct Builder {
+    netlist: Netlist,
+    scope: usize,
+    low: Option<Wire>,
+    high: Option<Wire>,
+}
+
+impl Builder {
+  pub fn new(name: &str) -> Builder {
+    Builder {
+      netlist: Netlist {
+        name: name.to_string(),
+        nodes: vec![],
+        scopes: vec![Scope { name: name.to_string(), parent: None }],
+        inputs: vec![],
+        outputs: vec![],
+      },
+      scope: 0,
+      low: None,
+      high: None,
+    }
+  }
+
+  pub fn build(self) -> Netlist {
+    self.netlist
+  }
+
+  fn push(&mut self, gate: Gate, scope: usize) -> Wire {
+    self.netlist.nodes.push(Node { gate, scope });
+    self.netlist.nodes.len() - 1
+  }
+
+  pub fn input(&mut self, name: &str) -> Wire {
+    let wire = self.push(Gate::Input, 0);
+    self.netlist.inputs.push(Port { name: name.to_string(), wires: vec![wire] });
+    wire
+  }
+
+  pub fn input_bus<const BITS: usize>(&mut self, name: &str) -> Bus<BITS> {
+    let mut bus = [0; BITS];
+    for wire in bus.iter_mut() {
+      *wire = self.push(Gate::Input, 0);
+    }
+    self.netlist.inputs.push(Port { name: name.to_string(), wires: bus.to_vec() });
+    bus
+  }
+
+  pub fn output(&mut self, name: &str, wire: Wire) {
+    self.output_bus(name, &[wire]);
+  }
+
+  pub fn output_bus(&mut self, name: &str, wires: &[Wire]) {
+    self.netlist.outputs.push(Port { name: name.to_string(), wires: wires.to_vec() });
+  }
+
+  // Constants are shared by the whole netlist, like the true/false pins of the HDL
+  pub fn constant(&mut self, signal: Signal) -> Wire {
+    let cached = match signal {
+      Low => self.low,
+      High => self.high,
+    };
+    cached.unwrap_or_else(|| {
+      let wire = self.push(Gate::Constant(signal), 0);
+      match signal {
+        Low => self.low = Some(wire),
+        High => self.high = Some(wire),
+      }
+      wire
+    })
+  }
+
+  pub fn nand(&mut self, in1: Wire, in2: Wire) -> Wire {
+    self.push(Gate::Nand(in1, in2), self.scope)
+  }
+
+  // Every node created by `body` is attributed to a new subchip called `name`
+  pub fn chip<T>(&mut self, name: &str, body: impl FnOnce(&mut Builder) -> T) -> T {
+    let parent = self.scope;
+    self.netlist.scopes.push(Scope { name: name.to_string(), parent: Some(parent) });
+    self.scope = self.netlist.scopes.len() - 1;
+    let result = body(self);
+    self.scope = parent;
+    result
+  }
+
+  pub fn not(&mut self, in1: Wire) -> Wire {
+    self.chip("not", |b| b.nand(in1, in1))
+  }
+
+  pub fn and(&mut self, in1: Wire, in2: Wire) -> Wire {
+    self.chip("and", |b| {
+      let n1 = b.nand(in1, in2);
+      let n2 = b.nand(in1, in2);
+      b.nand(n1, n2)
+    })
+  }
+
+  pub fn or(&mut self, in1: Wire, in2: Wire) -> Wire {
+    self.chip("or", |b| {
+      let n1 = b.nand(in1, in1);
+      let n2 = b.nand(in2, in2);
+      b.nand(n1, n2)
+    })
+  }
+
+  pub fn xor(&mut self, in1: Wire, in2: Wire) -> Wire {
+    self.chip("xor", |b| {
+      let n1 = b.nand(in1, in2);
+      let n2 = b.nand(n1, in1);
+      let n3 = b.nand(in1, in2);
+      let n4 = b.nand(n3, in2);
+      b.nand(n2, n4)
+    })
+  }
+
+  pub fn mux(&mut self, in1: Wire, in2: Wire, sel: Wire) -> Wire {
+    self.chip("mux", |b| {
+      let not_sel = b.nand(sel, sel);
+      let n1 = b.nand(in1, not_sel);
+      let n2 = b.nand(in2, sel);
+      b.nand(n1, n2)
+    })
+  }
+
+  pub fn dmux(&mut self, in1: Wire, sel: Wire) -> (Wire, Wire) {
+    self.chip("dmux", |b| {
+      let not_sel1 = b.nand(sel, sel);
+      let n1 = b.nand(in1, not_sel1);
+      let not_sel2 = b.nand(sel, sel);
+      let n2 = b.nand(in1, not_sel2);
+      let out1 = b.nand(n1, n2);
+      let n3 = b.nand(in1, sel);
+      let n4 = b.nand(in1, sel);
+      (out1, b.nand(n3, n4))
+    })
+  }
+
+  pub fn not_n<const BITS: usize>(&mut self, in1: Bus<BITS>) -> Bus<BITS> {
+    self.chip("not_n", |b| {
+      let mut out = [0; BITS];
+      for i in 0..BITS {
+        out[i] = b.not(in1[i]);
+      }
+      out
+    })
+  }
+
+  pub fn and_n<const BITS: usize>(&mut self, in1: Bus<BITS>, in2: Bus<BITS>) -> Bus<BITS> {
+    self.chip("and_n", |b| {
+      let mut out = [0; BITS];
+      for i in 0..BITS {
+        out[i] = b.and(in1[i], in2[i]);
+      }
+      out
+    })
+  }
+
+  pub fn or_n<const BITS: usize>(&mut self, in1: Bus<BITS>, in2: Bus<BITS>) -> Bus<BITS> {
+    self.chip("or_n", |b| {
+      let mut out = [0; BITS];
+      for i in 0..BITS {
+        out[i] = b.or(in1[i], in2[i]);
+      }
+      out
+    })
+  }
+
+  pub fn xor_n<const BITS: usize>(&mut self, in1: Bus<BITS>, in2: Bus<BITS>) -> Bus<BITS> {
+    self.chip("xor_n", |b| {
+      let mut out = [0; BITS];
+      for i in 0..BITS {
+        out[i] = b.xor(in1[i], in2[i]);
+      }
+      out
+    })
+  }
+
+  pub fn mux_n<const BITS: usize>(&mut self, in1: Bus<BITS>, in2: Bus<BITS>, sel: Wire) -> Bus<BITS> {
+    self.chip("mux_n", |b| {
+      let mut out = [0; BITS];
+      for i in 0..BITS {
+        out[i] = b.mux(in1[i], in2[i], sel);
+      }
+      out
+    })
+  }
+
+  pub fn or_m_way<const BITS: usize>(&mut self, in1: Bus<BITS>) -> Wire {
+    self.chip("or_m_way", |b| {
+      let low = b.constant(Low);
+      in1.iter().fold(low, |acc, &wire| b.or(acc, wire))
+    })
+  }
+
+  pub fn and_m_way<const BITS: usize>(&mut self, in1: Bus<BITS>) -> Wire {
+    self.chip("and_m_way", |b| {
+      let high = b.constant(High);
+      in1.iter().fold(high, |acc, &wire| b.and(acc, wire))
+    })
+  }
+
+  pub fn mux_4_way_n<const BITS: usize>(&mut self, in1: Bus<BITS>, in2: Bus<BITS>, in3: Bus<BITS>, in4: Bus<BITS>, sel1: Wire, sel2: Wire) -> Bus<BITS> {
+    self.chip("mux_4_way_n", |b| {
+      let low = b.mux_n(in1, in2, sel1);
+      let high = b.mux_n(in3, in4, sel1);
+      b.mux_n(low, high, sel2)
+    })
+  }
+
+  pub fn mux_8_way_n<const BITS: usize>(
+    &mut self,
+    in1: Bus<BITS>,
+    in2: Bus<BITS>,
+    in3: Bus<BITS>,
+    in4: Bus<BITS>,
+    in5: Bus<BITS>,
+    in6: Bus<BITS>,
+    in7: Bus<BITS>,
+    in8: Bus<BITS>,
+    sel1: Wire,
+    sel2: Wire,
+    sel3: Wire
+  ) -> Bus<BITS> {
+    self.chip("mux_8_way_n", |b| {
+      let low = b.mux_4_way_n(in1, in2, in3, in4, sel1, sel2);
+      let high = b.mux_4_way_n(in5, in6, in7, in8, sel1, sel2);
+      b.mux_n(low, high, sel3)
+    })
+  }
+
+  pub fn dmux_4_way(&mut self, in1: Wire, sel1: Wire, sel2: Wire) -> Bus<4> {
+    self.chip("dmux_4_way", |b| {
+      let mut out = [0; 4];
+      for (i, wire) in out.iter_mut().enumerate() {
+        let s1 = if i & 2 == 0 { b.not(sel1) } else { sel1 };
+        let s2 = if i & 1 == 0 { b.not(sel2) } else { sel2 };
+        *wire = b.and_m_way([in1, s1, s2]);
+      }
+      out
+    })
+  }
+
+  pub fn dmux_8_way(&mut self, in1: Wire, sel1: Wire, sel2: Wire, sel3: Wire) -> Bus<8> {
+    self.chip("dmux_8_way", |b| {
+      let mut out = [0; 8];
+      for (i, wire) in out.iter_mut().enumerate() {
+        let s1 = if i & 4 == 0 { b.not(sel1) } else { sel1 };
+        let s2 = if i & 2 == 0 { b.not(sel2) } else { sel2 };
+        let s3 = if i & 1 == 0 { b.not(sel3) } else { sel3 };
+        *wire = b.and_m_way([in1, s1, s2, s3]);
+      }
+      out
+    })
+  }
+
+  pub fn half_adder(&mut self, in1: Wire, in2: Wire) -> (Wire, Wire) {
+    self.chip("half_adder", |b| (b.xor(in1, in2), b.and(in1, in2)))
+  }
+
+  pub fn full_adder(&mut self, in1: Wire, in2: Wire, carry: Wire) -> (Wire, Wire) {
+    self.chip("full_adder", |b| {
+      let (s1, c1) = b.half_adder(in1, in2);
+      let (s2, c2) = b.half_adder(carry, s1);
+      (s2, b.or(c1, c2))
+    })
+  }
+
+  pub fn n_adder<const BITS: usize>(&mut self, in1: Bus<BITS>, in2: Bus<BITS>) -> Bus<BITS> {
+    self.chip("n_adder", |b| {
+      let mut carry = b.constant(Low);
+      let mut added = [0; BITS];
+      for i in (0..BITS).rev() {
+        let (sum, carry_tmp) = b.full_adder(in1[i], in2[i], carry);
+        carry = carry_tmp;
+        added[i] = sum;
+      }
+      added
+    })
+  }
+
+  pub fn n_adder_carry<const BITS: usize>(&mut self, in1: Bus<BITS>, in2: Bus<BITS>, carry: Wire) -> (Bus<BITS>, Wire) {
+    self.chip("n_adder_carry", |b| {
+      let mut carry = carry;
+      let mut added = [0; BITS];
+      for i in (0..BITS).rev() {
+        let (sum, carry_tmp) = b.full_adder(in1[i], in2[i], carry);
+        carry = carry_tmp;
+        added[i] = sum;
+      }
+      (added, carry)
+    })
+  }
+
+  pub fn multiplier<const BITS: usize>(&mut self, in1: Bus<BITS>, in2: Bus<BITS>) -> (Bus<BITS>, Bus<BITS>) {
+    self.chip("multiplier", |b| {
+      let mut high = [b.constant(Low); BITS];
+      let mut low = [0; BITS];
+      let low_carry = b.constant(Low);
+      for i in (0..BITS).rev() {
+        let row = b.and_n(in1, [in2[i]; BITS]);
+        let (sum, carry) = b.n_adder_carry(high, row, low_carry);
+        low[i] = sum[BITS - 1];
+        high[0] = carry;
+        high[1..].copy_from_slice(&sum[..BITS - 1]);
+      }
+      (high, low)
+    })
+  }
+
+  pub fn restoring_divider<const BITS: usize>(&mut self, in1: Bus<BITS>, in2: Bus<BITS>) -> (Bus<BITS>, Bus<BITS>) {
+    self.chip("restoring_divider", |b| {
+      let mut quotient = [0; BITS];
+      let mut remainder = [b.constant(Low); BITS];
+      let divisor = b.not_n(in2);
+      let high = b.constant(High);
+      for i in 0..BITS {
+        let overflow = remainder[0];
+        let mut shifted = [in1[i]; BITS];
+        shifted[..BITS - 1].copy_from_slice(&remainder[1..]);
+        let (difference, no_borrow) = b.n_adder_carry(shifted, divisor, high);
+        quotient[i] = b.or(overflow, no_borrow);
+        remainder = b.mux_n(shifted, difference, quotient[i]);
+      }
+      (quotient, remainder)
+    })
+  }
+
+  pub fn non_restoring_divider<const BITS: usize>(&mut self, in1: Bus<BITS>, in2: Bus<BITS>) -> (Bus<BITS>, Bus<BITS>) {
+    self.chip("non_restoring_divider", |b| {
+      let mut quotient = [0; BITS];
+      let mut remainder = [b.constant(Low); BITS];
+      let mut sign = b.constant(Low);
+      for i in 0..BITS {
+        let shifted_sign = remainder[0];
+        let mut shifted = [in1[i]; BITS];
+        shifted[..BITS - 1].copy_from_slice(&remainder[1..]);
+        let subtract = b.not(sign);
+        let not_in2 = b.not_n(in2);
+        let operand = b.mux_n(in2, not_in2, subtract);
+        let (sum, carry) = b.n_adder_carry(shifted, operand, subtract);
+        let sign_tmp = b.xor(shifted_sign, subtract);
+        sign = b.xor(sign_tmp, carry);
+        remainder = sum;
+        quotient[i] = b.not(sign);
+      }
+      let corrected = b.n_adder(remainder, in2);
+      (quotient, b.mux_n(remainder, corrected, sign))
+    })
+  }
+
+  fn and_all(&mut self, in1: &[Wire]) -> Wire {
+    let high = self.constant(High);
+    in1.iter().fold(high, |acc, &wire| self.and(acc, wire))
+  }
+
+  fn or_all(&mut self, in1: &[Wire]) -> Wire {
+    let low = self.constant(Low);
+    in1.iter().fold(low, |acc, &wire| self.or(acc, wire))
+  }
+
+  fn lookahead(&mut self, generate: &[Wire], propagate: &[Wire], start: usize, end: usize, carry: Wire) -> Wire {
+    self.chip("lookahead", |b| {
+      let mut terms = vec![];
+      for m in start..end {
+        let mut term = vec![generate[m]];
+        term.extend_from_slice(&propagate[m + 1..end]);
+        terms.push(b.and_all(&term));
+      }
+      let group_generate = b.or_all(&terms);
+      let group_propagate = b.and_all(&propagate[start..end]);
+      let carried = b.and(group_propagate, carry);
+      b.or(group_generate, carried)
+    })
+  }
+
+  pub fn cla_adder<const BITS: usize>(&mut self, in1: Bus<BITS>, in2: Bus<BITS>) -> Bus<BITS> {
+    self.chip("cla_adder", |b| {
+      let mut generate = [0; BITS];
+      let mut propagate = [0; BITS];
+      for j in 0..BITS {
+        generate[j] = b.and(in1[BITS - 1 - j], in2[BITS - 1 - j]);
+        propagate[j] = b.xor(in1[BITS - 1 - j], in2[BITS - 1 - j]);
+      }
+      let mut added = [0; BITS];
+      let mut carry = b.constant(Low);
+      for start in (0..BITS).step_by(GROUP) {
+        let end = (start + GROUP).min(BITS);
+        for j in start..end {
+          let carry_in = b.lookahead(&generate, &propagate, start, j, carry);
+          added[BITS - 1 - j] = b.xor(propagate[j], carry_in);
+        }
+        carry = b.lookahead(&generate, &propagate, start, end, carry);
+      }
+      added
+    })
+  }
+
+  fn ripple<const BITS: usize>(&mut self, in1: Bus<BITS>, in2: Bus<BITS>, start: usize, end: usize, mut carry: Wire) -> (Vec<Wire>, Wire) {
+    let mut added = vec![];
+    for j in start..end {
+      let (sum, carry_tmp) = self.full_adder(in1[BITS - 1 - j], in2[BITS - 1 - j], carry);
+      carry = carry_tmp;
+      added.push(sum);
+    }
+    (added, carry)
+  }
+
+  pub fn carry_select_adder<const BITS: usize>(&mut self, in1: Bus<BITS>, in2: Bus<BITS>) -> Bus<BITS> {
+    self.chip("carry_select_adder", |b| {
+      let mut added = [0; BITS];
+      let (low, high) = (b.constant(Low), b.constant(High));
+      let (first, mut carry) = b.ripple(in1, in2, 0, GROUP.min(BITS), low);
+      for (j, &sum) in first.iter().enumerate() {
+        added[BITS - 1 - j] = sum;
+      }
+      for start in (GROUP..BITS).step_by(GROUP) {
+        let end = (start + GROUP).min(BITS);
+        let (sum0, carry0) = b.ripple(in1, in2, start, end, low);
+        let (sum1, carry1) = b.ripple(in1, in2, start, end, high);
+        for j in start..end {
+          added[BITS - 1 - j] = b.mux(sum0[j - start], sum1[j - start], carry);
+        }
+        carry = b.mux(carry0, carry1, carry);
+      }
+      added
+    })
+  }
+
+  pub fn n_incrementor<const BITS: usize>(&mut self, in1: Bus<BITS>) -> Bus<BITS> {
+    self.chip("n_incrementor", |b| {
+      let mut carry = b.constant(High);
+      let low = b.constant(Low);
+      let mut added = [0; BITS];
+      for i in (0..BITS).rev() {
+        let (sum, carry_tmp) = b.full_adder(in1[i], low, carry);
+        carry = carry_tmp;
+        added[i] = sum;
+      }
+      added
+    })
+  }
+
+  pub fn alu<const BITS: usize>(
+    &mut self,
+    bits1: Bus<BITS>,
+    bits2: Bus<BITS>,
+    zx: Wire,
+    nx: Wire,
+    zy: Wire,
+    ny: Wire,
+    f: Wire,
+    no: Wire
+  ) -> (Bus<BITS>, Wire, Wire) {
+    self.alu_with(Builder::n_adder, bits1, bits2, zx, nx, zy, ny, f, no)
+  }
+
+  pub fn alu_with<const BITS: usize>(
+    &mut self,
+    adder: fn(&mut Builder, Bus<BITS>, Bus<BITS>) -> Bus<BITS>,
+    bits1: Bus<BITS>,
+    bits2: Bus<BITS>,
+    zx: Wire,
+    nx: Wire,
+    zy: Wire,
+    ny: Wire,
+    f: Wire,
+    no: Wire
+  ) -> (Bus<BITS>, Wire, Wire) {
+    self.chip("alu", |b| {
+      let low = [b.constant(Low); BITS];
+      let bits1 = b.mux_n(bits1, low, zx);
+      let not_bits1 = b.not_n(bits1);
+      let bits1 = b.mux_n(bits1, not_bits1, nx);
+      let bits2 = b.mux_n(bits2, low, zy);
+      let not_bits2 = b.not_n(bits2);
+      let bits2 = b.mux_n(bits2, not_bits2, ny);
+      let anded = b.and_n(bits1, bits2);
+      let added = adder(b, bits1, bits2);
+      let out = b.mux_n(anded, added, f);
+      let not_out = b.not_n(out);
+      let out = b.mux_n(out, not_out, no);
+      let any = b.or_m_way(out);
+      let zr = b.not(any);
+      (out, zr, out[0])
+    })
+  }
+}
+
+// Standalone netlists of the gates.rs and alu.rs chips, with the same pin names
+
+pub fn nand() -> Netlist {
+  let mut b = Builder::new("nand");
+  let (in1, in2) = (b.input("in1"), b.input("in2"));
+  let out = b.nand(in1, in2);
+  b.output("out", out);
+  b.build()
+}
+
+pub fn not() -> Netlist {
+  let mut b = Builder::new("not");
+  let in1 = b.input("in1");
+  let out = b.not(in1);
+  b.output("out", out);
+  b.build()
+}
+
+pub fn and() -> Netlist {
+  let mut b = Builder::new("and");
+  let (in1, in2) = (b.input("in1"), b.input("in2"));
+  let out = b.and(in1, in2);
+  b.output("out", out);
+  b.build()
+}
+
+pub fn or() -> Netlist {
+  let mut b = Builder::new("or");
+  let (in1, in2) = (b.input("in1"), b.input("in2"));
+  let out = b.or(in1, in2);
+  b.output("out", out);
+  b.build()
+}
+
+pub fn xor() -> Netlist {
+  let mut b = Builder::new("xor");
+  let (in1, in2) = (b.input("in1"), b.input("in2"));
+  let out = b.xor(in1, in2);
+  b.output("out", out);
+  b.build()
+}
+
+pub fn mux() -> Netlist {
+  let mut b = Builder::new("mux");
+  let (in1, in2, sel) = (b.input("in1"), b.input("in2"), b.input("sel"));
+  let out = b.mux(in1, in2, sel);
+  b.output("out", out);
+  b.build()
+}
+
+pub fn dmux() -> Netlist {
+  let mut b = Builder::new("dmux");
+  let (in1, sel) = (b.input("in1"), b.input("sel"));
+  let (out1, out2) = b.dmux(in1, sel);
+  b.output("out1", out1);
+  b.output("out2", out2);
+  b.build()
+}
+
+pub fn not_n<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("not_n::<{}>", BITS));
+  let in1 = b.input_bus::<BITS>("in1");
+  let out = b.not_n(in1);
+  b.output_bus("out", &out);
+  b.build()
+}
+
+pub fn and_n<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("and_n::<{}>", BITS));
+  let (in1, in2) = (b.input_bus::<BITS>("in1"), b.input_bus::<BITS>("in2"));
+  let out = b.and_n(in1, in2);
+  b.output_bus("out", &out);
+  b.build()
+}
+
+pub fn or_n<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("or_n::<{}>", BITS));
+  let (in1, in2) = (b.input_bus::<BITS>("in1"), b.input_bus::<BITS>("in2"));
+  let out = b.or_n(in1, in2);
+  b.output_bus("out", &out);
+  b.build()
+}
+
+pub fn xor_n<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("xor_n::<{}>", BITS));
+  let (in1, in2) = (b.input_bus::<BITS>("in1"), b.input_bus::<BITS>("in2"));
+  let out = b.xor_n(in1, in2);
+  b.output_bus("out", &out);
+  b.build()
+}
+
+pub fn mux_n<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("mux_n::<{}>", BITS));
+  let (in1, in2) = (b.input_bus::<BITS>("in1"), b.input_bus::<BITS>("in2"));
+  let sel = b.input("sel");
+  let out = b.mux_n(in1, in2, sel);
+  b.output_bus("out", &out);
+  b.build()
+}
+
+pub fn or_m_way<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("or_m_way::<{}>", BITS));
+  let in1 = b.input_bus::<BITS>("in1");
+  let out = b.or_m_way(in1);
+  b.output("out", out);
+  b.build()
+}
+
+pub fn and_m_way<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("and_m_way::<{}>", BITS));
+  let in1 = b.input_bus::<BITS>("in1");
+  let out = b.and_m_way(in1);
+  b.output("out", out);
+  b.build()
+}
+
+pub fn mux_4_way_n<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("mux_4_way_n::<{}>", BITS));
+  let ins = [b.input_bus::<BITS>("in1"), b.input_bus::<BITS>("in2"), b.input_bus::<BITS>("in3"), b.input_bus::<BITS>("in4")];
+  let (sel1, sel2) = (b.input("sel1"), b.input("sel2"));
+  let out = b.mux_4_way_n(ins[0], ins[1], ins[2], ins[3], sel1, sel2);
+  b.output_bus("out", &out);
+  b.build()
+}
+
+pub fn mux_8_way_n<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("mux_8_way_n::<{}>", BITS));
+  let mut ins = [[0; BITS]; 8];
+  for (i, bus) in ins.iter_mut().enumerate() {
+    *bus = b.input_bus::<BITS>(&format!("in{}", i + 1));
+  }
+  let (sel1, sel2, sel3) = (b.input("sel1"), b.input("sel2"), b.input("sel3"));
+  let out = b.mux_8_way_n(ins[0], ins[1], ins[2], ins[3], ins[4], ins[5], ins[6], ins[7], sel1, sel2, sel3);
+  b.output_bus("out", &out);
+  b.build()
+}
+
+pub fn dmux_4_way() -> Netlist {
+  let mut b = Builder::new("dmux_4_way");
+  let (in1, sel1, sel2) = (b.input("in1"), b.input("sel1"), b.input("sel2"));
+  let out = b.dmux_4_way(in1, sel1, sel2);
+  b.output_bus("out", &out);
+  b.build()
+}
+
+pub fn dmux_8_way() -> Netlist {
+  let mut b = Builder::new("dmux_8_way");
+  let (in1, sel1, sel2, sel3) = (b.input("in1"), b.input("sel1"), b.input("sel2"), b.input("sel3"));
+  let out = b.dmux_8_way(in1, sel1, sel2, sel3);
+  b.output_bus("out", &out);
+  b.build()
+}
+
+pub fn half_adder() -> Netlist {
+  let mut b = Builder::new("half_adder");
+  let (in1, in2) = (b.input("in1"), b.input("in2"));
+  let (sum, carry) = b.half_adder(in1, in2);
+  b.output("sum", sum);
+  b.output("carry", carry);
+  b.build()
+}
+
+pub fn full_adder() -> Netlist {
+  let mut b = Builder::new("full_adder");
+  let (in1, in2, carry) = (b.input("in1"), b.input("in2"), b.input("carry"));
+  let (sum, carry) = b.full_adder(in1, in2, carry);
+  b.output("sum", sum);
+  b.output("carry", carry);
+  b.build()
+}
+
+pub fn n_adder<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("n_adder::<{}>", BITS));
+  let (in1, in2) = (b.input_bus::<BITS>("in1"), b.input_bus::<BITS>("in2"));
+  let out = b.n_adder(in1, in2);
+  b.output_bus("out", &out);
+  b.build()
+}
+
+pub fn cla_adder<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("cla_adder::<{}>", BITS));
+  let (in1, in2) = (b.input_bus::<BITS>("in1"), b.input_bus::<BITS>("in2"));
+  let out = b.cla_adder(in1, in2);
+  b.output_bus("out", &out);
+  b.build()
+}
+
+pub fn carry_select_adder<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("carry_select_adder::<{}>", BITS));
+  let (in1, in2) = (b.input_bus::<BITS>("in1"), b.input_bus::<BITS>("in2"));
+  let out = b.carry_select_adder(in1, in2);
+  b.output_bus("out", &out);
+  b.build()
+}
+
+pub fn n_adder_carry<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("n_adder_carry::<{}>", BITS));
+  let (in1, in2) = (b.input_bus::<BITS>("in1"), b.input_bus::<BITS>("in2"));
+  let carry = b.input("carry");
+  let (out, carry) = b.n_adder_carry(in1, in2, carry);
+  b.output_bus("out", &out);
+  b.output("carry", carry);
+  b.build()
+}
+
+pub fn multiplier<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("multiplier::<{}>", BITS));
+  let (in1, in2) = (b.input_bus::<BITS>("in1"), b.input_bus::<BITS>("in2"));
+  let (high, low) = b.multiplier(in1, in2);
+  b.output_bus("high", &high);
+  b.output_bus("low", &low);
+  b.build()
+}
+
+pub fn restoring_divider<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("restoring_divider::<{}>", BITS));
+  let (in1, in2) = (b.input_bus::<BITS>("in1"), b.input_bus::<BITS>("in2"));
+  let (quotient, remainder) = b.restoring_divider(in1, in2);
+  b.output_bus("quotient", &quotient);
+  b.output_bus("remainder", &remainder);
+  b.build()
+}
+
+pub fn non_restoring_divider<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("non_restoring_divider::<{}>", BITS));
+  let (in1, in2) = (b.input_bus::<BITS>("in1"), b.input_bus::<BITS>("in2"));
+  let (quotient, remainder) = b.non_restoring_divider(in1, in2);
+  b.output_bus("quotient", &quotient);
+  b.output_bus("remainder", &remainder);
+  b.build()
+}
+
+pub fn n_incrementor<const BITS: usize>() -> Netlist {
+  let mut b = Builder::new(&format!("n_incrementor::<{}>", BITS));
+  let in1 = b.input_bus::<BITS>("in1");
+  let out = b.n_incrementor(in1);
+  b.output_bus("out", &out);
+  b.build()
+}
+
+pub fn alu<const BITS: usize>() -> Netlist {
+  alu_with::<BITS>("alu", Builder::n_adder)
+}
+
+// The alu built around another adder, named e.g. "alu_cla::<16>"
+pub fn alu_with<const BITS: usize>(name: &str, adder: fn(&mut Builder, Bus<BITS>, Bus<BITS>) -> Bus<BITS>) -> Netlist {
+  let mut b = Builder::new(&format!("{}::<{}>", name, BITS));
+  let (bits1, bits2) = (b.input_bus::<BITS>("bits1"), b.input_bus::<BITS>("bits2"));
+  let (zx, nx, zy, ny, f, no) = (b.input("zx"), b.input("nx"), b.input("zy"), b.input("ny"), b.input("f"), b.input("no"));
+  let (out, zr, ng) = b.alu_with(adder, bits1, bits2, zx, nx, zy, ny, f, no);
+  b.output_bus("out", &out);
+  b.output("zr", zr);
+  b.output("ng", ng);
+  b.build()
+}
+
+// Every chip of the crate with its default bus width, as listed by the command line.
+// There is no HDL loader: netlists are only built in Rust, and the commands taking a chip
+// by name, cost, dot and svg among others, look it up here.
+pub fn library() -> Vec<Netlist> {
+  vec![
+    nand(),
+    not(),
+    and(),
+    or(),
+    xor(),
+    mux(),
+    dmux(),
+    not_n::<16>(),
+    and_n::<16>(),
+    or_n::<16>(),
+    xor_n::<16>(),
+    mux_n::<16>(),
+    or_m_way::<8>(),
+    and_m_way::<8>(),
+    mux_4_way_n::<16>(),
+    mux_8_way_n::<16>(),
+    dmux_4_way(),
+    dmux_8_way(),
+    half_adder(),
+    full_adder(),
+    n_adder::<16>(),
+    n_adder_carry::<16>(),
+    cla_adder::<16>(),
+    carry_select_adder::<16>(),
+    multiplier::<16>(),
+    restoring_divider::<16>(),
+    non_restoring_divider::<16>(),
+    n_incrementor::<16>(),
+    alu::<16>(),
+    alu_with::<16>("alu_cla", Builder::cla_adder),
+    alu_with::<16>("alu_carry_select", Builder::carry_select_adder),
+  ]
+}
+
+pub fn find(name: &str) -> Option<Netlist> {
+  library().into_iter().find(|netlist| netlist.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates;
+    use crate::alu as alu_chips;
+    use crate::arithmetic;
+    use crate::utilities::*;
+    use std::convert::TryInto;
+
+    fn inputs(width: usize, value: usize) -> Vec<Signal> {
+        (0..width).rev().map(|i| if value >> i & 1 == 1 { High } else { Low }).collect()
+    }
+
+    #[test]
+    fn tgates() {
+        for value in 0..4 {
+            let i = inputs(2, value);
+            assert_eq!(not().evaluate(&i[..1]), vec![gates::not(i[0])]);
+            assert_eq!(and().evaluate(&i), vec![gates::and(i[0], i[1])]);
+            assert_eq!(or().evaluate(&i), vec![gates::or(i[0], i[1])]);
+            assert_eq!(xor().evaluate(&i), vec![gates::xor(i[0], i[1])]);
+            let (out1, out2) = gates::dmux(i[0], i[1]);
+            assert_eq!(dmux().evaluate(&i), vec![out1, out2]);
+        }
+        for value in 0..8 {
+            let i = inputs(3, value);
+            assert_eq!(mux().evaluate(&i), vec![gates::mux(i[0], i[1], i[2])]);
+        }
+    }
+    #[test]
+    fn tnand_count() {
+        assert_eq!(not().nand_count(), 1);
+        assert_eq!(and().nand_count(), 3);
+        assert_eq!(or().nand_count(), 3);
+        assert_eq!(xor().nand_count(), 5);
+        assert_eq!(mux().nand_count(), 4);
+        assert_eq!(not_n::<16>().nand_count(), 16);
+    }
+    #[test]
+    fn tbuses() {
+        for value in 0..256 {
+            let i = inputs(8, value);
+            let in1 = [i[0], i[1], i[2], i[3]];
+            let in2 = [i[4], i[5], i[6], i[7]];
+            assert_eq!(not_n::<4>().evaluate(&i[..4]), gates::not_n(in1).to_vec());
+            assert_eq!(and_n::<4>().evaluate(&i), gates::and_n(in1, in2).to_vec());
+            assert_eq!(or_n::<4>().evaluate(&i), gates::or_n(in1, in2).to_vec());
+            assert_eq!(xor_n::<4>().evaluate(&i), gates::xor_n(in1, in2).to_vec());
+            assert_eq!(or_m_way::<8>().evaluate(&i), vec![gates::or_m_way::<8, Signal>(i.clone().try_into().unwrap())]);
+            assert_eq!(and_m_way::<8>().evaluate(&i), vec![gates::and_m_way::<8, Signal>(i.clone().try_into().unwrap())]);
+            assert_eq!(n_adder::<4>().evaluate(&i), alu_chips::n_adder(in1, in2).to_vec());
+            assert_eq!(cla_adder::<4>().evaluate(&i), alu_chips::n_adder(in1, in2).to_vec());
+            assert_eq!(carry_select_adder::<4>().evaluate(&i), alu_chips::n_adder(in1, in2).to_vec());
+            assert_eq!(n_incrementor::<4>().evaluate(&i[4..]), alu_chips::n_incrementor(in2).to_vec());
+        }
+    }
+    #[test]
+    fn tmuxes() {
+        for value in 0..2048 {
+            let i = inputs(11, value);
+            let mut expected = gates::mux_8_way_n([i[0]], [i[1]], [i[2]], [i[3]], [i[4]], [i[5]], [i[6]], [i[7]], i[8], i[9], i[10]).to_vec();
+            assert_eq!(mux_8_way_n::<1>().evaluate(&i), expected);
+            expected = gates::mux_4_way_n([i[0]], [i[1]], [i[2]], [i[3]], i[4], i[5]).to_vec();
+            assert_eq!(mux_4_way_n::<1>().evaluate(&i[..6]), expected);
+            expected = gates::mux_n([i[0], i[1]], [i[2], i[3]], i[4]).to_vec();
+            assert_eq!(mux_n::<2>().evaluate(&i[..5]), expected);
+        }
+        for value in 0..16 {
+            let i = inputs(4, value);
+            assert_eq!(dmux_4_way().evaluate(&i[..3]), gates::dmux_4_way(i[0], i[1], i[2]).to_vec());
+            assert_eq!(dmux_8_way().evaluate(&i), gates::dmux_8_way(i[0], i[1], i[2], i[3]).to_vec());
+        }
+    }
+    #[test]
+    fn tadders() {
+        for value in 0..8 {
+            let i = inputs(3, value);
+            let (sum, carry) = alu_chips::half_adder(i[0], i[1]);
+            assert_eq!(half_adder().evaluate(&i[..2]), vec![sum, carry]);
+            let (sum, carry) = alu_chips::full_adder(i[0], i[1], i[2]);
+            assert_eq!(full_adder().evaluate(&i), vec![sum, carry]);
+        }
+    }
+    #[test]
+    fn talu() {
+        let netlist = alu::<16>();
+        for &(a, b) in [(0, 0), (1, -1), (5, -9), (1234, 4321), (-32768, 32767)].iter() {
+            for control in 0..64 {
+                let c = inputs(6, control);
+                let mut i = int_to_binary16(a).to_vec();
+                i.extend(int_to_binary16(b).iter());
+                i.extend(c.iter());
+                let (out, zr, ng) = alu_chips::alu(int_to_binary16(a), int_to_binary16(b), c[0], c[1], c[2], c[3], c[4], c[5]);
+                let mut expected = out.to_vec();
+                expected.push(zr);
+                expected.push(ng);
+                assert_eq!(netlist.evaluate(&i), expected);
+            }
+        }
+    }
+    #[test]
+    fn twide_adders() {
+        let (cla, carry_select) = (cla_adder::<6>(), carry_select_adder::<6>());
+        for value in 0..4096 {
+            let i = inputs(12, value);
+            let in1 = [i[0], i[1], i[2], i[3], i[4], i[5]];
+            let in2 = [i[6], i[7], i[8], i[9], i[10], i[11]];
+            assert_eq!(cla.evaluate(&i), alu_chips::n_adder(in1, in2).to_vec());
+            assert_eq!(carry_select.evaluate(&i), alu_chips::n_adder(in1, in2).to_vec());
+        }
+    }
+    #[test]
+    fn tarithmetic() {
+        let (mul, restoring, non_restoring) = (multiplier::<4>(), restoring_divider::<4>(), non_restoring_divider::<4>());
+        for value in 0..256 {
+            let i = inputs(8, value);
+            let in1 = [i[0], i[1], i[2], i[3]];
+            let in2 = [i[4], i[5], i[6], i[7]];
+            let (high, low) = arithmetic::multiplier(in1, in2);
+            assert_eq!(mul.evaluate(&i), [high, low].concat());
+            let (quotient, remainder) = arithmetic::restoring_divider(in1, in2);
+            assert_eq!(restoring.evaluate(&i), [quotient, remainder].concat());
+            let (quotient, remainder) = arithmetic::non_restoring_divider(in1, in2);
+            assert_eq!(non_restoring.evaluate(&i), [quotient, remainder].concat());
+        }
+        for value in 0..512 {
+            let i = inputs(9, value);
+            let (out, carry) = alu_chips::n_adder_carry([i[0], i[1], i[2], i[3]], [i[4], i[5], i[6], i[7]], i[8]);
+            let mut expected = out.to_vec();
+            expected.push(carry);
+            assert_eq!(n_adder_carry::<4>().evaluate(&i), expected);
+        }
+    }
+    #[test]
+    fn talu_with() {
+        let netlist = alu_with::<16>("alu_cla", Builder::cla_adder);
+        assert_eq!(netlist.name, "alu_cla::<16>");
+        for &(a, b) in [(0, 0), (1, -1), (5, -9), (1234, 4321), (-32768, 32767)].iter() {
+            for control in 0..64 {
+                let c = inputs(6, control);
+                let mut i = int_to_binary16(a).to_vec();
+                i.extend(int_to_binary16(b).iter());
+                i.extend(c.iter());
+                let (out, zr, ng) = alu_chips::alu(int_to_binary16(a), int_to_binary16(b), c[0], c[1], c[2], c[3], c[4], c[5]);
+                let mut expected = out.to_vec();
+                expected.push(zr);
+                expected.push(ng);
+                assert_eq!(netlist.evaluate(&i), expected);
+            }
+        }
+    }
+    #[test]
+    fn tscopes() {
+        let netlist = alu::<4>();
+        let carry = netlist.nodes.iter().rev().find(|node| netlist.scopes[node.scope].name == "xor").unwrap();
+        assert!(netlist.scope_path(carry.scope).starts_with("alu::<4>/alu/"));
+        assert_eq!(netlist.inputs.len(), 8);
+        assert_eq!(netlist.input_width(), 14);
+        assert_eq!(netlist.output_width(), 6);
+        assert!(find("mux_8_way_n::<16>").is_some());
+        assert!(find("unknown").is_none());
+    }
+    #[test]
+    fn tdisplay() {
+        let text = format!("{}", and());
+        assert!(text.starts_with("CHIP and {\n  IN in1, in2;\n  OUT out;\n"));
+        assert!(text.contains("w4 = nand(w2, w3); // and/and"));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::gates::*;
+use Signal::*;
+
+// A bus of up to 64 signals packed in the bits of a u64, for simulations where the gate level
+// functions are too slow. Bit BITS - 1 of the word is index 0 of the Signals, the msb, so
+// a Word holds the same number as the Signals it converts from. Every function below computes
+// the same outputs as the chip of the same name in gates.rs or alu.rs.
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct Word<const BITS: usize>(u64);
+
+impl<const BITS: usize> Word<BITS> {
+  pub const MASK: u64 = if BITS >= 64 { u64::MAX } else { (1 << BITS) - 1 };
+  const FITS: () = assert!(BITS <= 64, "a Word holds at most 64 bits");
+
+  // Keeps the lowest BITS bits of value
+  pub fn new(value: u64) -> Word<BITS> {
+    let () = Self::FITS;
+    Word(value & Self::MASK)
+  }
+
+  pub fn value(self) -> u64 {
+    self.0
+  }
+
+  // Two's complement value of the word, sign extended from bit BITS - 1
+  pub fn signed(self) -> i64 {
+    match BITS {
+      0 => 0,
+      64 => self.0 as i64,
+      _ => ((self.0 << (64 - BITS)) as i64) >> (64 - BITS),
+    }
+  }
+
+  pub fn bit(self, i: usize) -> Signal {
+    signal(self.0 >> (BITS - 1 - i) & 1 == 1)
+  }
+}
+
+fn signal(bit: bool) -> Signal {
+  if bit { High } else { Low }
+}
+
+fn all(sel: Signal) -> u64 {
+  if sel == High { u64::MAX } else { 0 }
+}
+
+impl<const BITS: usize> From<Signals<BITS>> for Word<BITS> {
+  fn from(signals: Signals<BITS>) -> Word<BITS> {
+    Word::new(signals.iter().fold(0, |acc, &s| acc << 1 | (s == High) as u64))
+  }
+}
+
+impl<const BITS: usize> From<Word<BITS>> for Signals<BITS> {
+  fn from(word: Word<BITS>) -> Signals<BITS> {
+    let mut signals = [Low; BITS];
+    for (i, s) in signals.iter_mut().enumerate() {
+      *s = word.bit(i);
+    }
+    signals
+  }
+}
+
+pub fn not_n<const BITS: usize>(in1: Word<BITS>) -> Word<BITS> {
+  Word::new(!in1.0)
+}
+
+pub fn and_n<const BITS: usize>(in1: Word<BITS>, in2: Word<BITS>) -> Word<BITS> {
+  Word(in1.0 & in2.0)
+}
+
+pub fn or_n<const BITS: usize>(in1: Word<BITS>, in2: Word<BITS>) -> Word<BITS> {
+  Word(in1.0 | in2.0)
+}
+
+pub fn xor_n<const BITS: usize>(in1: Word<BITS>, in2: Word<BITS>) -> Word<BITS> {
+  Word(in1.0 ^ in2.0)
+}
+
+pub fn mux_n<const BITS: usize>(in1: Word<BITS>, in2: Word<BITS>, sel: Signal) -> Word<BITS> {
+  Word(in1.0 & !all(sel) | in2.0 & all(sel))
+}
+
+pub fn or_m_way<const BITS: usize>(in1: Word<BITS>) -> Signal {
+  signal(in1.0 != 0)
+}
+
+pub fn and_m_way<const BITS: usize>(in1: Word<BITS>) -> Signal {
+  signal(in1.0 == Word::<BITS>::MASK)
+}
+
+pub fn mux_4_way_n<const BITS: usize>(in1: Word<BITS>, in2: Word<BITS>, in3: Word<BITS>, in4: Word<BITS>, sel1: Signal, sel2: Signal) -> Word<BITS> {
+  mux_n(mux_n(in1, in2, sel1), mux_n(in3, in4, sel1), sel2)
+}
+
+pub fn mux_8_way_n<const BITS: usize>(
+  in1: Word<BITS>,
+  in2: Word<BITS>,
+  in3: Word<BITS>,
+  in4: Word<BITS>,
+  in5: Word<BITS>,
+  in6: Word<BITS>,
+  in7: Word<BITS>,
+  in8: Word<BITS>,
+  sel1: Signal,
+  sel2: Signal,
+  sel3: Signal
+) -> Word<BITS> {
+  mux_n(mux_4_way_n(in1, in2, in3, in4, sel1, sel2), mux_4_way_n(in5, in6, in7, in8, sel1, sel2), sel3)
+}
+
+pub fn n_adder<const BITS: usize>(in1: Word<BITS>, in2: Word<BITS>) -> Word<BITS> {
+  Word::new(in1.0.wrapping_add(in2.0))
+}
+
+pub fn n_incrementor<const BITS: usize>(in1: Word<BITS>) -> Word<BITS> {
+  Word::new(in1.0.wrapping_add(1))
+}
+
+pub fn alu<const BITS: usize>(
+  bits1: Word<BITS>,
+  bits2: Word<BITS>,
+  zx: Signal,
+  nx: Signal,
+  zy: Signal,
+  ny: Signal,
+  f: Signal,
+  no: Signal
+) -> (Word<BITS>, Signal, Signal) {
+  let bits1 = mux_n(bits1, Word(0), zx);
+  let bits1 = mux_n(bits1, not_n(bits1), nx);
+  let bits2 = mux_n(bits2, Word(0), zy);
+  let bits2 = mux_n(bits2, not_n(bits2), ny);
+  let out = mux_n(and_n(bits1, bits2), n_adder(bits1, bits2), f);
+  let out = mux_n(out, not_n(out), no);
+  (out, signal(out.0 == 0), out.bit(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates;
+    use crate::alu as alu_chips;
+    use crate::utilities::*;
+
+    fn word(value: i16) -> Word<16> {
+        Word::new(value as u16 as u64)
+    }
+
+    #[test]
+    fn tconversions() {
+        assert_eq!(Word::from([High, Low, High]), Word::<3>::new(5));
+        assert_eq!(Signals::from(Word::<3>::new(6)), [High, High, Low]);
+        assert_eq!(Word::<4>::new(0xff).value(), 15);
+        assert_eq!(Word::<4>::new(0b1000).signed(), -8);
+        assert_eq!(Word::<64>::new(u64::MAX).signed(), -1);
+        for &x in [0i16, 1, -1, 12345, -32768, 32767].iter() {
+            assert_eq!(Word::from(int_to_binary16(x)), word(x));
+            assert_eq!(Signals::from(word(x)), int_to_binary16(x));
+            assert_eq!(word(x).signed(), x as i64);
+        }
+    }
+    #[test]
+    fn tgates() {
+        for a in 0..256u64 {
+            for b in (0..256u64).step_by(7) {
+                let (x, y) = (Word::<8>::new(a), Word::<8>::new(b));
+                let (sx, sy): (Signals<8>, Signals<8>) = (x.into(), y.into());
+                assert_eq!(Signals::from(not_n(x)), gates::not_n(sx));
+                assert_eq!(Signals::from(and_n(x, y)), gates::and_n(sx, sy));
+                assert_eq!(Signals::from(or_n(x, y)), gates::or_n(sx, sy));
+                assert_eq!(Signals::from(xor_n(x, y)), gates::xor_n(sx, sy));
+                assert_eq!(Signals::from(mux_n(x, y, Low)), gates::mux_n(sx, sy, Low));
+                assert_eq!(Signals::from(mux_n(x, y, High)), gates::mux_n(sx, sy, High));
+                assert_eq!(Signals::from(n_adder(x, y)), alu_chips::n_adder(sx, sy));
+            }
+            let x = Word::<8>::new(a);
+            let sx: Signals<8> = x.into();
+            assert_eq!(or_m_way(x), gates::or_m_way(sx));
+            assert_eq!(and_m_way(x), gates::and_m_way(sx));
+            assert_eq!(Signals::from(n_incrementor(x)), alu_chips::n_incrementor(sx));
+        }
+    }
+    #[test]
+    fn tmuxes() {
+        let ins: Vec<Word<4>> = (0..8).map(|i| Word::new(i * 3)).collect();
+        for sel in 0..8 {
+            let (s1, s2, s3) = (signal(sel & 1 == 1), signal(sel & 2 == 2), signal(sel & 4 == 4));
+            let expected = gates::mux_8_way_n(ins[0].into(), ins[1].into(), ins[2].into(), ins[3].into(), ins[4].into(), ins[5].into(), ins[6].into(), ins[7].into(), s1, s2, s3);
+            assert_eq!(Signals::from(mux_8_way_n(ins[0], ins[1], ins[2], ins[3], ins[4], ins[5], ins[6], ins[7], s1, s2, s3)), expected);
+            let expected = gates::mux_4_way_n(ins[0].into(), ins[1].into(), ins[2].into(), ins[3].into(), s1, s2);
+            assert_eq!(Signals::from(mux_4_way_n(ins[0], ins[1], ins[2], ins[3], s1, s2)), expected);
+        }
+    }
+    #[test]
+    fn talu() {
+        for &(a, b) in [(0, 0), (1, -1), (5, -9), (1234, 4321), (-32768, 32767)].iter() {
+            for control in 0..64 {
+                let c: Vec<Signal> = (0..6).map(|i| signal(control >> i & 1 == 1)).collect();
+                let (out, zr, ng) = alu(word(a), word(b), c[0], c[1], c[2], c[3], c[4], c[5]);
+                let expected = alu_chips::alu(int_to_binary16(a), int_to_binary16(b), c[0], c[1], c[2], c[3], c[4], c[5]);
+                assert_eq!((Signals::from(out), zr, ng), expected);
+            }
+        }
+    }
+}
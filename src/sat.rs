@@ -0,0 +1,464 @@
+use std::ops::Not;
+
+// A small CDCL sat solver: two watched literals, first uip clause learning with non
+// chronological backjumping, activity based decisions with phase saving and Luby restarts.
+// Learnt clauses are kept forever, which is fine for the size of the problems of the crate.
+
+pub type Var = usize;
+
+// Variable v is the literal 2v, its negation 2v + 1
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub struct Lit(usize);
+
+impl Lit {
+  pub fn positive(var: Var) -> Lit {
+    Lit(2 * var)
+  }
+
+  pub fn negative(var: Var) -> Lit {
+    Lit(2 * var + 1)
+  }
+
+  pub fn var(self) -> Var {
+    self.0 / 2
+  }
+
+  pub fn is_negative(self) -> bool {
+    self.0 & 1 == 1
+  }
+}
+
+impl Not for Lit {
+  type Output = Lit;
+  fn not(self) -> Lit {
+    Lit(self.0 ^ 1)
+  }
+}
+
+// Max heap of the unassigned variables by activity, with the position of every variable
+#[derive(Debug, Default)]
+struct Heap {
+    heap: Vec<Var>,
+    index: Vec<Option<usize>>,
+}
+
+impl Heap {
+  fn contains(&self, var: Var) -> bool {
+    self.index[var].is_some()
+  }
+
+  fn swap(&mut self, i: usize, j: usize) {
+    self.heap.swap(i, j);
+    self.index[self.heap[i]] = Some(i);
+    self.index[self.heap[j]] = Some(j);
+  }
+
+  fn up(&mut self, mut i: usize, activity: &[f64]) {
+    while i > 0 && activity[self.heap[(i - 1) / 2]] < activity[self.heap[i]] {
+      self.swap(i, (i - 1) / 2);
+      i = (i - 1) / 2;
+    }
+  }
+
+  fn down(&mut self, mut i: usize, activity: &[f64]) {
+    loop {
+      let mut largest = i;
+      for child in [2 * i + 1, 2 * i + 2].iter() {
+        if *child < self.heap.len() && activity[self.heap[*child]] > activity[self.heap[largest]] {
+          largest = *child;
+        }
+      }
+      if largest == i {
+        return;
+      }
+      self.swap(i, largest);
+      i = largest;
+    }
+  }
+
+  fn insert(&mut self, var: Var, activity: &[f64]) {
+    if self.index.len() <= var {
+      self.index.resize(var + 1, None);
+    }
+    if !self.contains(var) {
+      self.heap.push(var);
+      self.index[var] = Some(self.heap.len() - 1);
+      self.up(self.heap.len() - 1, activity);
+    }
+  }
+
+  fn pop(&mut self, activity: &[f64]) -> Option<Var> {
+    let top = *self.heap.first()?;
+    let last = self.heap.len() - 1;
+    self.swap(0, last);
+    self.heap.pop();
+    self.index[top] = None;
+    self.down(0, activity);
+    Some(top)
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    // Clauses watching each literal, visited when the literal becomes false
+    watches: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    phase: Vec<bool>,
+    activity: Vec<f64>,
+    increment: f64,
+    heap: Heap,
+    trail: Vec<Lit>,
+    trail_limits: Vec<usize>,
+    propagated: usize,
+    unsatisfiable: bool,
+    pub conflicts: u64,
+}
+
+impl Solver {
+  pub fn new() -> Solver {
+    Solver { increment: 1.0, ..Solver::default() }
+  }
+
+  pub fn new_var(&mut self) -> Var {
+    let var = self.values.len();
+    self.values.push(None);
+    self.level.push(0);
+    self.reason.push(None);
+    self.phase.push(false);
+    self.activity.push(0.0);
+    self.watches.push(vec![]);
+    self.watches.push(vec![]);
+    self.heap.insert(var, &self.activity);
+    var
+  }
+
+  pub fn var_count(&self) -> usize {
+    self.values.len()
+  }
+
+  pub fn value(&self, lit: Lit) -> Option<bool> {
+    self.values[lit.var()].map(|value| value != lit.is_negative())
+  }
+
+  fn decision_level(&self) -> usize {
+    self.trail_limits.len()
+  }
+
+  fn assign(&mut self, lit: Lit, reason: Option<usize>) {
+    let var = lit.var();
+    self.values[var] = Some(!lit.is_negative());
+    self.level[var] = self.decision_level();
+    self.reason[var] = reason;
+    self.trail.push(lit);
+  }
+
+  // Adds a clause before solving, returns false if the problem became trivially unsatisfiable
+  pub fn add_clause(&mut self, lits: &[Lit]) -> bool {
+    assert_eq!(self.decision_level(), 0, "clauses are added before solving");
+    let mut clause: Vec<Lit> = vec![];
+    for &lit in lits.iter() {
+      match self.value(lit) {
+          Some(true) => return true,
+          Some(false) => (),
+          None if clause.contains(&!lit) => return true,
+          None if !clause.contains(&lit) => clause.push(lit),
+          None => (),
+      }
+    }
+    match clause.len() {
+        0 => self.unsatisfiable = true,
+        1 => {
+          self.assign(clause[0], None);
+          self.unsatisfiable |= self.propagate().is_some();
+        },
+        _ => {
+          self.watch(clause);
+        },
+    }
+    !self.unsatisfiable
+  }
+
+  fn watch(&mut self, clause: Vec<Lit>) -> usize {
+    let index = self.clauses.len();
+    self.watches[clause[0].0].push(index);
+    self.watches[clause[1].0].push(index);
+    self.clauses.push(clause);
+    index
+  }
+
+  // Unit propagation over the trail, returns a conflicting clause if any
+  fn propagate(&mut self) -> Option<usize> {
+    while self.propagated < self.trail.len() {
+      let falsified = !self.trail[self.propagated];
+      self.propagated += 1;
+      let watching = std::mem::take(&mut self.watches[falsified.0]);
+      let mut kept = Vec::with_capacity(watching.len());
+      let mut conflict = None;
+      for (k, &index) in watching.iter().enumerate() {
+        if conflict.is_some() {
+          kept.extend_from_slice(&watching[k..]);
+          break;
+        }
+        let clause = &mut self.clauses[index];
+        if clause[0] == falsified {
+          clause.swap(0, 1);
+        }
+        let other = clause[0];
+        if self.values[other.var()].map(|value| value != other.is_negative()) == Some(true) {
+          kept.push(index);
+          continue;
+        }
+        let values = &self.values;
+        let replacement = (2..clause.len()).find(|&i| values[clause[i].var()].map(|value| value != clause[i].is_negative()) != Some(false));
+        match replacement {
+            Some(i) => {
+              clause.swap(1, i);
+              let lit = clause[1];
+              self.watches[lit.0].push(index);
+            },
+            None => {
+              kept.push(index);
+              match self.value(other) {
+                  Some(false) => conflict = Some(index),
+                  _ => self.assign(other, Some(index)),
+              }
+            },
+        }
+      }
+      self.watches[falsified.0] = kept;
+      if conflict.is_some() {
+        return conflict;
+      }
+    }
+    None
+  }
+
+  fn bump(&mut self, var: Var) {
+    self.activity[var] += self.increment;
+    if self.activity[var] > 1e100 {
+      self.activity.iter_mut().for_each(|a| *a *= 1e-100);
+      self.increment *= 1e-100;
+    }
+    if let Some(i) = self.heap.index.get(var).copied().flatten() {
+      self.heap.up(i, &self.activity);
+    }
+  }
+
+  // First uip learnt clause, asserting literal first, and the level to backjump to
+  fn analyze(&mut self, mut conflict: usize) -> (Vec<Lit>, usize) {
+    let mut seen = vec![false; self.var_count()];
+    let mut learnt = vec![Lit(0)];
+    let mut pending = 0;
+    let mut index = self.trail.len();
+    let mut asserting = None;
+    loop {
+      let start = if asserting.is_some() { 1 } else { 0 };
+      for k in start..self.clauses[conflict].len() {
+        let lit = self.clauses[conflict][k];
+        let var = lit.var();
+        if !seen[var] && self.level[var] > 0 {
+          seen[var] = true;
+          self.bump(var);
+          if self.level[var] == self.decision_level() {
+            pending += 1;
+          } else {
+            learnt.push(lit);
+          }
+        }
+      }
+      loop {
+        index -= 1;
+        if seen[self.trail[index].var()] {
+          break;
+        }
+      }
+      let lit = self.trail[index];
+      pending -= 1;
+      if pending == 0 {
+        learnt[0] = !lit;
+        break;
+      }
+      asserting = Some(lit);
+      conflict = self.reason[lit.var()].unwrap();
+      // the implied literal is the first of its reason clause once propagated
+      let position = self.clauses[conflict].iter().position(|&l| l == lit).unwrap();
+      self.clauses[conflict].swap(0, position);
+    }
+    self.increment /= 0.95;
+    let backjump = match learnt.len() {
+        1 => 0,
+        _ => {
+          let deepest = (1..learnt.len()).max_by_key(|&i| self.level[learnt[i].var()]).unwrap();
+          learnt.swap(1, deepest);
+          self.level[learnt[1].var()]
+        },
+    };
+    (learnt, backjump)
+  }
+
+  fn backjump(&mut self, level: usize) {
+    if self.decision_level() <= level {
+      return;
+    }
+    let limit = self.trail_limits[level];
+    for &lit in self.trail[limit..].iter() {
+      let var = lit.var();
+      self.phase[var] = !lit.is_negative();
+      self.values[var] = None;
+      self.reason[var] = None;
+      self.heap.insert(var, &self.activity);
+    }
+    self.trail.truncate(limit);
+    self.trail_limits.truncate(level);
+    self.propagated = limit;
+  }
+
+  fn decide(&mut self) -> Option<Lit> {
+    while let Some(var) = self.heap.pop(&self.activity) {
+      if self.values[var].is_none() {
+        return Some(if self.phase[var] { Lit::positive(var) } else { Lit::negative(var) });
+      }
+    }
+    None
+  }
+
+  // Model of every variable if the clauses are satisfiable, None if they are not
+  pub fn solve(&mut self) -> Option<Vec<bool>> {
+    if self.unsatisfiable {
+      return None;
+    }
+    let mut restart = 1;
+    let mut budget = luby(restart) * 100;
+    loop {
+      match self.propagate() {
+          Some(conflict) => {
+            self.conflicts += 1;
+            if self.decision_level() == 0 {
+              self.unsatisfiable = true;
+              return None;
+            }
+            let (learnt, level) = self.analyze(conflict);
+            self.backjump(level);
+            match learnt.len() {
+                1 => self.assign(learnt[0], None),
+                _ => {
+                  let asserting = learnt[0];
+                  let index = self.watch(learnt);
+                  self.assign(asserting, Some(index));
+                },
+            }
+            budget = budget.saturating_sub(1);
+          },
+          None if budget == 0 => {
+            restart += 1;
+            budget = luby(restart) * 100;
+            self.backjump(0);
+          },
+          None => match self.decide() {
+              Some(lit) => {
+                self.trail_limits.push(self.trail.len());
+                self.assign(lit, None);
+              },
+              None => {
+                let model = self.values.iter().map(|value| value.unwrap()).collect();
+                self.backjump(0);
+                return Some(model);
+              },
+          },
+      }
+    }
+  }
+}
+
+// 1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8...
+fn luby(mut i: u64) -> u64 {
+  loop {
+    let mut k = 1;
+    while (1 << k) - 1 < i {
+      k += 1;
+    }
+    if (1 << k) - 1 == i {
+      return 1 << (k - 1);
+    }
+    i -= (1 << (k - 1)) - 1;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn satisfies(clauses: &[Vec<Lit>], model: &[bool]) -> bool {
+        clauses.iter().all(|clause| clause.iter().any(|&lit| model[lit.var()] != lit.is_negative()))
+    }
+
+    #[test]
+    fn tluby() {
+        assert_eq!((1..16).map(luby).collect::<Vec<u64>>(), vec![1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+    }
+    #[test]
+    fn tsatisfiable() {
+        let mut solver = Solver::new();
+        let (a, b, c) = (solver.new_var(), solver.new_var(), solver.new_var());
+        let clauses = vec![
+            vec![Lit::positive(a), Lit::positive(b)],
+            vec![Lit::negative(a), Lit::positive(c)],
+            vec![Lit::negative(b), Lit::negative(c)],
+            vec![Lit::positive(c), Lit::positive(b)],
+        ];
+        for clause in clauses.iter() {
+            assert!(solver.add_clause(clause));
+        }
+        let model = solver.solve().unwrap();
+        assert!(satisfies(&clauses, &model));
+        assert!(solver.add_clause(&[Lit::positive(a), Lit::negative(a)]));
+        assert!(!solver.add_clause(&[]));
+    }
+    #[test]
+    fn tpigeonhole() {
+        // 5 pigeons in 4 holes
+        let mut solver = Solver::new();
+        let vars: Vec<Vec<Var>> = (0..5).map(|_| (0..4).map(|_| solver.new_var()).collect()).collect();
+        for pigeon in vars.iter() {
+            solver.add_clause(&pigeon.iter().map(|&v| Lit::positive(v)).collect::<Vec<Lit>>());
+        }
+        for (p1, pigeon1) in vars.iter().enumerate() {
+            for pigeon2 in vars[p1 + 1..].iter() {
+                for (&v1, &v2) in pigeon1.iter().zip(pigeon2.iter()) {
+                    solver.add_clause(&[Lit::negative(v1), Lit::negative(v2)]);
+                }
+            }
+        }
+        assert_eq!(solver.solve(), None);
+        assert!(solver.conflicts > 0);
+    }
+    #[test]
+    fn trandom() {
+        // random 3-sat around the threshold, checked against brute force
+        let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut random = |n: u64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed % n
+        };
+        for _ in 0..50 {
+            let clauses: Vec<Vec<Lit>> = (0..43).map(|_| (0..3).map(|_| {
+                let var = random(10) as usize;
+                if random(2) == 0 { Lit::positive(var) } else { Lit::negative(var) }
+            }).collect()).collect();
+            let mut solver = Solver::new();
+            (0..10).for_each(|_| { solver.new_var(); });
+            let consistent = clauses.iter().all(|clause| solver.add_clause(clause));
+            let brute = (0..1u32 << 10).any(|m| satisfies(&clauses, &(0..10).map(|v| m >> v & 1 == 1).collect::<Vec<bool>>()));
+            let result = if consistent { solver.solve() } else { None };
+            assert_eq!(result.is_some(), brute);
+            if let Some(model) = result {
+                assert!(satisfies(&clauses, &model));
+            }
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use crate::gates::Signal;
+use crate::netlist::{Gate, Netlist, Wire};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// Schematics of netlists, as Graphviz DOT or as a standalone SVG. Scopes above `depth` are
+// drawn as clusters and the subchips at that depth as single blocks: depth 2 of a library
+// chip shows the subchips inside its top scope, e.g. the mux_n and n_adder of the alu, and
+// None flattens everything to nands. Edges carrying several wires are labeled with the bus width.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Kind {
+    Input,
+    Output,
+    Constant,
+    Nand,
+    Block,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Item {
+    kind: Kind,
+    label: String,
+    // Innermost cluster holding the item
+    scope: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Graph {
+    items: Vec<Item>,
+    // From, to and number of wires, in order of first connection
+    edges: Vec<(usize, usize, usize)>,
+}
+
+fn depth_of(netlist: &Netlist, scope: usize) -> usize {
+  match netlist.scopes[scope].parent {
+      Some(parent) => 1 + depth_of(netlist, parent),
+      None => 0,
+  }
+}
+
+// Ancestor of the scope `levels` levels up
+fn ancestor(netlist: &Netlist, mut scope: usize, levels: usize) -> usize {
+  for _ in 0..levels {
+    scope = netlist.scopes[scope].parent.unwrap();
+  }
+  scope
+}
+
+fn port_label(name: &str, width: usize) -> String {
+  match width {
+      1 => name.to_string(),
+      width => format!("{}[{}]", name, width),
+  }
+}
+
+fn graph(netlist: &Netlist, depth: Option<usize>) -> Graph {
+  let mut items = vec![];
+  let mut owner: Vec<usize> = vec![0; netlist.nodes.len()];
+  for port in netlist.inputs.iter() {
+    items.push(Item { kind: Kind::Input, label: port_label(&port.name, port.wires.len()), scope: 0 });
+    port.wires.iter().for_each(|&wire| owner[wire] = items.len() - 1);
+  }
+  let mut blocks: HashMap<usize, usize> = HashMap::new();
+  let mut wires: HashMap<(usize, usize), Vec<Wire>> = HashMap::new();
+  let mut edges = vec![];
+  let mut connect = |wires: &mut HashMap<(usize, usize), Vec<Wire>>, from: usize, to: usize, wire: Wire| {
+    if from == to {
+      return;
+    }
+    let carried = wires.entry((from, to)).or_insert_with(|| {
+      edges.push((from, to));
+      vec![]
+    });
+    if !carried.contains(&wire) {
+      carried.push(wire);
+    }
+  };
+  for (wire, node) in netlist.nodes.iter().enumerate() {
+    let scope_depth = depth_of(netlist, node.scope);
+    let item = match (node.gate, depth) {
+        (Gate::Input, _) => continue,
+        (_, Some(depth)) if scope_depth >= depth => {
+          let block = ancestor(netlist, node.scope, scope_depth - depth);
+          *blocks.entry(block).or_insert_with(|| {
+            let parent = netlist.scopes[block].parent.unwrap_or(0);
+            items.push(Item { kind: Kind::Block, label: netlist.scopes[block].name.clone(), scope: parent });
+            items.len() - 1
+          })
+        },
+        (Gate::Constant(signal), _) => {
+          items.push(Item { kind: Kind::Constant, label: if signal == Signal::High { "1" } else { "0" }.to_string(), scope: node.scope });
+          items.len() - 1
+        },
+        (Gate::Nand(_, _), _) => {
+          items.push(Item { kind: Kind::Nand, label: "nand".to_string(), scope: node.scope });
+          items.len() - 1
+        },
+    };
+    owner[wire] = item;
+    if let Gate::Nand(in1, in2) = node.gate {
+      connect(&mut wires, owner[in1], item, in1);
+      connect(&mut wires, owner[in2], item, in2);
+    }
+  }
+  for port in netlist.outputs.iter() {
+    items.push(Item { kind: Kind::Output, label: port_label(&port.name, port.wires.len()), scope: 0 });
+    for &wire in port.wires.iter() {
+      connect(&mut wires, owner[wire], items.len() - 1, wire);
+    }
+  }
+  let edges = edges.into_iter().map(|(from, to)| (from, to, wires[&(from, to)].len())).collect();
+  Graph { items, edges }
+}
+
+fn quote(text: &str) -> String {
+  format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+pub fn dot(netlist: &Netlist, depth: Option<usize>) -> String {
+  let graph = graph(netlist, depth);
+  let mut out = String::new();
+  writeln!(out, "digraph {} {{", quote(&netlist.name)).unwrap();
+  writeln!(out, "  rankdir=LR;\n  node [fontname=\"Helvetica\", fontsize=10];\n  edge [arrowsize=0.6];").unwrap();
+  // clusters nest like the scopes, the chip itself being the top level graph
+  let mut children: Vec<Vec<usize>> = vec![vec![]; netlist.scopes.len()];
+  for (scope, s) in netlist.scopes.iter().enumerate() {
+    if let Some(parent) = s.parent {
+      children[parent].push(scope);
+    }
+  }
+  let mut used = vec![false; netlist.scopes.len()];
+  for item in graph.items.iter() {
+    let mut scope = Some(item.scope);
+    while let Some(s) = scope {
+      used[s] = true;
+      scope = netlist.scopes[s].parent;
+    }
+  }
+  fn cluster(out: &mut String, netlist: &Netlist, graph: &Graph, children: &[Vec<usize>], used: &[bool], scope: usize, indent: usize) {
+    let pad = " ".repeat(indent);
+    for (i, item) in graph.items.iter().enumerate().filter(|(_, item)| item.scope == scope) {
+      let attributes = match item.kind {
+          Kind::Input | Kind::Output => "shape=cds, style=filled, fillcolor=\"#e8e8e8\"",
+          Kind::Constant => "shape=plaintext",
+          Kind::Nand => "shape=box, height=0.3",
+          Kind::Block => "shape=box3d",
+      };
+      writeln!(out, "{}n{} [label={}, {}];", pad, i, quote(&item.label), attributes).unwrap();
+    }
+    for &child in children[scope].iter().filter(|&&child| used[child]) {
+      writeln!(out, "{}subgraph cluster_{} {{", pad, child).unwrap();
+      writeln!(out, "{}  label={};", pad, quote(&netlist.scopes[child].name)).unwrap();
+      cluster(out, netlist, graph, children, used, child, indent + 2);
+      writeln!(out, "{}}}", pad).unwrap();
+    }
+  }
+  cluster(&mut out, netlist, &graph, &children, &used, 0, 2);
+  for &(from, to, width) in graph.edges.iter() {
+    match width {
+        1 => writeln!(out, "  n{} -> n{};", from, to).unwrap(),
+        width => writeln!(out, "  n{} -> n{} [label=\"{}\", penwidth=2];", from, to, width).unwrap(),
+    }
+  }
+  out.push_str("}\n");
+  out
+}
+
+const COLUMN: usize = 150;
+const ROW: usize = 44;
+const BOX: (usize, usize) = (110, 28);
+const MARGIN: usize = 20;
+
+// A layered drawing needing no Graphviz: items in columns by their distance from the
+// inputs, outputs in the last column. Clusters are left out, blocks carry the subchip names.
+pub fn svg(netlist: &Netlist, depth: Option<usize>) -> String {
+  let graph = graph(netlist, depth);
+  // items are created in topological order, inputs before their readers
+  let mut column = vec![0; graph.items.len()];
+  let mut edges = graph.edges.clone();
+  edges.sort_by_key(|&(_, to, _)| to);
+  for &(from, to, _) in edges.iter() {
+    column[to] = column[to].max(column[from] + 1);
+  }
+  let last = column.iter().copied().max().unwrap_or(0);
+  for (i, item) in graph.items.iter().enumerate() {
+    if item.kind == Kind::Output {
+      column[i] = last;
+    }
+  }
+  let mut rows = vec![0; last + 1];
+  let mut position = vec![(0, 0); graph.items.len()];
+  for (i, &c) in column.iter().enumerate() {
+    position[i] = (MARGIN + c * COLUMN, MARGIN + rows[c] * ROW);
+    rows[c] += 1;
+  }
+  let width = 2 * MARGIN + last * COLUMN + BOX.0;
+  let height = 2 * MARGIN + rows.iter().copied().max().unwrap_or(1) * ROW;
+  let mut out = String::new();
+  writeln!(out, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"Helvetica\" font-size=\"11\">", width, height).unwrap();
+  writeln!(out, "<title>{}</title>", escape(&netlist.name)).unwrap();
+  for &(from, to, wires) in graph.edges.iter() {
+    let (x1, y1) = (position[from].0 + BOX.0, position[from].1 + BOX.1 / 2);
+    let (x2, y2) = (position[to].0, position[to].1 + BOX.1 / 2);
+    let stroke = if wires > 1 { 2 } else { 1 };
+    writeln!(out, "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\" stroke-width=\"{}\"/>", x1, y1, x2, y2, stroke).unwrap();
+    if wires > 1 {
+      writeln!(out, "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>", (x1 + x2) / 2, (y1 + y2) / 2 - 3, wires).unwrap();
+    }
+  }
+  for (item, &(x, y)) in graph.items.iter().zip(position.iter()) {
+    let fill = match item.kind {
+        Kind::Input | Kind::Output => "#e8e8e8",
+        Kind::Block => "#dde8f4",
+        Kind::Nand | Kind::Constant => "white",
+    };
+    writeln!(out, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"4\" fill=\"{}\" stroke=\"black\"/>", x, y, BOX.0, BOX.1, fill).unwrap();
+    writeln!(out, "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>", x + BOX.0 / 2, y + BOX.1 / 2 + 4, escape(&item.label)).unwrap();
+  }
+  out.push_str("</svg>\n");
+  out
+}
+
+fn escape(text: &str) -> String {
+  text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netlist;
+
+    #[test]
+    fn tflat() {
+        let and = dot(&netlist::and(), None);
+        assert_eq!(and, "digraph \"and\" {\n  rankdir=LR;\n  node [fontname=\"Helvetica\", fontsize=10];\n  edge [arrowsize=0.6];\n\
+            \x20 n0 [label=\"in1\", shape=cds, style=filled, fillcolor=\"#e8e8e8\"];\n\
+            \x20 n1 [label=\"in2\", shape=cds, style=filled, fillcolor=\"#e8e8e8\"];\n\
+            \x20 n5 [label=\"out\", shape=cds, style=filled, fillcolor=\"#e8e8e8\"];\n\
+            \x20 subgraph cluster_1 {\n    label=\"and\";\n    n2 [label=\"nand\", shape=box, height=0.3];\n\
+            \x20   n3 [label=\"nand\", shape=box, height=0.3];\n    n4 [label=\"nand\", shape=box, height=0.3];\n  }\n\
+            \x20 n0 -> n2;\n  n1 -> n2;\n  n0 -> n3;\n  n1 -> n3;\n  n2 -> n4;\n  n3 -> n4;\n  n4 -> n5;\n}\n");
+        // nested clusters, down to the nands of the xor of a half adder
+        let full_adder = dot(&netlist::full_adder(), None);
+        assert!(full_adder.contains("  subgraph cluster_1 {\n    label=\"full_adder\";\n    subgraph cluster_2 {\n      label=\"half_adder\";\n"));
+        assert!(full_adder.contains("        label=\"xor\";\n        n"));
+    }
+    #[test]
+    fn tblocks() {
+        // the alu as a cluster of its subchips, buses labeled with their width
+        let alu = netlist::alu::<16>();
+        let blocks = dot(&alu, Some(2));
+        assert!(blocks.contains("subgraph cluster_1 {\n    label=\"alu\";\n"));
+        assert!(blocks.contains("[label=\"n_adder\", shape=box3d];"));
+        assert!(blocks.contains("[label=\"bits1[16]\", shape=cds"));
+        assert!(blocks.contains("[label=\"16\", penwidth=2];"));
+        assert!(!blocks.contains("nand"));
+        // 6 mux_n, 3 not_n, and_n, n_adder, or_m_way and the not of zr
+        assert_eq!(blocks.matches("shape=box3d").count(), 13);
+        // everything in one block, the whole alu
+        let top = dot(&alu, Some(1));
+        assert_eq!(top.matches("shape=box3d").count(), 1);
+        assert!(top.contains("n9 -> n10 [label=\"16\", penwidth=2];"));
+        assert_eq!(top.matches(" -> ").count(), 8 + 1 + 3);
+        assert_eq!(dot(&alu, None).matches("label=\"nand\"").count(), alu.nand_count());
+    }
+    #[test]
+    fn tsvg() {
+        let svg = svg(&netlist::alu::<16>(), Some(2));
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.contains("<title>alu::&lt;16&gt;</title>"));
+        assert!(svg.contains(">n_adder</text>"));
+        assert_eq!(svg.matches("<rect").count(), 8 + 1 + 13 + 3);
+        assert!(svg.ends_with("</svg>\n"));
+        let flat = super::svg(&netlist::xor(), None);
+        assert_eq!(flat.matches("<rect").count(), 2 + 5 + 1);
+        assert_eq!(flat.matches("<line").count(), 11);
+    }
+}
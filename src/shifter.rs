@@ -0,0 +1,115 @@
+use crate::gates::*;
+
+// Logarithmic barrel shifters: one row of muxes per bit of the amount bus, the row for
+// amount bit k shifting by 2^k. The amount is msb first like every other bus of the crate,
+// shifting by BITS or more empties the word (or rotates modulo BITS).
+
+fn barrel<const BITS: usize, const SEL: usize, S: Logic>(
+  in1: Signals<BITS, S>,
+  amount: Signals<SEL, S>,
+  pick: impl Fn(&Signals<BITS, S>, usize, usize) -> S
+) -> Signals<BITS, S> {
+  let mut out = in1;
+  let mut weight = 1;
+  for &bit in amount.iter().rev() {
+    let mut shifted = [S::LOW; BITS];
+    for (i, signal) in shifted.iter_mut().enumerate() {
+      *signal = pick(&out, i, weight);
+    }
+    out = mux_n(out, shifted, bit);
+    weight = (weight * 2).min(2 * BITS);
+  }
+  out
+}
+
+pub fn shift_left<const BITS: usize, const SEL: usize, S: Logic>(in1: Signals<BITS, S>, amount: Signals<SEL, S>) -> Signals<BITS, S> {
+  barrel(in1, amount, |bits, i, weight| if i + weight < BITS { bits[i + weight] } else { S::LOW })
+}
+
+pub fn shift_right<const BITS: usize, const SEL: usize, S: Logic>(in1: Signals<BITS, S>, amount: Signals<SEL, S>) -> Signals<BITS, S> {
+  barrel(in1, amount, |bits, i, weight| if i >= weight { bits[i - weight] } else { S::LOW })
+}
+
+// Shifts the sign bit in from the left
+pub fn shift_right_arithmetic<const BITS: usize, const SEL: usize, S: Logic>(in1: Signals<BITS, S>, amount: Signals<SEL, S>) -> Signals<BITS, S> {
+  barrel(in1, amount, |bits, i, weight| if i >= weight { bits[i - weight] } else { bits[0] })
+}
+
+// Rotations compute their weights modulo BITS so any amount wraps around correctly
+fn rotate<const BITS: usize, const SEL: usize, S: Logic>(in1: Signals<BITS, S>, amount: Signals<SEL, S>, left: bool) -> Signals<BITS, S> {
+  let mut out = in1;
+  let mut weight = 1 % BITS.max(1);
+  for &bit in amount.iter().rev() {
+    let mut rotated = [S::LOW; BITS];
+    for (i, signal) in rotated.iter_mut().enumerate() {
+      *signal = if left { out[(i + weight) % BITS] } else { out[(i + BITS - weight) % BITS] };
+    }
+    out = mux_n(out, rotated, bit);
+    weight = weight * 2 % BITS;
+  }
+  out
+}
+
+pub fn rotate_left<const BITS: usize, const SEL: usize, S: Logic>(in1: Signals<BITS, S>, amount: Signals<SEL, S>) -> Signals<BITS, S> {
+  rotate(in1, amount, true)
+}
+
+pub fn rotate_right<const BITS: usize, const SEL: usize, S: Logic>(in1: Signals<BITS, S>, amount: Signals<SEL, S>) -> Signals<BITS, S> {
+  rotate(in1, amount, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Signal::*;
+    use crate::utilities::*;
+    use std::convert::TryInto;
+
+    fn amount(value: i16) -> Signals<4> {
+        int_to_binary16(value)[12..].try_into().unwrap()
+    }
+
+    #[test]
+    fn tshift_left() {
+        assert_eq!(shift_left([High, Low, High, High], [Low, High]), [Low, High, High, Low]);
+        for &x in [0i16, 1, -1, 12345, -32768, 0x5a5a].iter() {
+            for n in 0..16 {
+                assert_eq!(shift_left(int_to_binary16(x), amount(n)), int_to_binary16(x << n));
+            }
+        }
+    }
+    #[test]
+    fn tshift_right() {
+        assert_eq!(shift_right([High, Low, High, High], [Low, High]), [Low, High, Low, High]);
+        for &x in [0i16, 1, -1, 12345, -32768, 0x5a5a].iter() {
+            for n in 0..16 {
+                assert_eq!(shift_right(int_to_binary16(x), amount(n)), int_to_binary16(((x as u16) >> n) as i16));
+            }
+        }
+    }
+    #[test]
+    fn tshift_right_arithmetic() {
+        assert_eq!(shift_right_arithmetic([High, Low, High, High], [Low, High]), [High, High, Low, High]);
+        for &x in [0i16, 1, -1, 12345, -32768, -12345].iter() {
+            for n in 0..16 {
+                assert_eq!(shift_right_arithmetic(int_to_binary16(x), amount(n)), int_to_binary16(x >> n));
+            }
+        }
+    }
+    #[test]
+    fn trotate() {
+        assert_eq!(rotate_left([High, Low, High, High], [High, High]), [High, High, Low, High]);
+        assert_eq!(rotate_right([High, Low, Low], [High, Low, Low]), [Low, High, Low]);
+        for &x in [0i16, 1, -1, 12345, -32768, 0x5a5a].iter() {
+            for n in 0..16 {
+                assert_eq!(rotate_left(int_to_binary16(x), amount(n)), int_to_binary16((x as u16).rotate_left(n as u32) as i16));
+                assert_eq!(rotate_right(int_to_binary16(x), amount(n)), int_to_binary16((x as u16).rotate_right(n as u32) as i16));
+            }
+        }
+    }
+    #[test]
+    fn toversized_amount() {
+        assert_eq!(shift_left([High, High, High], [High, Low, Low]), [Low, Low, Low]);
+        assert_eq!(shift_right_arithmetic([High, Low, Low], [High, High, High]), [High, High, High]);
+    }
+}
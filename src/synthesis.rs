@@ -0,0 +1,378 @@
+use crate::gates::*;
+use crate::netlist::{Builder, Netlist, Wire};
+use crate::truth_table::TruthTable;
+use Signal::*;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+// Two level synthesis: every output is minimized into a sum of products with Quine-McCluskey,
+// then built as nand(nand(literals), ...), the nand-nand form of an or of ands. Rows and
+// minterms are numbered like truth table rows, the first input being the msb.
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Function {
+    pub inputs: Vec<String>,
+    // Name, minterms and don't care rows of every output
+    pub outputs: Vec<(String, Vec<u64>, Vec<u64>)>,
+}
+
+// A product of literals: the inputs whose bit is set in mask are absent from the product,
+// the others appear negated when their bit of value is 0
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub struct Implicant {
+    pub value: u64,
+    pub mask: u64,
+}
+
+impl Implicant {
+  fn covers(&self, minterm: u64) -> bool {
+    minterm & !self.mask == self.value
+  }
+
+  pub fn literals(&self, width: usize) -> usize {
+    width - self.mask.count_ones() as usize
+  }
+}
+
+// Every prime implicant of the function, by merging implicants that differ in a single input
+pub fn prime_implicants(minterms: &[u64], dont_cares: &[u64]) -> Vec<Implicant> {
+  let mut current: Vec<Implicant> = minterms.iter().chain(dont_cares.iter()).map(|&m| Implicant { value: m, mask: 0 }).collect();
+  current.sort();
+  current.dedup();
+  let mut primes = vec![];
+  while !current.is_empty() {
+    let mut merged = vec![false; current.len()];
+    let mut next = vec![];
+    for i in 0..current.len() {
+      for j in i + 1..current.len() {
+        let (a, b) = (current[i], current[j]);
+        let difference = a.value ^ b.value;
+        if a.mask == b.mask && difference.count_ones() == 1 {
+          next.push(Implicant { value: a.value & !difference, mask: a.mask | difference });
+          merged[i] = true;
+          merged[j] = true;
+        }
+      }
+    }
+    primes.extend(current.iter().zip(merged.iter()).filter(|(_, &m)| !m).map(|(&i, _)| i));
+    next.sort();
+    next.dedup();
+    current = next;
+  }
+  primes
+}
+
+// A cover of the minterms: the essential prime implicants, then greedily the prime covering
+// most of the remaining minterms, the one with fewer literals on ties. Not always minimal.
+pub fn minimize(width: usize, minterms: &[u64], dont_cares: &[u64]) -> Vec<Implicant> {
+  let primes = prime_implicants(minterms, dont_cares);
+  let mut uncovered: Vec<u64> = minterms.to_vec();
+  let mut cover: Vec<Implicant> = vec![];
+  for &minterm in minterms.iter() {
+    let covering: Vec<&Implicant> = primes.iter().filter(|p| p.covers(minterm)).collect();
+    if covering.len() == 1 && !cover.contains(covering[0]) {
+      cover.push(*covering[0]);
+    }
+  }
+  uncovered.retain(|&m| !cover.iter().any(|p| p.covers(m)));
+  while !uncovered.is_empty() {
+    let best = primes.iter().max_by_key(|p| {
+      let count = uncovered.iter().filter(|&&m| p.covers(m)).count();
+      (count, std::cmp::Reverse(p.literals(width)))
+    }).unwrap();
+    cover.push(*best);
+    uncovered.retain(|&m| !best.covers(m));
+  }
+  cover
+}
+
+// Inputs and outputs named after the columns of the table, bus pins as "name[i]"
+pub fn from_truth_table(table: &TruthTable) -> Function {
+  let pins = |columns: &[crate::truth_table::Column]| -> Vec<String> {
+    columns.iter().flat_map(|column| (0..column.width).map(move |i| match column.width {
+      1 => column.name.clone(),
+      _ => format!("{}[{}]", column.name, i),
+    })).collect()
+  };
+  let inputs = pins(&table.inputs);
+  let outputs = pins(&table.outputs).into_iter().enumerate().map(|(k, name)| {
+    let minterms = table.rows.iter().enumerate().filter(|(_, (_, out))| out[k] == High).map(|(row, _)| row as u64).collect();
+    (name, minterms, vec![])
+  }).collect();
+  Function { inputs, outputs }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr {
+    Constant(Signal),
+    Input(usize),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Xor(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+  pub fn evaluate(&self, inputs: &[Signal]) -> Signal {
+    match self {
+        Expr::Constant(signal) => *signal,
+        Expr::Input(i) => inputs[*i],
+        Expr::Not(e) => not(e.evaluate(inputs)),
+        Expr::And(a, b) => and(a.evaluate(inputs), b.evaluate(inputs)),
+        Expr::Or(a, b) => or(a.evaluate(inputs), b.evaluate(inputs)),
+        Expr::Xor(a, b) => xor(a.evaluate(inputs), b.evaluate(inputs)),
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "at {}: {}", self.position, self.message)
+  }
+}
+
+impl Error for ParseError {}
+
+// Recursive descent over `!`, `&`, `^`, `|` from the tightest to the loosest, parentheses,
+// the constants 0 and 1 and input names, numbered in order of first appearance
+struct Parser<'a> {
+    chars: Vec<(usize, char)>,
+    position: usize,
+    inputs: &'a mut Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+  fn peek(&mut self) -> Option<char> {
+    while self.position < self.chars.len() && self.chars[self.position].1.is_whitespace() {
+      self.position += 1;
+    }
+    self.chars.get(self.position).map(|&(_, c)| c)
+  }
+
+  fn error(&self, message: &str) -> ParseError {
+    let position = self.chars.get(self.position).map(|&(i, _)| i).unwrap_or_else(|| self.chars.last().map(|&(i, _)| i + 1).unwrap_or(0));
+    ParseError { position, message: message.to_string() }
+  }
+
+  fn binary(&mut self, operator: char, operand: fn(&mut Parser<'a>) -> Result<Expr, ParseError>, node: fn(Box<Expr>, Box<Expr>) -> Expr) -> Result<Expr, ParseError> {
+    let mut expr = operand(self)?;
+    while self.peek() == Some(operator) {
+      self.position += 1;
+      expr = node(Box::new(expr), Box::new(operand(self)?));
+    }
+    Ok(expr)
+  }
+
+  fn or(&mut self) -> Result<Expr, ParseError> {
+    self.binary('|', Parser::xor, Expr::Or)
+  }
+
+  fn xor(&mut self) -> Result<Expr, ParseError> {
+    self.binary('^', Parser::and, Expr::Xor)
+  }
+
+  fn and(&mut self) -> Result<Expr, ParseError> {
+    self.binary('&', Parser::unary, Expr::And)
+  }
+
+  fn unary(&mut self) -> Result<Expr, ParseError> {
+    match self.peek() {
+        Some('!') => {
+          self.position += 1;
+          Ok(Expr::Not(Box::new(self.unary()?)))
+        },
+        Some('(') => {
+          self.position += 1;
+          let expr = self.or()?;
+          match self.peek() {
+              Some(')') => {
+                self.position += 1;
+                Ok(expr)
+              },
+              _ => Err(self.error("expected )")),
+          }
+        },
+        Some('0') => {
+          self.position += 1;
+          Ok(Expr::Constant(Low))
+        },
+        Some('1') => {
+          self.position += 1;
+          Ok(Expr::Constant(High))
+        },
+        Some(c) if c.is_alphabetic() || c == '_' => {
+          let start = self.position;
+          while self.position < self.chars.len() && {
+            let c = self.chars[self.position].1;
+            c.is_alphanumeric() || c == '_' || c == '[' || c == ']'
+          } {
+            self.position += 1;
+          }
+          let name: String = self.chars[start..self.position].iter().map(|&(_, c)| c).collect();
+          let index = match self.inputs.iter().position(|input| *input == name) {
+              Some(index) => index,
+              None => {
+                self.inputs.push(name);
+                self.inputs.len() - 1
+              },
+          };
+          Ok(Expr::Input(index))
+        },
+        _ => Err(self.error("expected an input, a constant, ! or (")),
+    }
+  }
+}
+
+pub fn parse(source: &str, inputs: &mut Vec<String>) -> Result<Expr, ParseError> {
+  let mut parser = Parser { chars: source.char_indices().collect(), position: 0, inputs };
+  let expr = parser.or()?;
+  match parser.peek() {
+      None => Ok(expr),
+      Some(_) => Err(parser.error("unexpected character")),
+  }
+}
+
+// Function of named expressions, e.g. [("sum", "a ^ b"), ("carry", "a & b")]
+pub fn from_expressions(outputs: &[(&str, &str)]) -> Result<Function, ParseError> {
+  let mut inputs = vec![];
+  let exprs = outputs.iter().map(|(_, source)| parse(source, &mut inputs)).collect::<Result<Vec<Expr>, ParseError>>()?;
+  let width = inputs.len();
+  let outputs = outputs.iter().zip(exprs.iter()).map(|((name, _), expr)| {
+    let minterms = (0..1u64 << width).filter(|&row| {
+      let input: Vec<Signal> = (0..width).map(|i| if row >> (width - 1 - i) & 1 == 1 { High } else { Low }).collect();
+      expr.evaluate(&input) == High
+    }).collect();
+    (name.to_string(), minterms, vec![])
+  }).collect();
+  Ok(Function { inputs, outputs })
+}
+
+impl Builder {
+  // Nand of any number of wires, a not for a single one
+  fn nand_all(&mut self, wires: &[Wire]) -> Wire {
+    match wires {
+        [] => self.constant(Low),
+        [wire] => self.not(*wire),
+        _ => {
+          let last = wires.len() - 1;
+          let all_but_last = wires[1..last].iter().fold(wires[0], |acc, &wire| self.and(acc, wire));
+          self.nand(all_but_last, wires[last])
+        },
+    }
+  }
+}
+
+// Nand-nand netlist of the function. Negated inputs and products are shared between outputs.
+pub fn synthesize(name: &str, function: &Function) -> Netlist {
+  let width = function.inputs.len();
+  let mut b = Builder::new(name);
+  let inputs: Vec<Wire> = function.inputs.iter().map(|name| b.input(name)).collect();
+  let mut negated: HashMap<usize, Wire> = HashMap::new();
+  let mut products: HashMap<Implicant, Wire> = HashMap::new();
+  for (output, minterms, dont_cares) in function.outputs.iter() {
+    let cover = minimize(width, minterms, dont_cares);
+    let wire = b.chip(output, |b| {
+      let mut terms = vec![];
+      for implicant in cover.iter() {
+        let literals: Vec<(usize, bool)> = (0..width)
+          .filter(|i| implicant.mask >> (width - 1 - i) & 1 == 0)
+          .map(|i| (i, implicant.value >> (width - 1 - i) & 1 == 1))
+          .collect();
+        if literals.is_empty() {
+          return b.constant(High);
+        }
+        if let [(i, positive)] = literals[..] {
+          if cover.len() == 1 && positive {
+            return inputs[i];
+          }
+        }
+        // nand of the product, a single literal needs none: it is the opposite literal
+        let term = match products.get(implicant) {
+            Some(&term) => term,
+            None => {
+              let term = match literals[..] {
+                  [(i, true)] => *negated.entry(i).or_insert_with(|| b.not(inputs[i])),
+                  [(i, false)] => inputs[i],
+                  _ => {
+                    let wires: Vec<Wire> = literals.iter().map(|&(i, positive)| match positive {
+                      true => inputs[i],
+                      false => *negated.entry(i).or_insert_with(|| b.not(inputs[i])),
+                    }).collect();
+                    b.nand_all(&wires)
+                  },
+              };
+              products.insert(*implicant, term);
+              term
+            },
+        };
+        terms.push(term);
+      }
+      b.nand_all(&terms)
+    });
+    b.output(output, wire);
+  }
+  b.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip::*;
+    use crate::{gates, netlist};
+    use crate::truth_table::*;
+
+    fn reference(function: &Function) -> impl Fn(&[Signal]) -> Vec<Signal> + '_ {
+        move |input: &[Signal]| {
+            let row = input.iter().fold(0, |acc, &s| acc << 1 | (s == High) as u64);
+            function.outputs.iter().map(|(_, minterms, _)| Signal::from(minterms.contains(&row))).collect()
+        }
+    }
+
+    #[test]
+    fn tminimize() {
+        // f = b!c!d + a!d + ac with don't cares 9 and 14
+        let cover = minimize(4, &[4, 8, 10, 11, 12, 15], &[9, 14]);
+        let mut cover: Vec<(u64, u64)> = cover.iter().map(|p| (p.value, p.mask)).collect();
+        cover.sort();
+        assert_eq!(cover, vec![(0b0100, 0b1000), (0b1000, 0b0110), (0b1010, 0b0101)]);
+        assert_eq!(prime_implicants(&[0, 1, 2, 3, 4, 5, 6, 7], &[]), vec![Implicant { value: 0, mask: 7 }]);
+        assert!(minimize(3, &[], &[1]).is_empty());
+    }
+    #[test]
+    fn tparse() {
+        let mut inputs = vec![];
+        let expr = parse("a & !(b | 0) ^ a", &mut inputs).unwrap();
+        assert_eq!(inputs, vec!["a", "b"]);
+        assert_eq!(expr.evaluate(&[High, Low]), Low);
+        assert_eq!(parse("a & ", &mut inputs), Err(ParseError { position: 4, message: "expected an input, a constant, ! or (".to_string() }));
+        assert_eq!(parse("(a", &mut inputs).unwrap_err().message, "expected )");
+        assert_eq!(parse("a b", &mut inputs).unwrap_err().position, 2);
+    }
+    #[test]
+    fn tsynthesize_expressions() {
+        let function = from_expressions(&[("sum", "a ^ b ^ c"), ("carry", "a & b | c & (a ^ b)"), ("a", "a"), ("one", "a | !a"), ("zero", "a & !a")]).unwrap();
+        let netlist = synthesize("full_adder", &function);
+        assert_eq!(verify_netlist(&netlist, reference(&function), DEFAULT_LIMIT), Ok(8));
+        assert_eq!(netlist.evaluate(&[High, High, Low]), vec![Low, High, High, High, Low]);
+        let mux = synthesize("mux", &from_expressions(&[("out", "in1 & !sel | in2 & sel")]).unwrap());
+        // inputs are numbered in order of appearance: in1, sel, in2
+        assert_eq!(verify_netlist(&mux, |i| vec![gates::mux(i[0], i[2], i[1])], DEFAULT_LIMIT), Ok(8));
+        assert_eq!(mux.nand_count(), netlist::mux().nand_count());
+    }
+    #[test]
+    fn tsynthesize_dmux_8_way() {
+        let function = from_truth_table(&truth_table(&mut Dmux8Way).unwrap());
+        assert_eq!(function.inputs, vec!["in1", "sel1", "sel2", "sel3"]);
+        let netlist = synthesize("dmux_8_way", &function);
+        assert_eq!(verify_netlist(&netlist, |i| gates::dmux_8_way(i[0], i[1], i[2], i[3]).to_vec(), DEFAULT_LIMIT), Ok(16));
+        // the hand written chip recomputes the negated selection bits in every and_m_way
+        assert!(netlist.nand_count() < netlist::dmux_8_way().nand_count());
+    }
+}
@@ -0,0 +1,214 @@
+use crate::netlist::*;
+use std::fmt;
+
+// Static timing analysis of combinational netlists. Every nand has a delay, 1 by default,
+// and the arrival time of a wire is the worst delay accumulated from the inputs.
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct PinDelay {
+    pub input: String,
+    pub output: String,
+    pub delay: u32,
+}
+
+// Consecutive nands of the same subchip instance on a path
+#[derive(Debug, PartialEq, Clone)]
+pub struct Subgate {
+    pub scope: usize,
+    pub name: String,
+    pub arrival: u32,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Path {
+    pub input: String,
+    pub output: String,
+    pub delay: u32,
+    pub nands: Vec<Wire>,
+    pub subgates: Vec<Subgate>,
+}
+
+pub fn unit_delay(_: &Netlist, _: Wire) -> u32 {
+  1
+}
+
+// Every pin of the ports with its name, an output wire can be named by several ports
+fn pins(ports: &[Port]) -> Vec<(Wire, String)> {
+  ports.iter().flat_map(|port| port.wires.iter().enumerate().map(move |(i, &wire)| match port.wires.len() {
+    1 => (wire, port.name.clone()),
+    _ => (wire, format!("{}[{}]", port.name, i)),
+  })).collect()
+}
+
+// Arrival time of every wire reachable from `sources`, and the input of each nand it came from
+fn propagate<F: Fn(&Netlist, Wire) -> u32>(netlist: &Netlist, sources: &[Wire], delay: &F) -> (Vec<Option<u32>>, Vec<Option<Wire>>) {
+  let mut arrival = vec![None; netlist.nodes.len()];
+  let mut from = vec![None; netlist.nodes.len()];
+  for &source in sources {
+    arrival[source] = Some(0);
+  }
+  for (wire, node) in netlist.nodes.iter().enumerate() {
+    if let Gate::Nand(in1, in2) = node.gate {
+      let worst = match (arrival[in1], arrival[in2]) {
+        (Some(a1), Some(a2)) if a2 > a1 => Some(in2),
+        (Some(_), _) => Some(in1),
+        (None, Some(_)) => Some(in2),
+        (None, None) => None,
+      };
+      if let Some(worst) = worst {
+        arrival[wire] = arrival[worst].map(|a| a + delay(netlist, wire));
+        from[wire] = Some(worst);
+      }
+    }
+  }
+  (arrival, from)
+}
+
+pub fn pin_delays(netlist: &Netlist) -> Vec<PinDelay> {
+  pin_delays_with(netlist, unit_delay)
+}
+
+// Worst delay between every input and output pin connected by at least one path
+pub fn pin_delays_with<F: Fn(&Netlist, Wire) -> u32>(netlist: &Netlist, delay: F) -> Vec<PinDelay> {
+  let outputs = pins(&netlist.outputs);
+  let mut delays = vec![];
+  for (input, input_name) in pins(&netlist.inputs) {
+    let (arrival, _) = propagate(netlist, &[input], &delay);
+    for (output, output_name) in outputs.iter() {
+      if let Some(time) = arrival[*output] {
+        delays.push(PinDelay {
+          input: input_name.clone(),
+          output: output_name.clone(),
+          delay: time,
+        });
+      }
+    }
+  }
+  delays
+}
+
+// Wires of a port ("bits1") or of a single pin ("bits1[3]")
+pub fn pin_wires(ports: &[Port], pin: &str) -> Vec<Wire> {
+  select(ports, pin).into_iter().map(|(wire, _)| wire).collect()
+}
+
+fn select(ports: &[Port], pin: &str) -> Vec<(Wire, String)> {
+  let port = ports.iter().find(|port| port.name == pin).map(std::slice::from_ref).unwrap_or(&[]);
+  let mut selected = pins(port);
+  selected.extend(pins(ports).into_iter().filter(|(_, name)| name == pin));
+  selected
+}
+
+pub fn critical_path(netlist: &Netlist) -> Option<Path> {
+  critical_path_with(netlist, unit_delay)
+}
+
+// Slowest path from any input to any output, None if no output depends on an input
+pub fn critical_path_with<F: Fn(&Netlist, Wire) -> u32>(netlist: &Netlist, delay: F) -> Option<Path> {
+  slowest_path(netlist, &pins(&netlist.inputs), &pins(&netlist.outputs), &delay)
+}
+
+pub fn path(netlist: &Netlist, input: &str, output: &str) -> Option<Path> {
+  path_with(netlist, input, output, unit_delay)
+}
+
+// Slowest path between two pins or ports, e.g. from "bits1" to "ng" in the alu
+pub fn path_with<F: Fn(&Netlist, Wire) -> u32>(netlist: &Netlist, input: &str, output: &str, delay: F) -> Option<Path> {
+  slowest_path(netlist, &select(&netlist.inputs, input), &select(&netlist.outputs, output), &delay)
+}
+
+fn slowest_path<F: Fn(&Netlist, Wire) -> u32>(netlist: &Netlist, inputs: &[(Wire, String)], outputs: &[(Wire, String)], delay: &F) -> Option<Path> {
+  let sources: Vec<Wire> = inputs.iter().map(|(wire, _)| *wire).collect();
+  let (arrival, from) = propagate(netlist, &sources, delay);
+  let (output, output_name) = outputs.iter().filter(|(wire, _)| arrival[*wire].is_some()).max_by_key(|(wire, _)| arrival[*wire])?;
+  let output = *output;
+  let mut nands = vec![];
+  let mut wire = output;
+  while let Some(previous) = from[wire] {
+    nands.push(wire);
+    wire = previous;
+  }
+  nands.reverse();
+  let mut subgates: Vec<Subgate> = vec![];
+  for &nand in nands.iter() {
+    let scope = netlist.nodes[nand].scope;
+    match subgates.last_mut() {
+      Some(last) if last.scope == scope => last.arrival = arrival[nand].unwrap(),
+      _ => subgates.push(Subgate { scope, name: netlist.scope_path(scope), arrival: arrival[nand].unwrap() }),
+    }
+  }
+  Some(Path {
+    input: inputs.iter().find(|(input, _)| *input == wire).unwrap().1.clone(),
+    output: output_name.clone(),
+    delay: arrival[output].unwrap(),
+    nands,
+    subgates,
+  })
+}
+
+impl fmt::Display for Path {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} -> {}: delay {}", self.input, self.output, self.delay)?;
+    for subgate in self.subgates.iter() {
+      write!(f, "\n  {:>4} {}", subgate.arrival, subgate.name)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tpin_delays() {
+        let delays = pin_delays(&mux());
+        assert_eq!(delays.iter().map(|d| (d.input.as_str(), d.delay)).collect::<Vec<_>>(), vec![("in1", 2), ("in2", 2), ("sel", 3)]);
+        let delays = pin_delays(&n_adder::<4>());
+        let delay = |input: &str, output: &str| delays.iter().find(|d| d.input == input && d.output == output).map(|d| d.delay);
+        assert_eq!(delay("in1[0]", "out[3]"), None);
+        assert!(delay("in1[3]", "out[0]") > delay("in1[3]", "out[1]"));
+    }
+    #[test]
+    fn tcritical_path() {
+        let path = critical_path(&xor()).unwrap();
+        assert_eq!(path.delay, 3);
+        assert_eq!(path.nands.len(), 3);
+        assert_eq!(path.subgates, vec![Subgate { scope: 1, name: "xor/xor".to_string(), arrival: 3 }]);
+    }
+    #[test]
+    fn tripple_carry() {
+        let delay8 = critical_path(&n_adder::<8>()).unwrap().delay;
+        let delay16 = critical_path(&n_adder::<16>()).unwrap().delay;
+        assert_eq!(delay16 - delay8, 8 * 4);
+        let netlist = alu::<16>();
+        assert_eq!(critical_path(&netlist).unwrap().output, "zr");
+        let path = path(&netlist, "bits1", "ng").unwrap();
+        assert_eq!((path.input.as_str(), path.output.as_str()), ("bits1[15]", "ng"));
+        assert_eq!(path.subgates.iter().filter(|s| s.name.ends_with("full_adder/or")).count(), 15);
+        assert!(super::path(&netlist, "ng", "bits1").is_none());
+    }
+    #[test]
+    fn tadder_architectures() {
+        let ripple = critical_path(&n_adder::<16>()).unwrap().delay;
+        let lookahead = critical_path(&cla_adder::<16>()).unwrap().delay;
+        let select = critical_path(&carry_select_adder::<16>()).unwrap().delay;
+        assert!(lookahead < ripple);
+        assert!(select < ripple);
+    }
+    #[test]
+    fn tpin_wires() {
+        let netlist = alu::<4>();
+        assert_eq!(pin_wires(&netlist.inputs, "bits2"), vec![4, 5, 6, 7]);
+        assert_eq!(pin_wires(&netlist.inputs, "bits2[1]"), vec![5]);
+        assert!(pin_wires(&netlist.outputs, "bits2").is_empty());
+    }
+    #[test]
+    fn tdelay_annotation() {
+        let netlist = xor();
+        let path = critical_path_with(&netlist, |netlist, wire| if wire == netlist.outputs[0].wires[0] { 10 } else { 1 }).unwrap();
+        assert_eq!(path.delay, 12);
+        assert_eq!(pin_delays_with(&netlist, |_, _| 2)[0].delay, 6);
+        assert_eq!(format!("{}", path), "in1 -> out: delay 12\n    12 xor/xor");
+    }
+}
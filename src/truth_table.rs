@@ -0,0 +1,257 @@
+use crate::gates::*;
+use crate::chip::{AnyChip, Chip, Pins};
+use crate::netlist::{Netlist, Port};
+use crate::bitslice;
+use Signal::*;
+use std::error::Error;
+use std::fmt;
+
+// Exhaustive truth tables of combinational chips. Rows enumerate the inputs in binary order,
+// the first input pin being the msb, as in the nand2tetris .cmp files. Anything wider than
+// the limit is refused instead of running for hours.
+
+pub const DEFAULT_LIMIT: usize = 24;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TableError {
+    TooWide { width: usize, limit: usize },
+    Mismatch { input: Vec<Signal>, expected: Vec<Signal>, actual: Vec<Signal> },
+}
+
+fn bit_string(signals: &[Signal]) -> String {
+  signals.iter().map(|&s| if s == High { '1' } else { '0' }).collect()
+}
+
+impl fmt::Display for TableError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+        TableError::TooWide { width, limit } => write!(f, "{} inputs is more than the limit of {}", width, limit),
+        TableError::Mismatch { input, expected, actual } => write!(f, "input {}: expected {}, got {}",
+          bit_string(input), bit_string(expected), bit_string(actual)),
+    }
+  }
+}
+
+impl Error for TableError {}
+
+// Name and width of every column, one per port
+#[derive(Debug, PartialEq, Clone)]
+pub struct Column {
+    pub name: String,
+    pub width: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TruthTable {
+    pub name: String,
+    pub inputs: Vec<Column>,
+    pub outputs: Vec<Column>,
+    pub rows: Vec<(Vec<Signal>, Vec<Signal>)>,
+}
+
+// Rows are counted in a u64, so whatever the limit there are at most 63 inputs
+fn check_width(width: usize, limit: usize) -> Result<(), TableError> {
+  let limit = limit.min(63);
+  match width > limit {
+      true => Err(TableError::TooWide { width, limit }),
+      false => Ok(()),
+  }
+}
+
+// Row `row` of the table: the msb first binary of the row number on `width` signals
+pub fn row_input(row: u64, width: usize) -> Vec<Signal> {
+  (0..width).map(|i| if row >> (width - 1 - i) & 1 == 1 { High } else { Low }).collect()
+}
+
+// Every row of a table of `width` inputs, the row numbers being counted in a u64
+pub fn inputs(width: usize) -> impl Iterator<Item = Vec<Signal>> {
+  assert!(width < 64, "{} inputs have more rows than a u64 can count", width);
+  (0..1u64 << width).map(move |row| row_input(row, width))
+}
+
+pub fn columns(ports: &[Port]) -> Vec<Column> {
+  ports.iter().map(|port| Column { name: port.name.clone(), width: port.wires.len() }).collect()
+}
+
+// Columns come from the ports of the chip netlist, a chip without one gets single in and out buses
+pub fn truth_table(chip: &mut dyn AnyChip) -> Result<TruthTable, TableError> {
+  truth_table_with_limit(chip, DEFAULT_LIMIT)
+}
+
+pub fn truth_table_with_limit(chip: &mut dyn AnyChip, limit: usize) -> Result<TruthTable, TableError> {
+  check_width(chip.input_width(), limit)?;
+  let (inputs_columns, outputs_columns) = match chip.netlist() {
+      Some(netlist) => (columns(&netlist.inputs), columns(&netlist.outputs)),
+      None => (
+        vec![Column { name: "in".to_string(), width: chip.input_width() }],
+        vec![Column { name: "out".to_string(), width: chip.output_width() }],
+      ),
+  };
+  chip.reset();
+  let rows = inputs(chip.input_width()).map(|input| {
+    let output = chip.compute_signals(&input);
+    (input, output)
+  }).collect();
+  Ok(TruthTable { name: chip.name(), inputs: inputs_columns, outputs: outputs_columns, rows })
+}
+
+// Checks every row of a chip against a reference, returns the number of rows checked
+pub fn verify<C: Chip, F>(chip: &mut C, reference: F) -> Result<u64, TableError>
+where
+  F: Fn(C::Input) -> C::Output,
+{
+  verify_with_limit(chip, reference, DEFAULT_LIMIT)
+}
+
+pub fn verify_with_limit<C: Chip, F>(chip: &mut C, reference: F, limit: usize) -> Result<u64, TableError>
+where
+  F: Fn(C::Input) -> C::Output,
+{
+  check_width(C::Input::WIDTH, limit)?;
+  let mut rows = 0;
+  for input in inputs(C::Input::WIDTH) {
+    let expected = reference(C::Input::from_signals(&input)).to_signals();
+    let actual = chip.compute(C::Input::from_signals(&input)).to_signals();
+    if actual != expected {
+      return Err(TableError::Mismatch { input, expected, actual });
+    }
+    rows += 1;
+  }
+  Ok(rows)
+}
+
+// Same check on a netlist, simulated 64 rows at a time. Much faster on wide chips, it is the
+// way to go through the 2^22 rows of an 8 bit alu. The reference gets flattened ports.
+pub fn verify_netlist<F>(netlist: &Netlist, reference: F, limit: usize) -> Result<u64, TableError>
+where
+  F: Fn(&[Signal]) -> Vec<Signal>,
+{
+  let width = netlist.input_width();
+  check_width(width, limit)?;
+  let rows = 1u64 << width;
+  for batch in 0..rows.div_ceil(bitslice::LANES as u64) {
+    let first = batch * bitslice::LANES as u64;
+    let lanes = (rows - first).min(bitslice::LANES as u64);
+    let mut packed = vec![0u64; width];
+    let mut expected = vec![0u64; netlist.output_width()];
+    for k in 0..lanes {
+      let input = row_input(first + k, width);
+      for (word, &s) in packed.iter_mut().zip(input.iter()) {
+        *word |= ((s == High) as u64) << k;
+      }
+      for (word, &s) in expected.iter_mut().zip(reference(&input).iter()) {
+        *word |= ((s == High) as u64) << k;
+      }
+    }
+    let mask = if lanes == 64 { u64::MAX } else { (1 << lanes) - 1 };
+    let actual = netlist.evaluate(&packed);
+    let differences = actual.iter().zip(expected.iter()).fold(0, |acc, (a, e)| acc | ((a ^ e) & mask));
+    if differences != 0 {
+      let k = differences.trailing_zeros() as usize;
+      let input = row_input(first + k as u64, width);
+      return Err(TableError::Mismatch {
+        expected: reference(&input),
+        actual: actual.iter().map(|&word| bitslice::lane(word, k)).collect(),
+        input,
+      });
+    }
+  }
+  Ok(rows)
+}
+
+// Laid out like a nand2tetris .cmp file, one column per port with buses as bit strings
+impl fmt::Display for TruthTable {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let columns: Vec<&Column> = self.inputs.iter().chain(self.outputs.iter()).collect();
+    let widths: Vec<usize> = columns.iter().map(|c| c.name.len().max(c.width) + 2).collect();
+    for (column, &width) in columns.iter().zip(widths.iter()) {
+      write!(f, "|{:^width$}", column.name, width = width)?;
+    }
+    writeln!(f, "|")?;
+    for (input, output) in self.rows.iter() {
+      let mut signals = input.iter().chain(output.iter());
+      for (column, &width) in columns.iter().zip(widths.iter()) {
+        let cell: Vec<Signal> = signals.by_ref().take(column.width).copied().collect();
+        write!(f, "|{:^width$}", bit_string(&cell), width = width)?;
+      }
+      writeln!(f, "|")?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip::*;
+    use crate::{alu, netlist};
+    use crate::utilities::*;
+
+    #[test]
+    fn ttruth_table() {
+        let table = truth_table(&mut FullAdder).unwrap();
+        assert_eq!(table.rows.len(), 8);
+        assert_eq!(table.rows[3], (vec![Low, High, High], vec![Low, High]));
+        assert_eq!(format!("{}", truth_table(&mut And).unwrap()),
+            "| in1 | in2 | out |\n|  0  |  0  |  0  |\n|  0  |  1  |  0  |\n|  1  |  0  |  0  |\n|  1  |  1  |  1  |\n");
+        let table = truth_table(&mut Decoder::<1, 2>).unwrap();
+        assert_eq!(format!("{}", table), "| in | out |\n| 0  | 10  |\n| 1  | 01  |\n");
+        assert_eq!(truth_table(&mut netlist::full_adder()).unwrap().rows, truth_table(&mut FullAdder).unwrap().rows);
+        assert_eq!(truth_table(&mut Alu::<16>), Err(TableError::TooWide { width: 38, limit: DEFAULT_LIMIT }));
+    }
+    #[test]
+    fn tinputs() {
+        assert_eq!(inputs(0).collect::<Vec<_>>(), vec![vec![]]);
+        assert_eq!(inputs(2).nth(2), Some(vec![High, Low]));
+        assert_eq!(inputs(63).nth(5).map(|row| row[60..].to_vec()), Some(vec![High, Low, High]));
+    }
+    #[test]
+    #[should_panic(expected = "64 inputs have more rows than a u64 can count")]
+    fn tinputs_width() {
+        let _ = inputs(64);
+    }
+    #[test]
+    fn tcheck_width() {
+        let wide = netlist::and_m_way::<64>();
+        assert_eq!(verify_netlist(&wide, |_| vec![Low], usize::MAX), Err(TableError::TooWide { width: 64, limit: 63 }));
+        assert_eq!(truth_table_with_limit(&mut wide.clone(), 64).unwrap_err(), TableError::TooWide { width: 64, limit: 63 });
+        assert_eq!(check_width(63, usize::MAX), Ok(()));
+    }
+    #[test]
+    fn tverify() {
+        let rows = verify(&mut FullAdder, |(a, b, c)| {
+            let sum = [a, b, c].iter().filter(|&&s| s == High).count();
+            (Signal::from(sum & 1 == 1), Signal::from(sum >= 2))
+        });
+        assert_eq!(rows, Ok(8));
+        let rows = verify(&mut Mux8WayN::<1>, |(ins, s1, s2, s3)| ins[bool::from(s1) as usize + 2 * bool::from(s2) as usize + 4 * bool::from(s3) as usize]);
+        assert_eq!(rows, Ok(1 << 11));
+        let error = verify(&mut Or, |(a, b)| a ^ b).unwrap_err();
+        assert_eq!(error, TableError::Mismatch { input: vec![High, High], expected: vec![Low], actual: vec![High] });
+        assert_eq!(format!("{}", error), "input 11: expected 0, got 1");
+    }
+    #[test]
+    fn tverify_netlist() {
+        // every one of the 2^18 rows of the 6 bit alu against a reference on integers
+        let reference = |input: &[Signal]| {
+            let x = from_signals_truncated::<u8, 6>(Pins::from_signals(&input[..6]), Order::MsbFirst);
+            let y = from_signals_truncated::<u8, 6>(Pins::from_signals(&input[6..12]), Order::MsbFirst);
+            let c: Vec<bool> = input[12..].iter().map(|&s| bool::from(s)).collect();
+            let x = if c[0] { 0 } else { x };
+            let x = if c[1] { !x } else { x };
+            let y = if c[2] { 0 } else { y };
+            let y = if c[3] { !y } else { y };
+            let out = if c[4] { x.wrapping_add(y) } else { x & y };
+            let out = if c[5] { !out } else { out } & 63;
+            let mut signals = to_signals_truncated::<6, _>(out, Order::MsbFirst).to_vec();
+            signals.extend_from_slice(&[Signal::from(out == 0), Signal::from(out >= 32)]);
+            signals
+        };
+        assert_eq!(verify_netlist(&netlist::alu::<6>(), reference, DEFAULT_LIMIT), Ok(1 << 18));
+        let rows = verify_netlist(&netlist::not(), |input| vec![input[0]], DEFAULT_LIMIT);
+        assert_eq!(rows, Err(TableError::Mismatch { input: vec![Low], expected: vec![Low], actual: vec![High] }));
+        assert!(verify_netlist(&netlist::alu::<16>(), |_| vec![], DEFAULT_LIMIT).is_err());
+        let rows = verify(&mut Alu::<2>, |(a, b, zx, nx, zy, ny, f, no)| alu::alu(a, b, zx, nx, zy, ny, f, no));
+        assert_eq!(rows, Ok(1 << 10));
+    }
+}
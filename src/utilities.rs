@@ -1,34 +1,177 @@
 use crate::gates::*;
-use crate::alu::n_incrementor;
 use Signal::*;
-use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
 
 
 pub fn int_to_binary16(int: i16) -> Signals<16> {
-  let binary_string = format!("{:016b}", int);
-  let mut binary: Vec<Signal> = vec![];
-  for bit in binary_string.chars() {
-      match bit {
-          '0' => binary.push(Low),
-          '1' => binary.push(High),
-          _ => ()
+  to_signals_truncated(int, Order::MsbFirst)
+}
+
+pub fn binary_to_int16(binary: Signals<16>) -> i16 {
+  from_signals(binary, Order::MsbFirst).unwrap()
+}
+
+// Bit order of a bus: the chips of the crate are msb first, index 0 holding the sign bit
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Order {
+    MsbFirst,
+    LsbFirst,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ConversionError {
+    // The integer needs more than the available signals
+    Truncation { value: i128, bits: usize },
+    // The signals hold a value outside the range of the integer type
+    Overflow { bits: usize, target: &'static str },
+}
+
+impl fmt::Display for ConversionError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+        ConversionError::Truncation { value, bits } => write!(f, "{} does not fit in {} bits", value, bits),
+        ConversionError::Overflow { bits, target } => write!(f, "{} bits hold a value out of the range of {}", bits, target),
+    }
+  }
+}
+
+impl Error for ConversionError {}
+
+// Primitive integers the conversions accept, signed ones use two's complement
+pub trait Integer: Copy {
+    const SIGNED: bool;
+    fn to_i128(self) -> i128;
+    fn from_i128(value: i128) -> Option<Self>;
+    fn wrap_i128(value: i128) -> Self;
+}
+
+macro_rules! integer {
+  ($($t:ty: $signed:expr),*) => {$(
+    impl Integer for $t {
+      const SIGNED: bool = $signed;
+      fn to_i128(self) -> i128 {
+        self as i128
+      }
+      fn from_i128(value: i128) -> Option<$t> {
+        use std::convert::TryFrom;
+        <$t>::try_from(value).ok()
+      }
+      fn wrap_i128(value: i128) -> $t {
+        value as $t
       }
+    }
+  )*};
+}
+
+integer!(u8: false, u16: false, u32: false, u64: false, i8: true, i16: true, i32: true, i64: true);
+
+fn ordered<const BITS: usize>(mut signals: Signals<BITS>, order: Order) -> Signals<BITS> {
+  if order == Order::LsbFirst {
+    signals.reverse();
   }
-  binary.try_into().unwrap()
+  signals
 }
 
-pub fn binary_to_int16(mut binary: Signals<16>) -> i16 {
-  let mut sign = 1;
-  if binary[0] == High {
-      sign = - 1;
-      binary = n_incrementor(not_n(binary)); 
+// Bits above BITS are dropped, like an `as` cast to a BITS wide integer
+pub fn to_signals_truncated<const BITS: usize, T: Integer>(value: T, order: Order) -> Signals<BITS> {
+  let value = value.to_i128();
+  let mut signals = [Low; BITS];
+  for (i, signal) in signals.iter_mut().enumerate() {
+    let shift = (BITS - 1 - i).min(127);
+    *signal = if value >> shift & 1 == 1 { High } else { Low };
   }
-  let binary_string = binary.iter().fold(String::from(""), |mut acc, &bit| {
-      match bit {
-          Low => acc.push('0'),
-          High => acc.push('1')
-      }
-      acc
-  });
-  sign * i16::from_str_radix(&binary_string, 2).unwrap()
-}
\ No newline at end of file
+  ordered(signals, order)
+}
+
+// Fails unless the value is in the range of a BITS wide integer of the same signedness as T
+pub fn to_signals<const BITS: usize, T: Integer>(value: T, order: Order) -> Result<Signals<BITS>, ConversionError> {
+  let v = value.to_i128();
+  let fits = match (T::SIGNED, BITS) {
+      (_, 0) => v == 0,
+      (true, _) if BITS <= 127 => -(1 << (BITS - 1)) <= v && v < 1 << (BITS - 1),
+      (false, _) if BITS <= 126 => v < 1 << BITS,
+      _ => true,
+  };
+  match fits {
+      true => Ok(to_signals_truncated(value, order)),
+      false => Err(ConversionError::Truncation { value: v, bits: BITS }),
+  }
+}
+
+// Reads the signals as a signed number when T is signed, unsigned otherwise
+pub fn from_signals<T: Integer, const BITS: usize>(signals: Signals<BITS>, order: Order) -> Result<T, ConversionError> {
+  let signals = ordered(signals, order);
+  let overflow = ConversionError::Overflow { bits: BITS, target: std::any::type_name::<T>() };
+  let negative = T::SIGNED && BITS > 0 && signals[0] == High;
+  let mut value: i128 = if negative { -1 } else { 0 };
+  for &signal in signals.iter() {
+    value = value.checked_mul(2).and_then(|v| v.checked_add((signal == High) as i128)).ok_or(overflow)?;
+  }
+  T::from_i128(value).ok_or(overflow)
+}
+
+// Keeps the lowest bits that fit in T, like an `as` cast
+pub fn from_signals_truncated<T: Integer, const BITS: usize>(signals: Signals<BITS>, order: Order) -> T {
+  let signals = ordered(signals, order);
+  let negative = T::SIGNED && BITS > 0 && signals[0] == High;
+  let value = signals.iter().fold(if negative { -1i128 } else { 0 }, |acc, &s| acc.wrapping_mul(2) | (s == High) as i128);
+  T::wrap_i128(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bits::bits;
+
+    #[test]
+    fn tbinary16() {
+        for &x in [0i16, 1, -1, 12345, -32768, 32767].iter() {
+            assert_eq!(binary_to_int16(int_to_binary16(x)), x);
+        }
+        assert_eq!(int_to_binary16(-2), bits("1111_1111_1111_1110"));
+    }
+    #[test]
+    fn tto_signals() {
+        assert_eq!(to_signals(5u8, Order::MsbFirst), Ok(bits::<4>("0101")));
+        assert_eq!(to_signals(5u8, Order::LsbFirst), Ok(bits::<4>("1010")));
+        assert_eq!(to_signals(-3i32, Order::MsbFirst), Ok(bits::<4>("1101")));
+        assert_eq!(to_signals::<4, _>(16u64, Order::MsbFirst), Err(ConversionError::Truncation { value: 16, bits: 4 }));
+        assert_eq!(to_signals::<4, _>(8i8, Order::MsbFirst), Err(ConversionError::Truncation { value: 8, bits: 4 }));
+        assert_eq!(to_signals::<4, _>(-9i8, Order::MsbFirst), Err(ConversionError::Truncation { value: -9, bits: 4 }));
+        assert_eq!(to_signals::<64, _>(u64::MAX, Order::MsbFirst), Ok([High; 64]));
+        assert_eq!(to_signals::<70, _>(-1i64, Order::MsbFirst), Ok([High; 70]));
+        // 1 << 127 overflows an i128, the widest unsigned checks must not depend on it
+        assert_eq!(to_signals::<127, _>(0u8, Order::MsbFirst), Ok([Low; 127]));
+        assert_eq!(to_signals::<127, _>(u64::MAX, Order::LsbFirst).map(|s| s[..64] == [High; 64] && s[64..] == [Low; 63]), Ok(true));
+        assert_eq!(to_signals::<128, _>(0u64, Order::MsbFirst), Ok([Low; 128]));
+        assert_eq!(to_signals::<127, _>(i64::MIN, Order::MsbFirst).map(|s| s[63]), Ok(High));
+        assert_eq!(to_signals::<128, _>(-1i8, Order::MsbFirst), Ok([High; 128]));
+        assert_eq!(to_signals_truncated::<4, _>(0x1234u16, Order::MsbFirst), bits("0100"));
+    }
+    #[test]
+    fn tfrom_signals() {
+        assert_eq!(from_signals::<u8, 4>(bits("1101"), Order::MsbFirst), Ok(13));
+        assert_eq!(from_signals::<i8, 4>(bits("1101"), Order::MsbFirst), Ok(-3));
+        assert_eq!(from_signals::<i8, 4>(bits("1101"), Order::LsbFirst), Ok(-5));
+        assert_eq!(from_signals::<u8, 9>(bits("1_0000_0000"), Order::MsbFirst), Err(ConversionError::Overflow { bits: 9, target: "u8" }));
+        assert_eq!(from_signals::<u8, 9>(bits("0_1111_1111"), Order::MsbFirst), Ok(255));
+        assert_eq!(from_signals::<i64, 0>([], Order::MsbFirst), Ok(0));
+        assert_eq!(from_signals::<u64, 200>([High; 200], Order::MsbFirst), Err(ConversionError::Overflow { bits: 200, target: "u64" }));
+        assert_eq!(from_signals::<i32, 200>([High; 200], Order::MsbFirst), Ok(-1));
+        assert_eq!(from_signals_truncated::<u8, 12>(bits("1111_0000_0001"), Order::MsbFirst), 1);
+        assert_eq!(from_signals_truncated::<i8, 12>(bits("0000_1000_0000"), Order::MsbFirst), -128);
+    }
+    #[test]
+    fn troundtrip() {
+        for x in i8::MIN..=i8::MAX {
+            let signals: Signals<8> = to_signals(x, Order::LsbFirst).unwrap();
+            assert_eq!(from_signals(signals, Order::LsbFirst), Ok(x));
+            assert_eq!(from_signals::<u8, 8>(signals, Order::LsbFirst), Ok(x as u8));
+        }
+        for &x in [0u32, 1, 0xdead_beef, u32::MAX].iter() {
+            let signals: Signals<32> = to_signals(x, Order::MsbFirst).unwrap();
+            assert_eq!(from_signals(signals, Order::MsbFirst), Ok(x));
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use crate::gates::*;
+use crate::level::Level;
+use crate::chip::{Chip, Pins};
+use crate::hack::{self, Computer, RAM_SIZE};
+use crate::utilities::{to_signals_truncated, Order};
+use std::fmt;
+
+// Waveforms of clocked simulations as IEEE 1364 value change dump files, the text format
+// GTKWave and every hdl simulator read. A clock cycle is two time units: tick raises clk,
+// tock lowers it. Values are recorded when they change, at the current time, and names
+// with slashes ("cpu/pc") are nested in scopes.
+
+pub const TIMESCALE: &str = "1 ns";
+
+#[derive(Debug, PartialEq, Clone)]
+struct Variable {
+    scope: Vec<String>,
+    name: String,
+    width: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Tracer {
+    pub name: String,
+    pub time: u64,
+    variables: Vec<Variable>,
+    // Current value of every variable, None until first set
+    values: Vec<Option<String>>,
+    changes: Vec<(u64, usize, String)>,
+    clock: usize,
+}
+
+// Short identifier of variable n, in base 94 over the printable characters
+fn identifier(mut n: usize) -> String {
+  let mut id = String::new();
+  loop {
+    id.push((b'!' + (n % 94) as u8) as char);
+    n /= 94;
+    if n == 0 {
+      return id;
+    }
+    n -= 1;
+  }
+}
+
+impl Tracer {
+  pub fn new(name: &str) -> Tracer {
+    let mut tracer = Tracer { name: name.to_string(), time: 0, variables: vec![], values: vec![], changes: vec![], clock: 0 };
+    tracer.clock = tracer.add("clk", 1);
+    tracer
+  }
+
+  // Declares a variable of `width` bits, returns the handle to set it with
+  pub fn add(&mut self, name: &str, width: usize) -> usize {
+    assert!(width > 0, "{} has no bits", name);
+    let mut scope: Vec<String> = name.split('/').map(|part| part.to_string()).collect();
+    let name = scope.pop().unwrap();
+    self.variables.push(Variable { scope, name, width });
+    self.values.push(None);
+    self.variables.len() - 1
+  }
+
+  // Value of the variable from now on, msb first. Level signals show up as x and z.
+  pub fn set<S: Copy + Into<Level>>(&mut self, variable: usize, signals: &[S]) {
+    assert_eq!(signals.len(), self.variables[variable].width, "wrong width for {}", self.variables[variable].name);
+    let value: String = signals.iter().map(|&s| s.into().to_string()).collect();
+    if self.values[variable].as_ref() == Some(&value) {
+      return;
+    }
+    let time = self.time;
+    match self.changes.iter_mut().rev().take_while(|(t, _, _)| *t == time).find(|(_, v, _)| *v == variable) {
+        Some(change) => change.2 = value.clone(),
+        None => self.changes.push((time, variable, value.clone())),
+    }
+    self.values[variable] = Some(value);
+  }
+
+  // Two's complement of the integer on the width of the variable
+  pub fn set_int(&mut self, variable: usize, value: i64) {
+    let width = self.variables[variable].width;
+    let signals = to_signals_truncated::<64, _>(value, Order::MsbFirst);
+    let signals: Vec<Signal> = signals[64 - width.min(64)..].to_vec();
+    let padding = vec![if value < 0 { Signal::High } else { Signal::Low }; width.saturating_sub(64)];
+    self.set(variable, &[padding, signals].concat());
+  }
+
+  // Rising edge, starting a new cycle
+  pub fn tick(&mut self) {
+    if !self.changes.is_empty() {
+      self.time += 1;
+    }
+    self.set(self.clock, &[Signal::High]);
+  }
+
+  // Falling edge, in the middle of the cycle
+  pub fn tock(&mut self) {
+    self.time += 1;
+    self.set(self.clock, &[Signal::Low]);
+  }
+}
+
+impl fmt::Display for Tracer {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "$version nand_to_tetris $end")?;
+    writeln!(f, "$timescale {} $end", TIMESCALE)?;
+    writeln!(f, "$scope module {} $end", self.name)?;
+    // variables sorted by scope path, so that every scope is declared once, and in order of
+    // declaration within a scope
+    let mut order: Vec<usize> = (0..self.variables.len()).collect();
+    order.sort_by(|&a, &b| self.variables[a].scope.cmp(&self.variables[b].scope));
+    let mut scope: Vec<String> = vec![];
+    for &v in order.iter() {
+      let variable = &self.variables[v];
+      let common = scope.iter().zip(variable.scope.iter()).take_while(|(a, b)| a == b).count();
+      for _ in common..scope.len() {
+        writeln!(f, "$upscope $end")?;
+      }
+      for name in variable.scope[common..].iter() {
+        writeln!(f, "$scope module {} $end", name)?;
+      }
+      scope = variable.scope.clone();
+      match variable.width {
+          1 => writeln!(f, "$var wire 1 {} {} $end", identifier(v), variable.name)?,
+          width => writeln!(f, "$var wire {} {} {} [{}:0] $end", width, identifier(v), variable.name, width - 1)?,
+      }
+    }
+    for _ in 0..scope.len() + 1 {
+      writeln!(f, "$upscope $end")?;
+    }
+    writeln!(f, "$enddefinitions $end")?;
+    let value = |v: usize, value: &str| match self.variables[v].width {
+        1 => format!("{}{}", value, identifier(v)),
+        _ => format!("b{} {}", value, identifier(v)),
+    };
+    // every variable has a value at time 0, x when it was not set yet
+    writeln!(f, "#0\n$dumpvars")?;
+    for v in 0..self.variables.len() {
+      let initial = self.changes.iter().find(|(time, variable, _)| *time == 0 && *variable == v).map(|(_, _, value)| value.clone());
+      writeln!(f, "{}", value(v, &initial.unwrap_or_else(|| "x".repeat(self.variables[v].width))))?;
+    }
+    writeln!(f, "$end")?;
+    let mut time = 0;
+    for (t, v, change) in self.changes.iter().filter(|(time, _, _)| *time > 0) {
+      if *t != time {
+        writeln!(f, "#{}", t)?;
+        time = *t;
+      }
+      writeln!(f, "{}", value(*v, change))?;
+    }
+    // the end of the last cycle
+    writeln!(f, "#{}", self.time + 1)
+  }
+}
+
+// Runs a clocked chip on one input per cycle, tracing its flattened input and output pins
+pub fn trace_chip<C: Chip>(chip: &mut C, inputs: impl IntoIterator<Item = C::Input>) -> Tracer {
+  let mut tracer = Tracer::new(&chip.name().replace("::", "").replace(['<', '>'], ""));
+  let (input, output) = (tracer.add("in", C::Input::WIDTH), tracer.add("out", C::Output::WIDTH));
+  for pins in inputs {
+    tracer.tick();
+    let signals = pins.to_signals();
+    tracer.set(input, &signals);
+    let out = chip.compute(C::Input::from_signals(&signals));
+    tracer.set(output, &out.to_signals());
+    tracer.tock();
+  }
+  tracer
+}
+
+// Runs the computer for at most `cycles` instructions, tracing the registers, the flags of
+// the alu and the signals between the cpu and the memories with the names of the nand2tetris
+// CPU chip
+pub fn trace_computer(computer: &mut Computer, cycles: u64) -> Tracer {
+  let mut tracer = Tracer::new("computer");
+  let pc = tracer.add("cpu/pc", 16);
+  let instruction = tracer.add("cpu/instruction", 16);
+  let a = tracer.add("cpu/a", 16);
+  let d = tracer.add("cpu/d", 16);
+  let address = tracer.add("memory/addressM", 15);
+  let in_m = tracer.add("memory/inM", 16);
+  let out_m = tracer.add("memory/outM", 16);
+  let write_m = tracer.add("memory/writeM", 1);
+  let zr = tracer.add("cpu/alu/zr", 1);
+  let ng = tracer.add("cpu/alu/ng", 1);
+  let end = computer.cycles + cycles;
+  while computer.cycles < end && (computer.pc as usize) < computer.rom.len() {
+    let current = computer.rom[computer.pc as usize];
+    let location = computer.a as u16 as usize % RAM_SIZE;
+    let c_instruction = current >> 15 == 1;
+    let y = if current >> 12 & 1 == 1 { computer.ram[location] } else { computer.a };
+    tracer.tick();
+    tracer.set_int(pc, computer.pc as i64);
+    tracer.set_int(instruction, current as i64);
+    tracer.set_int(a, computer.a as i64);
+    tracer.set_int(d, computer.d as i64);
+    tracer.set_int(address, location as i64);
+    tracer.set_int(in_m, computer.ram[location] as i64);
+    // the alu computes on every instruction, outM is only written for C-instructions
+    let out = hack::compute(current, computer.d, y, computer.extended);
+    tracer.set_int(out_m, out as i64);
+    tracer.set(write_m, &[Signal::from(c_instruction && current >> 3 & 1 == 1)]);
+    tracer.set(zr, &[Signal::from(out == 0)]);
+    tracer.set(ng, &[Signal::from(out < 0)]);
+    computer.step();
+    tracer.tock();
+  }
+  tracer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip::{Bit, Register};
+    use crate::bits::bits;
+    use Signal::*;
+
+    #[test]
+    fn tidentifier() {
+        assert_eq!(identifier(0), "!");
+        assert_eq!(identifier(93), "~");
+        assert_eq!(identifier(94), "!!");
+        assert_eq!(identifier(95), "\"!");
+    }
+    #[test]
+    fn tbit() {
+        // load 1, keep it, load 0
+        let tracer = trace_chip(&mut Bit::default(), vec![(High, High), (Low, Low), (Low, High)]);
+        assert_eq!(tracer.to_string(), "$version nand_to_tetris $end\n$timescale 1 ns $end\n$scope module bit $end\n\
+            $var wire 1 ! clk $end\n$var wire 2 \" in [1:0] $end\n$var wire 1 # out $end\n$upscope $end\n$enddefinitions $end\n\
+            #0\n$dumpvars\n1!\nb11 \"\n0#\n$end\n#1\n0!\n#2\n1!\nb00 \"\n1#\n#3\n0!\n#4\n1!\nb01 \"\n#5\n0!\n#6\n");
+    }
+    #[test]
+    fn tscopes() {
+        let mut tracer = Tracer::new("top");
+        let (y, z, x) = (tracer.add("cpu/pc", 4), tracer.add("ram/out", 1), tracer.add("cpu/alu/x", 1));
+        let w = tracer.add("cpu/alu/zr", 1);
+        tracer.tick();
+        tracer.set(x, &[Level::X]);
+        tracer.set(x, &[Level::Z]);
+        tracer.set_int(y, -1);
+        tracer.set(z, &[High]);
+        tracer.tock();
+        tracer.set(z, &[High]);
+        tracer.set(w, &[Low]);
+        let vcd = tracer.to_string();
+        // cpu/alu declared after ram still nests in the one cpu scope
+        assert!(vcd.contains("$scope module top $end\n$var wire 1 ! clk $end\n$scope module cpu $end\n$var wire 4 \" pc [3:0] $end\n\
+            $scope module alu $end\n$var wire 1 $ x $end\n$var wire 1 % zr $end\n$upscope $end\n$upscope $end\n\
+            $scope module ram $end\n$var wire 1 # out $end\n$upscope $end\n$upscope $end\n"));
+        assert_eq!(vcd.matches("$scope module cpu $end").count(), 1);
+        assert!(vcd.ends_with("$dumpvars\n1!\nb1111 \"\n1#\nz$\nx%\n$end\n#1\n0!\n0%\n#2\n"));
+    }
+    #[test]
+    fn tregister() {
+        let inputs = vec![(bits("1010"), High), (bits("0110"), Low), (bits("0110"), High), (bits("0000"), Low)];
+        let vcd = trace_chip(&mut Register::<4>::default(), inputs).to_string();
+        assert!(vcd.contains("$var wire 5 \" in [4:0] $end\n$var wire 4 # out [3:0] $end\n"));
+        assert!(vcd.contains("#2\n1!\nb01100 \"\nb1010 #\n#3\n0!\n#4\n1!\nb01101 \"\n#5\n0!\n#6\n1!\nb00000 \"\nb0110 #\n"));
+    }
+    #[test]
+    fn tcomputer() {
+        let program = hack::assemble("@2\nD=A\n@3\nD=D+A\n@R0\nM=D", false).unwrap();
+        let mut computer = Computer::new(program, false);
+        let vcd = trace_computer(&mut computer, 100).to_string();
+        assert_eq!(computer.ram[0], 5);
+        assert!(vcd.contains("$scope module cpu $end\n$var wire 16 \" pc [15:0] $end\n"));
+        assert!(vcd.contains("$var wire 1 ) writeM $end\n"));
+        assert!(vcd.contains("$scope module alu $end\n$var wire 1 * zr $end\n$var wire 1 + ng $end\n"));
+        // outM and the flags follow the alu on every instruction: D=A clears zr, and @R0
+        // computes D&A with its zero control bits
+        assert!(vcd.contains("#2\n1!\nb0000000000000001 \"\nb1110110000010000 #\nb0000000000000010 $\nb000000000000010 &\nb0000000000000010 (\n0*\n"));
+        assert!(vcd.contains("#8\n1!\nb0000000000000100 \"\nb0000000000000000 #\nb0000000000000101 %\nb0000000000000001 (\n"));
+        // the last cycle, M=D with A = 0, writes 5 to RAM[0]
+        assert!(vcd.ends_with("#10\n1!\nb0000000000000101 \"\nb1110001100001000 #\nb0000000000000000 $\n\
+            b000000000000000 &\nb0000000000000101 (\n1)\n#11\n0!\n#12\n"));
+    }
+}
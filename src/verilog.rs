@@ -0,0 +1,428 @@
+use crate::gates::*;
+use crate::netlist::{self, Gate, Netlist, Port, Wire};
+
+// Structural Verilog of netlists, to synthesize the chips and to cross-check the simulation
+// with Icarus or Verilator. Every nand is a gate primitive and every scope a module
+// instantiated by its parent, scopes with the same gates sharing one module. Scopes know
+// their wires but not their pin names, so the ports of a subchip module are an `in` and an
+// `out` vector in wire order, while the top module has the ports of the netlist, msb first.
+// A subchip with an output nothing reads or with two inputs on one wire, as the muxes of the
+// alu on constants, has fewer ports and gets a variant module suffixed _2, _3...
+// Netlists are combinational, sequential chips are not exported from them: the flip flops
+// only come with the fixed Dff, Bit and Register modules written by `register`.
+
+// Verilog identifier of a chip, in the camel case of the nand2tetris chip names, which also
+// keeps not, and, xor and the other gate names from clashing with the primitives
+pub fn module_name(name: &str) -> String {
+  name.split(|c: char| !c.is_ascii_alphanumeric())
+    .filter(|word| !word.is_empty())
+    .map(|word| word[..1].to_uppercase() + &word[1..])
+    .collect()
+}
+
+struct Exporter<'a> {
+    netlist: &'a Netlist,
+    children: Vec<Vec<usize>>,
+    // Nands of every scope, not counting the ones of its subchips
+    nands: Vec<Vec<Wire>>,
+    readers: Vec<Vec<Wire>>,
+    // Wires of the output ports
+    exported: Vec<bool>,
+    // Name and text after the name of every module, dependencies first
+    modules: Vec<(String, String)>,
+}
+
+impl<'a> Exporter<'a> {
+  fn new(netlist: &'a Netlist) -> Exporter<'a> {
+    let mut children = vec![vec![]; netlist.scopes.len()];
+    for (scope, s) in netlist.scopes.iter().enumerate() {
+      if let Some(parent) = s.parent {
+        children[parent].push(scope);
+      }
+    }
+    let mut nands = vec![vec![]; netlist.scopes.len()];
+    let mut readers = vec![vec![]; netlist.nodes.len()];
+    for (wire, node) in netlist.nodes.iter().enumerate() {
+      if let Gate::Nand(in1, in2) = node.gate {
+        nands[node.scope].push(wire);
+        readers[in1].push(wire);
+        readers[in2].push(wire);
+      }
+    }
+    let mut exported = vec![false; netlist.nodes.len()];
+    for &wire in netlist.outputs.iter().flat_map(|port| port.wires.iter()) {
+      exported[wire] = true;
+    }
+    Exporter { netlist, children, nands, readers, exported, modules: vec![] }
+  }
+
+  // Nands of the scope and its subchips, in wire order
+  fn subtree(&self, scope: usize) -> Vec<Wire> {
+    let mut wires = self.nands[scope].clone();
+    for &child in self.children[scope].iter() {
+      wires.extend(self.subtree(child));
+    }
+    wires.sort_unstable();
+    wires
+  }
+
+  // The child of `scope` containing `inner`, one of the scopes below it
+  fn child(&self, mut inner: usize, scope: usize) -> usize {
+    while self.netlist.scopes[inner].parent != Some(scope) {
+      inner = self.netlist.scopes[inner].parent.unwrap();
+    }
+    inner
+  }
+
+  // Statements of the module of the scope and the number of wires it drives, named w[0],
+  // w[1]... `names` holds the names of the wires read from outside the scope.
+  fn body(&mut self, scope: usize, names: &mut [String]) -> (usize, Vec<String>) {
+    let mut wires = 0;
+    let mut gates = vec![];
+    let mut instances = vec![];
+    let mut seen = vec![];
+    for wire in self.subtree(scope) {
+      let inner = self.netlist.nodes[wire].scope;
+      if inner == scope {
+        names[wire] = format!("w[{}]", wires);
+        wires += 1;
+        gates.push(wire);
+        continue;
+      }
+      let child = self.child(inner, scope);
+      if seen.contains(&child) {
+        continue;
+      }
+      seen.push(child);
+      // a subchip driving nothing outside is dead logic and left out
+      if let Some((name, inputs, outputs)) = self.module(child) {
+        for &output in outputs.iter() {
+          names[output] = format!("w[{}]", wires);
+          wires += 1;
+        }
+        instances.push((name, inputs, outputs));
+      }
+    }
+    let list = |wires: &[Wire], names: &[String]| wires.iter().map(|&wire| names[wire].clone()).collect::<Vec<String>>().join(", ");
+    let mut lines: Vec<String> = gates.iter().map(|&wire| match self.netlist.nodes[wire].gate {
+        Gate::Nand(in1, in2) => format!("  nand ({}, {}, {});", names[wire], names[in1], names[in2]),
+        _ => unreachable!(),
+    }).collect();
+    for (k, (name, inputs, outputs)) in instances.iter().enumerate() {
+      lines.push(format!("  {} u{} (.in({{{}}}), .out({{{}}}));", name, k, list(inputs, names), list(outputs, names)));
+    }
+    (wires, lines)
+  }
+
+  // Module of a subchip with the wires of its in and out ports, None when it has no outputs
+  fn module(&mut self, scope: usize) -> Option<(String, Vec<Wire>, Vec<Wire>)> {
+    let nodes = self.subtree(scope);
+    let mut inside = vec![false; self.netlist.nodes.len()];
+    for &wire in nodes.iter() {
+      inside[wire] = true;
+    }
+    let mut inputs: Vec<Wire> = nodes.iter().flat_map(|&wire| match self.netlist.nodes[wire].gate {
+        Gate::Nand(in1, in2) => vec![in1, in2],
+        _ => vec![],
+    }).filter(|&wire| !inside[wire]).collect();
+    inputs.sort_unstable();
+    inputs.dedup();
+    let outputs: Vec<Wire> = nodes.iter().copied()
+      .filter(|&wire| self.exported[wire] || self.readers[wire].iter().any(|&reader| !inside[reader]))
+      .collect();
+    if outputs.is_empty() {
+      return None;
+    }
+    let mut names = vec![String::new(); self.netlist.nodes.len()];
+    for (k, &wire) in inputs.iter().enumerate() {
+      names[wire] = format!("in[{}]", k);
+    }
+    let (wires, lines) = self.body(scope, &mut names);
+    let mut text = format!("(\n  input [0:{}] in,\n  output [0:{}] out\n);\n  wire [0:{}] w;\n", inputs.len() - 1, outputs.len() - 1, wires - 1);
+    for line in lines {
+      text.push_str(&line);
+      text.push('\n');
+    }
+    let outs: Vec<String> = outputs.iter().map(|&wire| names[wire].clone()).collect();
+    text.push_str(&format!("  assign out = {{{}}};\nendmodule\n", outs.join(", ")));
+    let name = self.register(&module_name(&self.netlist.scopes[scope].name), text);
+    Some((name, inputs, outputs))
+  }
+
+  // Name of the module with this text, a new one suffixed _2, _3... when the name is taken
+  fn register(&mut self, base: &str, text: String) -> String {
+    for n in 1.. {
+      let name = match n {
+          1 => base.to_string(),
+          _ => format!("{}_{}", base, n),
+      };
+      match self.modules.iter().find(|(other, _)| *other == name) {
+          Some((_, other)) if *other == text => return name,
+          Some(_) => (),
+          None => {
+            self.modules.push((name.clone(), text));
+            return name;
+          },
+      }
+    }
+    unreachable!()
+  }
+}
+
+// Names of the input then output ports, an output called like an input, as the carry of
+// full_adder, getting an _out suffix
+fn port_names(netlist: &Netlist) -> Vec<String> {
+  let inputs: Vec<String> = netlist.inputs.iter().map(|port| port.name.clone()).collect();
+  let outputs = netlist.outputs.iter().map(|port| match inputs.contains(&port.name) {
+      true => format!("{}_out", port.name),
+      false => port.name.clone(),
+  });
+  inputs.iter().cloned().chain(outputs).collect()
+}
+
+fn declaration(kind: &str, name: &str, port: &Port) -> String {
+  match port.wires.len() {
+      1 => format!("  {} {}", kind, name),
+      width => format!("  {} [{}:0] {}", kind, width - 1, name),
+  }
+}
+
+fn literal(signals: &[Signal]) -> String {
+  let bits: String = signals.iter().map(|s| s.to_string()).collect();
+  format!("{}'b{}", signals.len(), bits)
+}
+
+// Modules of the netlist, its subchips first and the top module, named after the chip, last
+pub fn modules(netlist: &Netlist) -> String {
+  let mut exporter = Exporter::new(netlist);
+  let top = module_name(&netlist.name);
+  // reserved, a subchip called like the chip gets another name
+  exporter.modules.push((top.clone(), String::new()));
+  let mut names = vec![String::new(); netlist.nodes.len()];
+  for port in netlist.inputs.iter() {
+    let width = port.wires.len();
+    for (i, &wire) in port.wires.iter().enumerate() {
+      names[wire] = match width {
+          1 => port.name.clone(),
+          _ => format!("{}[{}]", port.name, width - 1 - i),
+      };
+    }
+  }
+  for (wire, node) in netlist.nodes.iter().enumerate() {
+    if let Gate::Constant(signal) = node.gate {
+      names[wire] = literal(&[signal]);
+    }
+  }
+  let (wires, lines) = exporter.body(0, &mut names);
+  let pins = port_names(netlist);
+  let (input_names, output_names) = pins.split_at(netlist.inputs.len());
+  let ports: Vec<String> = netlist.inputs.iter().zip(input_names.iter()).map(|(port, name)| declaration("input", name, port))
+    .chain(netlist.outputs.iter().zip(output_names.iter()).map(|(port, name)| declaration("output", name, port)))
+    .collect();
+  let mut text = format!("(\n{}\n);\n", ports.join(",\n"));
+  if wires > 0 {
+    text.push_str(&format!("  wire [0:{}] w;\n", wires - 1));
+  }
+  for line in lines {
+    text.push_str(&line);
+    text.push('\n');
+  }
+  for (port, name) in netlist.outputs.iter().zip(output_names.iter()) {
+    let bits: Vec<String> = port.wires.iter().map(|&wire| names[wire].clone()).collect();
+    match bits.len() {
+        1 => text.push_str(&format!("  assign {} = {};\n", name, bits[0])),
+        _ => text.push_str(&format!("  assign {} = {{{}}};\n", name, bits.join(", "))),
+    }
+  }
+  text.push_str("endmodule\n");
+  exporter.modules.remove(0);
+  exporter.modules.push((top, text));
+  exporter.modules.iter().map(|(name, text)| format!("module {} {}", name, text)).collect::<Vec<String>>().join("\n")
+}
+
+// The flip flop of the Hack platform, out(t) = in(t - 1), Low at power up like chip::Dff
+pub const DFF: &str = "module Dff (
+  input clk,
+  input in,
+  output reg out
+);
+  initial out = 1'b0;
+  always @(posedge clk) out <= in;
+endmodule
+";
+
+// Registers as in chip.rs, from a fixed template rather than a netlist: Dff, the Bit keeping
+// its value until load with the exported nand Mux, and a Register of `bits` Bits
+pub fn register(bits: usize) -> String {
+  assert!(bits > 0, "register::<{}> has no bits", bits);
+  let bit = "module Bit (
+  input clk,
+  input in,
+  input load,
+  output out
+);
+  wire next;
+  Mux mux (.in1(out), .in2(in), .sel(load), .out(next));
+  Dff dff (.clk(clk), .in(next), .out(out));
+endmodule
+";
+  let register = format!("module Register{bits} (
+  input clk,
+  input [{msb}:0] in,
+  input load,
+  output [{msb}:0] out
+);
+  genvar i;
+  generate
+    for (i = 0; i < {bits}; i = i + 1) begin : bits
+      Bit b (.clk(clk), .in(in[i]), .load(load), .out(out[i]));
+    end
+  endgenerate
+endmodule
+", bits = bits, msb = bits - 1);
+  [DFF.to_string(), modules(&netlist::mux()), bit.to_string(), register].join("\n")
+}
+
+// Testbench applying the vectors to the top module of `modules` and comparing its outputs
+// with the expected ones, printing every mismatch, then PASSED or FAILED. The expected
+// outputs of a .cmp file check the Verilog independently of this crate, the ones of
+// Netlist::evaluate check the simulators against each other.
+pub fn testbench(netlist: &Netlist, vectors: &[Vec<Signal>], expected: &[Vec<Signal>]) -> String {
+  assert_eq!(vectors.len(), expected.len(), "one expected output per vector");
+  let top = module_name(&netlist.name);
+  let mut text = "`timescale 1ns / 1ns\n".to_string();
+  text.push_str(&format!("module {}Test;\n", top));
+  let pins = port_names(netlist);
+  let (input_names, output_names) = pins.split_at(netlist.inputs.len());
+  for (port, name) in netlist.inputs.iter().zip(input_names.iter()) {
+    text.push_str(&format!("{};\n", declaration("reg", name, port)));
+  }
+  for (port, name) in netlist.outputs.iter().zip(output_names.iter()) {
+    text.push_str(&format!("{};\n", declaration("wire", name, port)));
+  }
+  text.push_str("  integer errors = 0;\n\n");
+  let connections: Vec<String> = pins.iter().map(|name| format!(".{0}({0})", name)).collect();
+  text.push_str(&format!("  {} chip ({});\n\n  initial begin\n", top, connections.join(", ")));
+  let outputs = format!("{{{}}}", output_names.join(", "));
+  for (n, (vector, expected)) in vectors.iter().zip(expected.iter()).enumerate() {
+    assert_eq!(expected.len(), netlist.output_width(), "wrong number of outputs for {}", netlist.name);
+    let mut signals = vector.iter().copied();
+    for (port, name) in netlist.inputs.iter().zip(input_names.iter()) {
+      let bits: Vec<Signal> = signals.by_ref().take(port.wires.len()).collect();
+      text.push_str(&format!("    {} = {};\n", name, literal(&bits)));
+    }
+    let expected = literal(expected);
+    text.push_str(&format!("    #1 if ({} !== {}) begin\n", outputs, expected));
+    text.push_str("      errors = errors + 1;\n");
+    text.push_str(&format!("      $display(\"vector {}: expected %b, got %b\", {}, {});\n    end\n", n, expected, outputs));
+  }
+  text.push_str("    if (errors == 0)\n");
+  text.push_str(&format!("      $display(\"PASSED {} vectors\");\n    else\n", vectors.len()));
+  text.push_str(&format!("      $display(\"FAILED %0d of {} vectors\", errors);\n", vectors.len()));
+  text.push_str("    $finish;\n  end\nendmodule\n");
+  text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netlist::Builder;
+    use crate::atpg;
+    use Signal::*;
+    use std::fs;
+    use std::process::Command;
+
+    #[test]
+    fn tmodule_name() {
+        assert_eq!(module_name("xor"), "Xor");
+        assert_eq!(module_name("alu::<16>"), "Alu16");
+        assert_eq!(module_name("mux_8_way_n::<16>"), "Mux8WayN16");
+    }
+    #[test]
+    fn txor() {
+        assert_eq!(modules(&netlist::xor()), "module Xor_2 (\n  input [0:1] in,\n  output [0:0] out\n);\n  wire [0:4] w;\n\
+            \x20 nand (w[0], in[0], in[1]);\n  nand (w[1], w[0], in[0]);\n  nand (w[2], in[0], in[1]);\n  nand (w[3], w[2], in[1]);\n\
+            \x20 nand (w[4], w[1], w[3]);\n  assign out = {w[4]};\nendmodule\n\nmodule Xor (\n  input in1,\n  input in2,\n  output out\n);\n\
+            \x20 wire [0:0] w;\n  Xor_2 u0 (.in({in1, in2}), .out({w[0]}));\n  assign out = w[0];\nendmodule\n");
+    }
+    #[test]
+    fn thierarchy() {
+        // one module per distinct subchip, the 16 full adders of the alu share 3, ng is the msb
+        // of out
+        let alu = modules(&netlist::alu::<16>());
+        assert_eq!(alu.matches("endmodule").count(), 20);
+        assert_eq!(alu.matches("module FullAdder").count(), 3);
+        assert!(alu.ends_with("  assign out = {w[0], w[1], w[2], w[3], w[4], w[5], w[6], w[7], w[8], w[9], w[10], w[11], w[12], w[13], w[14], w[15]};\n\
+            \x20 assign zr = w[16];\n  assign ng = w[0];\nendmodule\n"));
+        // inputs and outputs with the same name, constants and output ports on inputs
+        assert!(modules(&netlist::full_adder()).contains("  input carry,\n  output sum,\n  output carry_out\n"));
+        let mut b = Builder::new("wiring");
+        let (a, x) = (b.input("a"), b.input_bus::<2>("x"));
+        let (low, high) = (b.constant(Low), b.constant(High));
+        let and = b.and(a, high);
+        b.output_bus("out", &[x[1], low, and, x[0]]);
+        let wiring = b.build();
+        assert_eq!(modules(&wiring), "module And (\n  input [0:1] in,\n  output [0:0] out\n);\n  wire [0:2] w;\n  nand (w[0], in[0], in[1]);\n\
+            \x20 nand (w[1], in[0], in[1]);\n  nand (w[2], w[0], w[1]);\n  assign out = {w[2]};\nendmodule\n\nmodule Wiring (\n  input a,\n\
+            \x20 input [1:0] x,\n  output [3:0] out\n);\n  wire [0:0] w;\n  And u0 (.in({a, 1'b1}), .out({w[0]}));\n\
+            \x20 assign out = {x[0], 1'b0, w[0], x[1]};\nendmodule\n");
+    }
+    #[test]
+    fn tregister() {
+        let verilog = register(16);
+        assert!(verilog.starts_with(DFF));
+        assert!(verilog.contains("module Mux (\n"));
+        assert!(verilog.contains("module Register16 (\n  input clk,\n  input [15:0] in,\n"));
+    }
+    #[test]
+    #[should_panic(expected = "register::<0> has no bits")]
+    fn tregister_width() {
+        register(0);
+    }
+    #[test]
+    fn ttestbench() {
+        let and = netlist::and();
+        let vectors = vec![vec![Low, High], vec![High, High]];
+        assert_eq!(testbench(&and, &vectors, &[vec![Low], vec![High]]), "`timescale 1ns / 1ns\nmodule AndTest;\n  reg in1;\n  reg in2;\n\
+            \x20 wire out;\n  integer errors = 0;\n\n  And chip (.in1(in1), .in2(in2), .out(out));\n\n  initial begin\n    in1 = 1'b0;\n    in2 = 1'b1;\n\
+            \x20   #1 if ({out} !== 1'b0) begin\n      errors = errors + 1;\n      $display(\"vector 0: expected %b, got %b\", 1'b0, {out});\n    end\n\
+            \x20   in1 = 1'b1;\n    in2 = 1'b1;\n    #1 if ({out} !== 1'b1) begin\n      errors = errors + 1;\n\
+            \x20     $display(\"vector 1: expected %b, got %b\", 1'b1, {out});\n    end\n    if (errors == 0)\n      $display(\"PASSED 2 vectors\");\n\
+            \x20   else\n      $display(\"FAILED %0d of 2 vectors\", errors);\n    $finish;\n  end\nendmodule\n");
+        // expectations of a .cmp file are taken as they are, right or wrong
+        assert!(testbench(&and, &vectors, &[vec![High], vec![High]]).contains("    #1 if ({out} !== 1'b1) begin\n      errors = errors + 1;\n\
+            \x20     $display(\"vector 0: expected %b, got %b\", 1'b1, {out});\n"));
+        let alu = netlist::alu::<16>();
+        let vectors = vec![vec![High; 38]];
+        let bench = testbench(&alu, &vectors, &[alu.evaluate(&vectors[0])]);
+        assert!(bench.contains("  reg [15:0] bits1;\n"));
+        assert!(bench.contains("    #1 if ({out, zr, ng} !== 18'b"));
+    }
+    // Compiles the modules and testbenches of a few chips with Icarus Verilog and runs them,
+    // when IVERILOG is set: IVERILOG=1 cargo test tiverilog, with iverilog and vvp on the path
+    #[test]
+    fn tiverilog() {
+        if std::env::var_os("IVERILOG").is_none() {
+            return;
+        }
+        let directory = std::env::temp_dir().join(format!("nand_to_tetris_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let compile = |name: &str, source: String| {
+            let (path, simulation) = (directory.join(format!("{}.v", name)), directory.join(name));
+            fs::write(&path, source).unwrap();
+            let status = Command::new("iverilog").arg("-o").arg(&simulation).arg(&path).status().expect("cannot run iverilog");
+            assert!(status.success(), "iverilog failed on {}", path.display());
+            simulation
+        };
+        for chip in [netlist::full_adder(), netlist::mux_8_way_n::<16>(), netlist::alu::<16>()].iter() {
+            let vectors = atpg::generate(chip).vectors;
+            let expected: Vec<Vec<Signal>> = vectors.iter().map(|vector| chip.evaluate(vector)).collect();
+            let simulation = compile(&module_name(&chip.name), format!("{}\n{}", testbench(chip, &vectors, &expected), modules(chip)));
+            let output = Command::new("vvp").arg(&simulation).output().expect("cannot run vvp");
+            let output = String::from_utf8_lossy(&output.stdout);
+            assert!(output.contains(&format!("PASSED {} vectors", vectors.len())), "{}:\n{}", chip.name, output);
+        }
+        compile("Register16", register(16));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}